@@ -6,10 +6,18 @@ use std::{
 
 use num::FromPrimitive;
 
-use crate::{cart::header::CartHeader, mem::Addr, util::slice::copy_from_safe};
+use crate::{
+    cart::header::CartHeader,
+    mem::Addr,
+    util::{
+        slice::copy_from_safe,
+        state::{SaveState, StateReader, StateWriter},
+    },
+};
 
 use super::{
     cart_hw::CartHw,
+    consts::{RAM_BANK_SIZE, ROM_BANK_SIZE},
     hw_mbc1::HwMbc1,
     hw_mbc3::HwMbc3,
     hw_mbc5::HwMbc5,
@@ -21,16 +29,19 @@ use super::{
 pub struct Cart {
     header: CartHeader,
     hw: Box<dyn CartHw>,
+
+    /// Set when cartridge RAM is written to, until the RAM is saved.
+    is_ram_dirty: bool,
 }
 
 impl Cart {
     /// Attempts to load a gb file at the given `file_path` and create a new `Cart` instance.
-    pub fn load_from(file_path: &str, verbose: bool) -> Result<Self, String> {
-        let path = Path::new(file_path);
+    pub fn load_from(file_path: impl AsRef<Path>, verbose: bool) -> Result<Self, String> {
+        let path = file_path.as_ref();
         let Some(ext) = path.extension() else {
             return Err(format!(
                 "File extension for file {} wasn't specified.",
-                file_path
+                path.display()
             ));
         };
 
@@ -40,8 +51,8 @@ impl Cart {
             ));
         }
 
-        let Ok(rom) = fs::read(file_path) else {
-            return Err(format!("Unable to read file {}.", file_path));
+        let Ok(rom) = fs::read(path) else {
+            return Err(format!("Unable to read file {}.", path.display()));
         };
 
         let cart_type_id = rom[0x0147];
@@ -63,7 +74,11 @@ impl Cart {
 
         let hw = Self::create_hw(&header, &rom);
 
-        return Ok(Self { header, hw });
+        return Ok(Self {
+            header,
+            hw,
+            is_ram_dirty: false,
+        });
     }
 
     /// Creates the specific cartridge hardware implementation for the cartridge type
@@ -95,7 +110,74 @@ impl Cart {
     }
 
     pub fn write(&mut self, addr: Addr, data: u8) {
-        self.hw.write(addr, data);
+        if self.hw.write(addr, data) {
+            self.is_ram_dirty = true;
+        }
+    }
+
+    /// Returns the cartridge hardware to its power-on state, keeping RAM.
+    pub fn reset(&mut self) {
+        self.hw.reset();
+    }
+
+    /// The ROM bank mapped at $4000-$7FFF.
+    pub fn rom_bank(&self) -> usize {
+        self.hw.rom_bank()
+    }
+
+    pub fn rom_size(&self) -> usize {
+        self.header.rom_bank_count * ROM_BANK_SIZE
+    }
+
+    /// Where the ROM byte mapped at `addr` ($0000-$7FFF) is in the ROM.
+    pub fn rom_offset(&self, addr: Addr) -> usize {
+        let bank = if addr < 0x4000 { 0 } else { self.rom_bank() };
+        return self.banked_rom_offset(bank, addr);
+    }
+
+    /// Where the byte at `addr` in ROM bank `bank` is in the ROM.
+    pub(crate) fn banked_rom_offset(&self, bank: usize, addr: Addr) -> usize {
+        (bank * ROM_BANK_SIZE + addr as usize % ROM_BANK_SIZE) % self.rom_size()
+    }
+
+    /// Reads the byte at `addr` in ROM bank `bank`, mapped or not.
+    pub fn peek_rom(&self, bank: usize, addr: Addr) -> u8 {
+        self.hw.rom()[self.banked_rom_offset(bank, addr)]
+    }
+
+    /// Changes the byte at `addr` in ROM bank `bank`, for debuggers patching
+    /// code.
+    pub fn poke_rom(&mut self, bank: usize, addr: Addr, data: u8) {
+        let offset = self.banked_rom_offset(bank, addr);
+        self.hw.rom_mut()[offset] = data;
+    }
+
+    pub fn ram_bank_count(&self) -> usize {
+        self.ram().len().div_ceil(RAM_BANK_SIZE)
+    }
+
+    /// Reads the byte at `addr` ($A000-$BFFF) in RAM bank `bank`, whether
+    /// it's mapped or RAM is enabled or not. $FF if there's no such bank.
+    pub fn peek_ram(&self, bank: usize, addr: Addr) -> u8 {
+        let offset = bank * RAM_BANK_SIZE + addr as usize % RAM_BANK_SIZE;
+        return *self.ram().get(offset).unwrap_or(&0xFF);
+    }
+
+    /// Changes the byte at `addr` ($A000-$BFFF) in RAM bank `bank`.
+    pub fn poke_ram(&mut self, bank: usize, addr: Addr, data: u8) {
+        let offset = bank * RAM_BANK_SIZE + addr as usize % RAM_BANK_SIZE;
+        if let Some(byte) = self.ram_mut().get_mut(offset) {
+            *byte = data;
+            self.is_ram_dirty = true;
+        }
+    }
+
+    pub fn is_ram_dirty(&self) -> bool {
+        self.is_ram_dirty
+    }
+
+    pub fn clear_ram_dirty(&mut self) {
+        self.is_ram_dirty = false;
     }
 
     pub fn ram(&self) -> &[u8] {
@@ -110,3 +192,17 @@ impl Cart {
         &self.header
     }
 }
+
+impl SaveState for Cart {
+    fn save_state(&self, w: &mut StateWriter) {
+        self.hw.save_state(w);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.hw.load_state(r)?;
+
+        // The restored RAM may not match the battery save anymore.
+        self.is_ram_dirty = !self.ram().is_empty();
+        return Ok(());
+    }
+}
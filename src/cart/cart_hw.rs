@@ -1,11 +1,22 @@
-use crate::mem::Addr;
+use crate::{mem::Addr, util::state::SaveState};
 
 /// Functionality that any cartridge type (ROM-only, MBC1, etc.) must provide.
-pub trait CartHw {
+/// The saved state covers RAM and banking registers, but not ROM.
+pub trait CartHw: SaveState {
+    fn rom(&self) -> &[u8];
     fn rom_mut(&mut self) -> &mut [u8];
     fn ram(&self) -> &[u8];
     fn ram_mut(&mut self) -> &mut [u8];
 
+    /// Returns the banking registers to their power-on values. RAM is kept,
+    /// like it is on a cartridge with a battery.
+    fn reset(&mut self);
+
+    /// The ROM bank mapped at $4000-$7FFF.
+    fn rom_bank(&self) -> usize;
+
     fn read(&self, addr: Addr) -> u8;
-    fn write(&mut self, addr: Addr, data: u8);
+
+    /// Returns whether the write stored to RAM.
+    fn write(&mut self, addr: Addr, data: u8) -> bool;
 }
@@ -3,11 +3,12 @@ use num::FromPrimitive;
 use crate::{
     cart::consts::{RAM_BANK_SIZE, ROM_BANK_SIZE},
     other::mode::CompatibilityMode,
+    util::state::{StateReader, StateWriter},
 };
 
 use super::type_::CartType;
 
-const NINTENDO_LOGO: &[u8] = include_bytes!("..\\..\\assets\\files\\nintendo_logo.txt");
+const NINTENDO_LOGO: &[u8] = include_bytes!("../../assets/files/nintendo_logo.txt");
 
 const CGB_FLAG_BACKWARD_COMPATIBILE: u8 = 0x80;
 const CGB_FLAG_CGB_ONLY: u8 = 0xC0;
@@ -22,6 +23,10 @@ pub struct CartHeader {
     pub is_nintendo_logo_matching: bool,
     pub checksum: u8,
     pub is_checksum_matching: bool,
+
+    /// Big-endian sum of all ROM bytes except these two. Not verified by
+    /// the hardware, but useful for telling ROMs apart.
+    pub global_checksum: u16,
 }
 
 impl CartHeader {
@@ -73,6 +78,8 @@ impl CartHeader {
 
         let (checksum, is_matching) = check_header_checksum(rom);
 
+        let global_checksum = ((rom[0x14E] as u16) << 8) | (rom[0x14F] as u16);
+
         return Ok(Self {
             title,
             cgb_flag,
@@ -82,6 +89,7 @@ impl CartHeader {
             is_nintendo_logo_matching,
             checksum,
             is_checksum_matching: is_matching,
+            global_checksum,
         });
     }
 
@@ -93,6 +101,30 @@ impl CartHeader {
         }
     }
 
+    /// Writes the title and global checksum, which identify the game that a
+    /// save state or movie file was made with.
+    pub fn write_id(&self, w: &mut StateWriter) {
+        w.write_bytes(self.title().as_bytes());
+        w.write_u16(self.global_checksum);
+    }
+
+    /// Checks that an ID written by `write_id` matches this game.
+    pub fn check_id(&self, r: &mut StateReader) -> Result<(), String> {
+        let title = String::from_utf8_lossy(r.read_bytes()?).into_owned();
+        let global_checksum = r.read_u16()?;
+        if title != self.title() || global_checksum != self.global_checksum {
+            return Err(format!(
+                "made with {} (checksum {:0>4X}), but running {} (checksum {:0>4X}).",
+                title,
+                global_checksum,
+                self.title(),
+                self.global_checksum
+            ));
+        }
+
+        return Ok(());
+    }
+
     pub fn compatibility_mode(&self) -> CompatibilityMode {
         match self.cgb_flag {
             CGB_FLAG_BACKWARD_COMPATIBILE => CompatibilityMode::CgbBackward,
@@ -137,6 +169,7 @@ impl CartHeader {
             "  Checksum ({:#02x}) Matches: {}",
             self.checksum, self.is_checksum_matching
         );
+        println!("  Global Checksum: {:#06x}", self.global_checksum);
 
         println!();
     }
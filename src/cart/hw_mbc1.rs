@@ -1,6 +1,12 @@
 use num::FromPrimitive;
 
-use crate::{mem::Addr, util::bits::Bits};
+use crate::{
+    mem::Addr,
+    util::{
+        bits::Bits,
+        state::{SaveState, StateReader, StateWriter},
+    },
+};
 
 use super::{
     cart_hw::CartHw,
@@ -66,6 +72,10 @@ impl HwMbc1 {
 }
 
 impl CartHw for HwMbc1 {
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
     fn rom_mut(&mut self) -> &mut [u8] {
         &mut self.rom
     }
@@ -78,6 +88,17 @@ impl CartHw for HwMbc1 {
         &mut self.ram
     }
 
+    fn reset(&mut self) {
+        self.ram_enable = false;
+        self.bank_sel_lower_5 = 0;
+        self.bank_sel_upper_2 = 0;
+        self.mode_sel = Mode::RamBanking;
+    }
+
+    fn rom_bank(&self) -> usize {
+        self.rom_bank_sel() as usize
+    }
+
     // todo cleanup
     fn read(&self, addr: Addr) -> u8 {
         match addr {
@@ -115,7 +136,7 @@ impl CartHw for HwMbc1 {
     }
 
     // todo cleanup
-    fn write(&mut self, addr: Addr, data: u8) {
+    fn write(&mut self, addr: Addr, data: u8) -> bool {
         match addr {
             0x0000..=0x1FFF => {
                 self.ram_enable = data.bits(3, 0) == 0xA;
@@ -139,6 +160,7 @@ impl CartHw for HwMbc1 {
 
                     if let Some(val) = self.ram.get_mut(addr) {
                         *val = data;
+                        return true;
                     }
                 }
             }
@@ -146,6 +168,28 @@ impl CartHw for HwMbc1 {
                 panic!("Invalid MBC1 write address");
             }
         }
+        return false;
+    }
+}
+
+impl SaveState for HwMbc1 {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bytes(&self.ram);
+        w.write_bool(self.ram_enable);
+        w.write_u8(self.bank_sel_lower_5);
+        w.write_u8(self.bank_sel_upper_2);
+        w.write_u8(self.mode_sel as u8);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        r.read_bytes_into(&mut self.ram)?;
+        self.ram_enable = r.read_bool()?;
+        self.bank_sel_lower_5 = r.read_u8()?;
+        self.bank_sel_upper_2 = r.read_u8()?;
+        let mode_sel = r.read_u8()?;
+        self.mode_sel = Mode::from_u8(mode_sel)
+            .ok_or(format!("Invalid MBC1 banking mode in state: {}.", mode_sel))?;
+        return Ok(());
     }
 }
 
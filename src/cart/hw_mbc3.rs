@@ -1,10 +1,19 @@
-use crate::{mem::Addr, util::bits::Bits};
+use crate::{
+    mem::Addr,
+    util::{
+        bits::Bits,
+        state::{SaveState, StateReader, StateWriter},
+    },
+};
 
 use super::{
     cart_hw::CartHw,
     consts::{RAM_BANK_SIZE, ROM_BANK_SIZE},
 };
 
+/// The lowest RAM bank select value that selects an RTC register.
+const RTC_REG_SEL: u8 = 0x08;
+
 /// MBC3 cartridge hardware. Features 2MB ROM and/or 64KB RAM, and Timer.
 pub struct HwMbc3 {
     rom: Vec<u8>,
@@ -37,12 +46,17 @@ impl HwMbc3 {
         self.rom_bank_sel
     }
 
+    /// $08-$0C select the RTC registers instead of a RAM bank.
     pub fn ram_bank_sel(&self) -> u8 {
-        self.ram_rtc_register // todo
+        self.ram_bank_rtc_reg_sel
     }
 }
 
 impl CartHw for HwMbc3 {
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
     fn rom_mut(&mut self) -> &mut [u8] {
         &mut self.rom
     }
@@ -55,6 +69,17 @@ impl CartHw for HwMbc3 {
         &mut self.ram
     }
 
+    fn reset(&mut self) {
+        self.rom_bank_sel = 0;
+        self.ram_timer_enable = false;
+        self.ram_rtc_register = 0;
+        self.ram_bank_rtc_reg_sel = 0;
+    }
+
+    fn rom_bank(&self) -> usize {
+        self.rom_bank_sel() as usize
+    }
+
     fn read(&self, addr: Addr) -> u8 {
         match addr {
             0x0000..=0x3FFF => self.rom[addr as usize],
@@ -70,15 +95,12 @@ impl CartHw for HwMbc3 {
                 self.rom[addr]
             }
             0xA000..=0xBFFF => {
-                // RAM Bank 01-7F
-                let rel_addr = addr - 0x4000;
+                // RAM Bank 00-07. The RTC registers read as 0.
+                let rel_addr = addr - 0xA000;
                 let bank_sel = self.ram_bank_sel() as usize;
                 let bank_offs = bank_sel * RAM_BANK_SIZE;
                 let addr = bank_offs + (rel_addr as usize);
-                if addr >= self.rom.len() {
-                    return 0;
-                }
-                self.ram[addr]
+                *self.ram.get(addr).unwrap_or(&0)
             }
             _ => {
                 panic!("Invalid MBC3 read address");
@@ -86,7 +108,7 @@ impl CartHw for HwMbc3 {
         }
     }
 
-    fn write(&mut self, addr: Addr, data: u8) {
+    fn write(&mut self, addr: Addr, data: u8) -> bool {
         match addr {
             0x0000..=0x1FFF => {
                 self.ram_timer_enable = data.bits(3, 0) == 0xA;
@@ -101,20 +123,43 @@ impl CartHw for HwMbc3 {
                 // todo: latch clock registers
             }
             0xA000..=0xBFFF => {
-                // RAM Bank 00-03
+                // RAM Bank 00-07. The RTC registers aren't emulated yet, so
+                // writes to them are dropped.
+                if !self.ram_timer_enable || self.ram_bank_sel() >= RTC_REG_SEL {
+                    return false;
+                }
                 let rel_addr = addr - 0xA000;
-                let bank_offs = (self.ram_bank_sel() as usize) * 0x2000;
+                let bank_offs = (self.ram_bank_sel() as usize) * RAM_BANK_SIZE;
                 let addr = bank_offs + (rel_addr as usize);
 
-                if addr >= self.ram.len() {
-                    return;
+                if let Some(val) = self.ram.get_mut(addr) {
+                    *val = data;
+                    return true;
                 }
-
-                self.ram[addr] = data;
             }
             _ => {
                 panic!("Invalid MBC3 write address");
             }
         }
+        return false;
+    }
+}
+
+impl SaveState for HwMbc3 {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.rom_bank_sel);
+        w.write_bytes(&self.ram);
+        w.write_bool(self.ram_timer_enable);
+        w.write_u8(self.ram_rtc_register);
+        w.write_u8(self.ram_bank_rtc_reg_sel);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.rom_bank_sel = r.read_u8()?;
+        r.read_bytes_into(&mut self.ram)?;
+        self.ram_timer_enable = r.read_bool()?;
+        self.ram_rtc_register = r.read_u8()?;
+        self.ram_bank_rtc_reg_sel = r.read_u8()?;
+        return Ok(());
     }
 }
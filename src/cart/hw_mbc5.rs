@@ -1,4 +1,10 @@
-use crate::{mem::Addr, util::bits::Bits};
+use crate::{
+    mem::Addr,
+    util::{
+        bits::Bits,
+        state::{SaveState, StateReader, StateWriter},
+    },
+};
 
 use super::{
     cart_hw::CartHw,
@@ -45,6 +51,10 @@ impl HwMbc5 {
 }
 
 impl CartHw for HwMbc5 {
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
     fn rom_mut(&mut self) -> &mut [u8] {
         &mut self.rom
     }
@@ -57,6 +67,17 @@ impl CartHw for HwMbc5 {
         &mut self.ram
     }
 
+    fn reset(&mut self) {
+        self.ram_enable = false;
+        self.rom_bank_sel_lower_8 = 0;
+        self.rom_bank_sel_upper_1 = 0;
+        self.ram_bank_sel = 0;
+    }
+
+    fn rom_bank(&self) -> usize {
+        self.rom_bank_sel() as usize
+    }
+
     // todo cleanup
     fn read(&self, addr: Addr) -> u8 {
         match addr {
@@ -94,7 +115,7 @@ impl CartHw for HwMbc5 {
     }
 
     // todo cleanup
-    fn write(&mut self, addr: Addr, data: u8) {
+    fn write(&mut self, addr: Addr, data: u8) -> bool {
         match addr {
             0x0000..=0x1FFF => {
                 if data == 0x0A {
@@ -122,6 +143,7 @@ impl CartHw for HwMbc5 {
 
                     if let Some(val) = self.ram.get_mut(addr) {
                         *val = data;
+                        return true;
                     }
                 }
             }
@@ -129,6 +151,26 @@ impl CartHw for HwMbc5 {
                 panic!("Invalid MBC5 write address");
             }
         }
+        return false;
+    }
+}
+
+impl SaveState for HwMbc5 {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bytes(&self.ram);
+        w.write_bool(self.ram_enable);
+        w.write_u8(self.rom_bank_sel_lower_8);
+        w.write_u8(self.rom_bank_sel_upper_1);
+        w.write_u8(self.ram_bank_sel);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        r.read_bytes_into(&mut self.ram)?;
+        self.ram_enable = r.read_bool()?;
+        self.rom_bank_sel_lower_8 = r.read_u8()?;
+        self.rom_bank_sel_upper_1 = r.read_u8()?;
+        self.ram_bank_sel = r.read_u8()?;
+        return Ok(());
     }
 }
 
@@ -1,4 +1,7 @@
-use crate::mem::Addr;
+use crate::{
+    mem::Addr,
+    util::state::{SaveState, StateReader, StateWriter},
+};
 
 use super::{cart_hw::CartHw, consts::ROM_BANK_SIZE};
 
@@ -16,6 +19,10 @@ impl HwRomOnly {
 }
 
 impl CartHw for HwRomOnly {
+    fn rom(&self) -> &[u8] {
+        self.rom.as_slice()
+    }
+
     fn rom_mut(&mut self) -> &mut [u8] {
         self.rom.as_mut_slice()
     }
@@ -28,12 +35,27 @@ impl CartHw for HwRomOnly {
         &mut []
     }
 
+    fn reset(&mut self) {}
+
+    fn rom_bank(&self) -> usize {
+        1
+    }
+
     fn read(&self, addr: Addr) -> u8 {
         let addr = addr as usize;
         return *self.rom.get(addr).unwrap_or(&0);
     }
 
-    fn write(&mut self, _: Addr, _: u8) {
+    fn write(&mut self, _: Addr, _: u8) -> bool {
         // Does nothing.
+        false
+    }
+}
+
+impl SaveState for HwRomOnly {
+    fn save_state(&self, _w: &mut StateWriter) {}
+
+    fn load_state(&mut self, _r: &mut StateReader) -> Result<(), String> {
+        return Ok(());
     }
 }
@@ -82,6 +82,26 @@ impl CartType {
             );
     }
 
+    /// Does the cartridge keep its RAM (or timer) powered when the Game Boy is off?
+    pub fn has_battery(self) -> bool {
+        use CartType::*;
+
+        return matches!(
+            self,
+            Mbc1_Ram_Battery
+                | Mbc2_Battery
+                | Rom_Ram_Battery
+                | Mmm01_Ram_Battery
+                | Mbc3_Timer_Battery
+                | Mbc3_Timer_Ram_Battery
+                | Mbc3_Ram_Battery
+                | Mbc5_Ram_Battery
+                | Mbc5_Rumble_Ram_Battery
+                | Mbc7_Sensor_Rumble_Ram_Battery
+                | HuC1_Ram_Battery
+        );
+    }
+
     pub fn mbc_type(self) -> Option<MbcType> {
         use CartType::*;
 
@@ -0,0 +1,380 @@
+use crate::{
+    cpu::instr::{decode_at, Cond, Instr, R16Mem, R16Stk, R16, R8},
+    mem::Addr,
+    util::math::join_16,
+};
+
+/// An instruction in RGBDS syntax, e.g. `ld a, [hl+]` or `jr nz, $0150`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Disasm {
+    pub text: String,
+    pub size: u16,
+}
+
+/// Disassembles the instruction at `addr`, reading memory through `read`.
+/// Branch targets and memory operands are named by `label` when it has a
+/// name for them.
+pub fn disassemble(
+    addr: Addr,
+    read: impl Fn(Addr) -> u8,
+    label: impl Fn(Addr) -> Option<String>,
+) -> Disasm {
+    let Ok(instr) = decode_at(addr, &read) else {
+        return Disasm {
+            text: format!("db ${:02X}", read(addr)),
+            size: 1,
+        };
+    };
+
+    // Operands are only read for instructions that have them, so nothing
+    // past the end of the instruction is touched.
+    let imm8 = || read(addr.wrapping_add(1));
+    let imm16 = || join_16(read(addr.wrapping_add(2)), imm8());
+    let target = || {
+        let target = branch_target(instr, addr, &read).unwrap();
+        label(target).unwrap_or_else(|| format!("${:04X}", target))
+    };
+    // Writes to ROM go to the MBC's registers, so they aren't named.
+    let operand = |addr: Addr, is_write: bool| {
+        let name = if is_write && addr < 0x8000 {
+            None
+        } else {
+            label(addr)
+        };
+        name.unwrap_or_else(|| format!("${:04X}", addr))
+    };
+
+    let text = match instr {
+        Instr::Nop => "nop".to_owned(),
+        Instr::Ld_R16_Imm16 { dst } => format!("ld {}, ${:04X}", r16(dst), imm16()),
+        Instr::Ld_R16MemP_A { dst } => format!("ld {}, a", r16_mem(dst)),
+        Instr::Ld_A_R16MemP { src } => format!("ld a, {}", r16_mem(src)),
+        Instr::Ld_Imm16P_Sp => format!("ld [{}], sp", operand(imm16(), true)),
+
+        Instr::Inc_R16 { operand } => format!("inc {}", r16(operand)),
+        Instr::Dec_R16 { operand } => format!("dec {}", r16(operand)),
+        Instr::Add_Hl_R16 { operand } => format!("add hl, {}", r16(operand)),
+
+        Instr::Inc_R8 { operand } => format!("inc {}", r8(operand)),
+        Instr::Dec_R8 { operand } => format!("dec {}", r8(operand)),
+
+        Instr::Ld_R8_Imm8 { dst } => format!("ld {}, ${:02X}", r8(dst), imm8()),
+
+        Instr::Rlca => "rlca".to_owned(),
+        Instr::RRca => "rrca".to_owned(),
+        Instr::Rla => "rla".to_owned(),
+        Instr::Rra => "rra".to_owned(),
+        Instr::Daa => "daa".to_owned(),
+        Instr::Cpl => "cpl".to_owned(),
+        Instr::Scf => "scf".to_owned(),
+        Instr::Ccf => "ccf".to_owned(),
+
+        Instr::Jr_Imm8 => format!("jr {}", target()),
+        Instr::Jr_Cond_Imm8 { cond } => format!("jr {}, {}", cond_name(cond), target()),
+
+        Instr::Stop => "stop".to_owned(),
+
+        Instr::Ld_R8_R8 { dst, src } => format!("ld {}, {}", r8(dst), r8(src)),
+        Instr::Halt => "halt".to_owned(),
+
+        Instr::Add_A_R8 { operand } => format!("add a, {}", r8(operand)),
+        Instr::Adc_A_R8 { operand } => format!("adc a, {}", r8(operand)),
+        Instr::Sub_A_R8 { operand } => format!("sub a, {}", r8(operand)),
+        Instr::Sbc_A_R8 { operand } => format!("sbc a, {}", r8(operand)),
+        Instr::And_A_R8 { operand } => format!("and a, {}", r8(operand)),
+        Instr::Xor_A_R8 { operand } => format!("xor a, {}", r8(operand)),
+        Instr::Or_A_R8 { operand } => format!("or a, {}", r8(operand)),
+        Instr::Cp_A_R8 { operand } => format!("cp a, {}", r8(operand)),
+
+        Instr::Add_A_Imm8 => format!("add a, ${:02X}", imm8()),
+        Instr::Adc_A_Imm8 => format!("adc a, ${:02X}", imm8()),
+        Instr::Sub_A_Imm8 => format!("sub a, ${:02X}", imm8()),
+        Instr::Sbc_A_Imm8 => format!("sbc a, ${:02X}", imm8()),
+        Instr::And_A_Imm8 => format!("and a, ${:02X}", imm8()),
+        Instr::Xor_A_Imm8 => format!("xor a, ${:02X}", imm8()),
+        Instr::Or_A_Imm8 => format!("or a, ${:02X}", imm8()),
+        Instr::Cp_A_Imm8 => format!("cp a, ${:02X}", imm8()),
+
+        Instr::Ret_Cond { cond } => format!("ret {}", cond_name(cond)),
+        Instr::Ret => "ret".to_owned(),
+        Instr::Reti => "reti".to_owned(),
+        Instr::Jp_Cond_Imm16 { cond } => format!("jp {}, {}", cond_name(cond), target()),
+        Instr::Jp_Imm16 => format!("jp {}", target()),
+        Instr::Jp_Hl => "jp hl".to_owned(),
+        Instr::Call_Cond_Imm16 { cond } => format!("call {}, {}", cond_name(cond), target()),
+        Instr::Call_Imm16 => format!("call {}", target()),
+        Instr::Rst_Tgt3 { tgt3 } => format!("rst ${:02X}", tgt3 * 8),
+
+        Instr::Pop_R16Stk { reg } => format!("pop {}", r16_stk(reg)),
+        Instr::Push_R16Stk { reg } => format!("push {}", r16_stk(reg)),
+
+        Instr::Ldh_CP_A => "ldh [c], a".to_owned(),
+        Instr::Ldh_Imm8P_A => format!("ldh [{}], a", operand(0xFF00 | imm8() as Addr, true)),
+        Instr::Ld_Imm16P_A => format!("ld [{}], a", operand(imm16(), true)),
+        Instr::Ldh_A_CP => "ldh a, [c]".to_owned(),
+        Instr::Ldh_A_Imm8P => format!("ldh a, [{}]", operand(0xFF00 | imm8() as Addr, false)),
+        Instr::Ld_A_Imm16P => format!("ld a, [{}]", operand(imm16(), false)),
+
+        Instr::Add_Sp_Imm8 => format!("add sp, {}", signed(imm8())),
+        Instr::Ld_Hl_SpImm8 => match signed(imm8()) {
+            offset if offset.starts_with('-') => format!("ld hl, sp{}", offset),
+            offset => format!("ld hl, sp+{}", offset),
+        },
+        Instr::Ld_Sp_Hl => "ld sp, hl".to_owned(),
+
+        Instr::Di => "di".to_owned(),
+        Instr::Ei => "ei".to_owned(),
+
+        Instr::Rlc_R8 { operand } => format!("rlc {}", r8(operand)),
+        Instr::Rrc_R8 { operand } => format!("rrc {}", r8(operand)),
+        Instr::Rl_R8 { operand } => format!("rl {}", r8(operand)),
+        Instr::Rr_R8 { operand } => format!("rr {}", r8(operand)),
+        Instr::Sla_R8 { operand } => format!("sla {}", r8(operand)),
+        Instr::Sra_R8 { operand } => format!("sra {}", r8(operand)),
+        Instr::Swap_R8 { operand } => format!("swap {}", r8(operand)),
+        Instr::Srl_R8 { operand } => format!("srl {}", r8(operand)),
+
+        Instr::Bit_B3_R8 { b3, operand } => format!("bit {}, {}", b3, r8(operand)),
+        Instr::Res_B3_R8 { b3, operand } => format!("res {}, {}", b3, r8(operand)),
+        Instr::Set_B3_R8 { b3, operand } => format!("set {}, {}", b3, r8(operand)),
+
+        Instr::Invalid(op) => format!("db ${:02X}", op),
+    };
+
+    return Disasm {
+        text,
+        size: instr.size(),
+    };
+}
+
+/// Where a jump, call or RST at `addr` goes, if it's known without running
+/// it (i.e. not `jp hl`).
+pub fn branch_target(instr: Instr, addr: Addr, read: impl Fn(Addr) -> u8) -> Option<Addr> {
+    let imm8 = || read(addr.wrapping_add(1));
+    let target = match instr {
+        Instr::Jr_Imm8 | Instr::Jr_Cond_Imm8 { .. } => {
+            let next = addr.wrapping_add(instr.size());
+            next.wrapping_add_signed(imm8() as i8 as i16)
+        }
+        Instr::Jp_Imm16
+        | Instr::Jp_Cond_Imm16 { .. }
+        | Instr::Call_Imm16
+        | Instr::Call_Cond_Imm16 { .. } => join_16(read(addr.wrapping_add(2)), imm8()),
+        Instr::Rst_Tgt3 { tgt3 } => tgt3 as Addr * 8,
+        _ => return None,
+    };
+
+    return Some(target);
+}
+
+/// Disassembles `before` instructions leading up to `pc`, then `after`
+/// instructions from it. Code can't be decoded backwards reliably, so the
+/// earlier ones are decoded from the furthest address that lines up with
+/// `pc`. Names come from `label`, as in `disassemble`.
+pub fn disassemble_around(
+    pc: Addr,
+    before: usize,
+    after: usize,
+    read: impl Fn(Addr) -> u8,
+    label: impl Fn(Addr) -> Option<String>,
+) -> Vec<(Addr, Disasm)> {
+    let mut addr = sync_start(pc, before, &read);
+    let mut lines = vec![];
+    let mut after_count = 0;
+
+    while after_count < after {
+        if addr == pc || after_count > 0 {
+            after_count += 1;
+        }
+        let disasm = disassemble(addr, &read, &label);
+        let size = disasm.size;
+        lines.push((addr, disasm));
+        addr = addr.wrapping_add(size);
+    }
+
+    return lines;
+}
+
+/// Finds where to start decoding to show up to `before` instructions
+/// before `pc`.
+fn sync_start(pc: Addr, before: usize, read: impl Fn(Addr) -> u8) -> Addr {
+    let max_offset = (3 * before) as u16;
+
+    for offset in (1..=max_offset).rev() {
+        let start = pc.wrapping_sub(offset);
+        let mut addrs = vec![];
+        let mut len = 0;
+        while len < offset {
+            let addr = start.wrapping_add(len);
+            addrs.push(addr);
+            len += decode_at(addr, &read).map_or(1, |instr| instr.size());
+        }
+
+        if len == offset {
+            return addrs[addrs.len().saturating_sub(before)];
+        }
+    }
+
+    return pc;
+}
+
+fn r8(reg: R8) -> &'static str {
+    match reg {
+        R8::B => "b",
+        R8::C => "c",
+        R8::D => "d",
+        R8::E => "e",
+        R8::H => "h",
+        R8::L => "l",
+        R8::HlMem => "[hl]",
+        R8::A => "a",
+    }
+}
+
+fn r16(reg: R16) -> &'static str {
+    match reg {
+        R16::BC => "bc",
+        R16::DE => "de",
+        R16::HL => "hl",
+        R16::SP => "sp",
+    }
+}
+
+fn r16_stk(reg: R16Stk) -> &'static str {
+    match reg {
+        R16Stk::BC => "bc",
+        R16Stk::DE => "de",
+        R16Stk::HL => "hl",
+        R16Stk::AF => "af",
+    }
+}
+
+fn r16_mem(reg: R16Mem) -> &'static str {
+    match reg {
+        R16Mem::BC => "[bc]",
+        R16Mem::DE => "[de]",
+        R16Mem::HlInc => "[hl+]",
+        R16Mem::HlDec => "[hl-]",
+    }
+}
+
+fn cond_name(cond: Cond) -> &'static str {
+    match cond {
+        Cond::NZ => "nz",
+        Cond::Z => "z",
+        Cond::NC => "nc",
+        Cond::C => "c",
+    }
+}
+
+/// A signed 8-bit offset in hex, e.g. `$05` or `-$03`.
+fn signed(imm8: u8) -> String {
+    match imm8 as i8 {
+        offset if offset < 0 => format!("-${:02X}", offset.unsigned_abs()),
+        offset => format!("${:02X}", offset),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disassemble_bytes(addr: Addr, bytes: &[u8]) -> Disasm {
+        let read = |a: Addr| {
+            bytes
+                .get(a.wrapping_sub(addr) as usize)
+                .copied()
+                .unwrap_or(0)
+        };
+        return disassemble(addr, read, |_| None);
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let cases: &[(&[u8], &str)] = &[
+            (&[0x00], "nop"),
+            (&[0x2A], "ld a, [hl+]"),
+            (&[0x32], "ld [hl-], a"),
+            (&[0x01, 0x34, 0x12], "ld bc, $1234"),
+            (&[0x08, 0x00, 0xC0], "ld [$C000], sp"),
+            (&[0x36, 0x7F], "ld [hl], $7F"),
+            (&[0x78], "ld a, b"),
+            (&[0x96], "sub a, [hl]"),
+            (&[0xFE, 0x90], "cp a, $90"),
+            (&[0xE0, 0x40], "ldh [$FF40], a"),
+            (&[0xF2], "ldh a, [c]"),
+            (&[0xFA, 0xA0, 0xC0], "ld a, [$C0A0]"),
+            (&[0xE8, 0xFD], "add sp, -$03"),
+            (&[0xF8, 0x05], "ld hl, sp+$05"),
+            (&[0xF8, 0x80], "ld hl, sp-$80"),
+            (&[0xF5], "push af"),
+            (&[0xFF], "rst $38"),
+            (&[0xCB, 0x7E], "bit 7, [hl]"),
+            (&[0xCB, 0x37], "swap a"),
+            (&[0xD3], "db $D3"),
+        ];
+
+        for (bytes, text) in cases {
+            let disasm = disassemble_bytes(0x0150, bytes);
+            assert_eq!(disasm.text, *text);
+            assert_eq!(disasm.size as usize, bytes.len(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_disassemble_around() {
+        // ld a, $01; ld [$C000], a; nop; inc a; jr $0150
+        let bytes = [0x3E, 0x01, 0xEA, 0x00, 0xC0, 0x00, 0x3C, 0x18, 0xF7];
+        let read = |addr: Addr| {
+            bytes
+                .get(addr.wrapping_sub(0x0150) as usize)
+                .copied()
+                .unwrap_or(0)
+        };
+
+        let lines = disassemble_around(0x0156, 2, 2, read, |_| None);
+        let addrs = lines.iter().map(|(addr, _)| *addr).collect::<Vec<_>>();
+        assert_eq!(addrs, vec![0x0152, 0x0155, 0x0156, 0x0157]);
+        assert_eq!(lines[3].1.text, "jr $0150");
+    }
+
+    #[test]
+    fn test_branch_targets() {
+        assert_eq!(
+            disassemble_bytes(0x0150, &[0x20, 0xFE]).text,
+            "jr nz, $0150"
+        );
+        assert_eq!(disassemble_bytes(0x0150, &[0x18, 0x10]).text, "jr $0162");
+        assert_eq!(
+            disassemble_bytes(0x0150, &[0xCD, 0x00, 0x40]).text,
+            "call $4000"
+        );
+        assert_eq!(
+            disassemble_bytes(0x0150, &[0xDA, 0x34, 0x12]).text,
+            "jp c, $1234"
+        );
+
+        let read = |addr: Addr| [0x20, 0xFE][addr as usize - 0x0150];
+        let disasm = disassemble(0x0150, read, |addr| {
+            (addr == 0x0150).then(|| ".loop".to_owned())
+        });
+        assert_eq!(disasm.text, "jr nz, .loop");
+    }
+
+    #[test]
+    fn test_operand_labels() {
+        let label = |addr: Addr| Some(format!("x{:04X}", addr));
+        let cases: &[(&[u8], &str)] = &[
+            (&[0xFA, 0xA0, 0xC0], "ld a, [xC0A0]"),
+            (&[0xE0, 0x80], "ldh [xFF80], a"),
+            (&[0xEA, 0x00, 0x20], "ld [$2000], a"),
+            (&[0xFA, 0x00, 0x40], "ld a, [x4000]"),
+            (&[0x01, 0x34, 0x12], "ld bc, $1234"),
+        ];
+
+        for (bytes, text) in cases {
+            let read = |addr: Addr| bytes[addr as usize - 0x0150];
+            assert_eq!(disassemble(0x0150, read, label).text, *text);
+        }
+    }
+}
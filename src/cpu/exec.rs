@@ -2,7 +2,11 @@ use std::mem::transmute;
 
 use crate::{
     debug::{self, debug_state},
-    mem::{io_regs::IoReg, Addr},
+    debugger::{
+        call_stack::{enter_routine, leave_routine, FrameKind},
+        cdl::ReadUse,
+    },
+    mem::{bus::Bus, io_regs::IoReg, Addr},
     sys::Sys,
     util::{
         bits::Bits,
@@ -19,16 +23,16 @@ use super::{
 /// Executes the instruction at PC and updates PC.
 /// Returns the number of machine cycles needed to execute
 /// the instruction.
-pub fn execute_next_instr(sys: &mut Sys) -> u32 {
+pub fn execute_next_instr<B: Bus>(sys: &mut Sys<B>) -> u32 {
     debug::record_curr_instr(sys);
 
     let mut pc = sys.regs.pc();
-    let mut op = sys.mem.read(pc);
+    let mut op = sys.mem.read_as(pc, ReadUse::Opcode);
     let has_cb_prefix;
 
     if op == Instr::CB_PREFIX {
         pc += 1;
-        op = sys.mem.read(pc);
+        op = sys.mem.read_as(pc, ReadUse::Opcode);
         has_cb_prefix = true;
     } else {
         has_cb_prefix = false;
@@ -155,35 +159,35 @@ pub fn execute_next_instr(sys: &mut Sys) -> u32 {
 }
 
 // Helper functions.
-fn set_pc(sys: &mut Sys, addr: Addr) {
+fn set_pc<B: Bus>(sys: &mut Sys<B>, addr: Addr) {
     sys.regs.set_16(CpuReg16::PC, addr);
 }
 
-fn inc_pc(sys: &mut Sys) {
+fn inc_pc<B: Bus>(sys: &mut Sys<B>) {
     //
     let mut pc = sys.regs.pc();
     pc = u16::wrapping_add(pc, 1);
     sys.regs.set_16(CpuReg16::PC, pc);
 }
 
-fn set_sp(sys: &mut Sys, addr: Addr) {
+fn set_sp<B: Bus>(sys: &mut Sys<B>, addr: Addr) {
     sys.regs.set_16(CpuReg16::SP, addr);
 }
 
-fn inc_sp(sys: &mut Sys) {
+fn inc_sp<B: Bus>(sys: &mut Sys<B>) {
     let mut sp = sys.regs.sp();
     sp = u16::wrapping_add(sp, 1);
     sys.regs.set_16(CpuReg16::SP, sp);
 }
 
-fn dec_sp(sys: &mut Sys) {
+fn dec_sp<B: Bus>(sys: &mut Sys<B>) {
     let mut sp = sys.regs.sp();
     sp = u16::wrapping_sub(sp, 1);
     sys.regs.set_16(CpuReg16::SP, sp);
 }
 
-fn take_imm_u8(sys: &mut Sys) -> u8 {
-    let imm8 = sys.mem.read(sys.regs.pc());
+fn take_imm_u8<B: Bus>(sys: &mut Sys<B>) -> u8 {
+    let imm8 = sys.mem.read_as(sys.regs.pc(), ReadUse::Operand);
     inc_pc(sys);
 
     if debug_state().config.enable_debug_print {
@@ -193,15 +197,15 @@ fn take_imm_u8(sys: &mut Sys) -> u8 {
     return imm8;
 }
 
-fn take_imm_i8(sys: &mut Sys) -> i8 {
+fn take_imm_i8<B: Bus>(sys: &mut Sys<B>) -> i8 {
     let imm8 = take_imm_u8(sys);
     return unsafe { transmute(imm8) };
 }
 
-fn take_imm_u16(sys: &mut Sys) -> u16 {
-    let lo = sys.mem.read(sys.regs.pc());
+fn take_imm_u16<B: Bus>(sys: &mut Sys<B>) -> u16 {
+    let lo = sys.mem.read_as(sys.regs.pc(), ReadUse::Operand);
     inc_pc(sys);
-    let hi = sys.mem.read(sys.regs.pc());
+    let hi = sys.mem.read_as(sys.regs.pc(), ReadUse::Operand);
     inc_pc(sys);
 
     let imm16 = join_16(hi, lo);
@@ -213,7 +217,7 @@ fn take_imm_u16(sys: &mut Sys) -> u16 {
     return imm16;
 }
 
-fn is_condition_met(sys: &mut Sys, cond: Cond) -> bool {
+fn is_condition_met<B: Bus>(sys: &mut Sys<B>, cond: Cond) -> bool {
     let z = sys.regs.get_flag(CpuFlag::Z);
     let c = sys.regs.get_flag(CpuFlag::C);
 
@@ -225,7 +229,7 @@ fn is_condition_met(sys: &mut Sys, cond: Cond) -> bool {
     }
 }
 
-fn get_r8_data(sys: &mut Sys, operand: R8) -> u8 {
+fn get_r8_data<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     if let Some(reg) = operand.get_reg() {
         return sys.regs.get_8(reg);
     } else {
@@ -234,7 +238,7 @@ fn get_r8_data(sys: &mut Sys, operand: R8) -> u8 {
     }
 }
 
-fn set_r8_data(sys: &mut Sys, operand: R8, data: u8) {
+fn set_r8_data<B: Bus>(sys: &mut Sys<B>, operand: R8, data: u8) {
     if let Some(reg) = operand.get_reg() {
         sys.regs.set_8(reg, data);
     } else {
@@ -243,7 +247,7 @@ fn set_r8_data(sys: &mut Sys, operand: R8, data: u8) {
     }
 }
 
-fn push_16(sys: &mut Sys, data: u16) {
+fn push_16<B: Bus>(sys: &mut Sys<B>, data: u16) {
     let (hi, lo) = split_16(data);
 
     dec_sp(sys);
@@ -253,7 +257,7 @@ fn push_16(sys: &mut Sys, data: u16) {
     sys.mem.write(sys.regs.sp(), lo);
 }
 
-fn pop_16(sys: &mut Sys) -> u16 {
+fn pop_16<B: Bus>(sys: &mut Sys<B>) -> u16 {
     let lo = sys.mem.read(sys.regs.sp());
     inc_sp(sys);
 
@@ -263,17 +267,18 @@ fn pop_16(sys: &mut Sys) -> u16 {
     return join_16(hi, lo);
 }
 
-pub fn call(sys: &mut Sys, prev_pc: u16, next_pc: u16) {
+pub fn call<B: Bus>(sys: &mut Sys<B>, prev_pc: u16, next_pc: u16, kind: FrameKind) {
     push_16(sys, prev_pc);
     set_pc(sys, next_pc);
+    enter_routine(sys, prev_pc, kind);
 }
 
 // Block 0 functions.
-fn nop(_: &mut Sys) -> u8 {
+fn nop<B: Bus>(_: &mut Sys<B>) -> u8 {
     return 1;
 }
 
-fn ld_r16_imm16(sys: &mut Sys, dst: R16) -> u8 {
+fn ld_r16_imm16<B: Bus>(sys: &mut Sys<B>, dst: R16) -> u8 {
     let imm16 = take_imm_u16(sys);
     let reg = dst.get_reg();
     sys.regs.set_16(reg, imm16);
@@ -281,7 +286,7 @@ fn ld_r16_imm16(sys: &mut Sys, dst: R16) -> u8 {
     return 3;
 }
 
-fn ld_r16memp_a(sys: &mut Sys, dst: R16Mem) -> u8 {
+fn ld_r16memp_a<B: Bus>(sys: &mut Sys<B>, dst: R16Mem) -> u8 {
     let data = sys.regs.get_8(CpuReg8::A);
     let (dstp, inc) = dst.get_reg_inc();
 
@@ -292,7 +297,7 @@ fn ld_r16memp_a(sys: &mut Sys, dst: R16Mem) -> u8 {
     return 2;
 }
 
-fn ld_a_r16memp(sys: &mut Sys, src: R16Mem) -> u8 {
+fn ld_a_r16memp<B: Bus>(sys: &mut Sys<B>, src: R16Mem) -> u8 {
     let (srcp, inc) = src.get_reg_inc();
 
     let addr = sys.regs.get_16(srcp);
@@ -304,7 +309,7 @@ fn ld_a_r16memp(sys: &mut Sys, src: R16Mem) -> u8 {
     return 2;
 }
 
-fn ld_imm16_sp(sys: &mut Sys) -> u8 {
+fn ld_imm16_sp<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let addr = take_imm_u16(sys);
     let sp_data = sys.regs.get_16(CpuReg16::SP);
     let (hi, lo) = split_16(sp_data);
@@ -314,7 +319,7 @@ fn ld_imm16_sp(sys: &mut Sys) -> u8 {
     return 5;
 }
 
-fn inc_dec_r16(sys: &mut Sys, operand: R16, inc: i16) -> u8 {
+fn inc_dec_r16<B: Bus>(sys: &mut Sys<B>, operand: R16, inc: i16) -> u8 {
     let mut data = sys.regs.get_16(operand.get_reg());
     data = add16_ui(data, inc);
     sys.regs.set_16(operand.get_reg(), data);
@@ -322,7 +327,7 @@ fn inc_dec_r16(sys: &mut Sys, operand: R16, inc: i16) -> u8 {
     return 2;
 }
 
-fn add_hl_r16(sys: &mut Sys, operand: R16) -> u8 {
+fn add_hl_r16<B: Bus>(sys: &mut Sys<B>, operand: R16) -> u8 {
     let hl = sys.regs.get_16(CpuReg16::HL);
     let operand = sys.regs.get_16(operand.get_reg());
 
@@ -337,7 +342,7 @@ fn add_hl_r16(sys: &mut Sys, operand: R16) -> u8 {
     return 2;
 }
 
-fn inc_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn inc_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let mut data = get_r8_data(sys, operand);
     let h = data.bits(3, 0) == 0b1111;
 
@@ -352,7 +357,7 @@ fn inc_r8(sys: &mut Sys, operand: R8) -> u8 {
     return if operand == R8::HlMem { 3 } else { 1 };
 }
 
-fn dec_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn dec_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let data = get_r8_data(sys, operand);
 
     let res = sub_2_u8(data, 1);
@@ -365,21 +370,21 @@ fn dec_r8(sys: &mut Sys, operand: R8) -> u8 {
     return if operand == R8::HlMem { 3 } else { 1 };
 }
 
-fn ld_r8_imm8(sys: &mut Sys, dst: R8) -> u8 {
+fn ld_r8_imm8<B: Bus>(sys: &mut Sys<B>, dst: R8) -> u8 {
     let imm8 = take_imm_u8(sys);
     set_r8_data(sys, dst, imm8);
 
     return if dst == R8::HlMem { 3 } else { 2 };
 }
 
-fn rlca(sys: &mut Sys) -> u8 {
+fn rlca<B: Bus>(sys: &mut Sys<B>) -> u8 {
     rlc_r8(sys, R8::A);
     sys.regs.set_flag(CpuFlag::Z, false);
 
     return 1;
 }
 
-fn rrca(sys: &mut Sys) -> u8 {
+fn rrca<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let mut data = sys.regs.get_8(CpuReg8::A);
     let c = data.bit(0) == 0b1;
     data = u8::rotate_right(data, 1);
@@ -393,7 +398,7 @@ fn rrca(sys: &mut Sys) -> u8 {
     return 1;
 }
 
-fn rla(sys: &mut Sys) -> u8 {
+fn rla<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let c = if sys.regs.get_flag(CpuFlag::C) {
         0b1
     } else {
@@ -413,7 +418,7 @@ fn rla(sys: &mut Sys) -> u8 {
     return 1;
 }
 
-fn rra(sys: &mut Sys) -> u8 {
+fn rra<B: Bus>(sys: &mut Sys<B>) -> u8 {
     rr_r8(sys, R8::A);
 
     sys.regs.set_flag(CpuFlag::Z, false);
@@ -421,7 +426,7 @@ fn rra(sys: &mut Sys) -> u8 {
     return 1;
 }
 
-fn daa(sys: &mut Sys) -> u8 {
+fn daa<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let subtraction = sys.regs.get_flag(CpuFlag::N);
     let half_carry = sys.regs.get_flag(CpuFlag::H);
     let carry = sys.regs.get_flag(CpuFlag::C);
@@ -454,7 +459,7 @@ fn daa(sys: &mut Sys) -> u8 {
     return 1;
 }
 
-fn cpl(sys: &mut Sys) -> u8 {
+fn cpl<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let mut data = sys.regs.get_8(CpuReg8::A);
     data = !data;
     sys.regs.set_8(CpuReg8::A, data);
@@ -465,7 +470,7 @@ fn cpl(sys: &mut Sys) -> u8 {
     return 1;
 }
 
-fn scf(sys: &mut Sys) -> u8 {
+fn scf<B: Bus>(sys: &mut Sys<B>) -> u8 {
     sys.regs.set_flag(CpuFlag::N, false);
     sys.regs.set_flag(CpuFlag::H, false);
     sys.regs.set_flag(CpuFlag::C, true);
@@ -473,7 +478,7 @@ fn scf(sys: &mut Sys) -> u8 {
     return 1;
 }
 
-fn ccf(sys: &mut Sys) -> u8 {
+fn ccf<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let c = sys.regs.get_flag(CpuFlag::C);
 
     sys.regs.set_flag(CpuFlag::N, false);
@@ -483,7 +488,7 @@ fn ccf(sys: &mut Sys) -> u8 {
     return 1;
 }
 
-fn jr_imm8(sys: &mut Sys) -> u8 {
+fn jr_imm8<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let rel = take_imm_i8(sys);
     if (rel == -2) && sys.options.kill_on_infinite_loop {
         //debug::fail("Ininite loop.");
@@ -498,7 +503,7 @@ fn jr_imm8(sys: &mut Sys) -> u8 {
     return 3;
 }
 
-fn jr_cond_imm8(sys: &mut Sys, cond: Cond) -> u8 {
+fn jr_cond_imm8<B: Bus>(sys: &mut Sys<B>, cond: Cond) -> u8 {
     let rel = take_imm_i8(sys);
     if is_condition_met(sys, cond) {
         let mut pc = sys.regs.pc();
@@ -515,36 +520,46 @@ fn jr_cond_imm8(sys: &mut Sys, cond: Cond) -> u8 {
     // todo jumping from correct starting addr??
 }
 
-fn stop(sys: &mut Sys) -> u8 {
-    //sys.cpu_enable = false;
+fn stop<B: Bus>(sys: &mut Sys<B>) -> u8 {
+    // DIV is reset when STOP is executed.
+    sys.mem.set_io_reg(IoReg::Div, 0x00);
+    sys.div_timer_clock.reset();
 
     if sys.is_cgb_only_mode() {
         // Handle Double-Speed mode toggle request.
         println!("STOP");
 
-        let key1 = sys.mem.io_regs.get(IoReg::Key1);
+        let mut key1 = sys.mem.io_reg(IoReg::Key1);
         let switch_requested = key1.bit(0) == 1;
         if switch_requested {
-            sys.mem.io_regs.mut_(IoReg::Key1, |key1: &mut u8| {
-                // Toggle Double-Speed mode field in KEY1 reg.
-                // let prev_speed_mode = key1.bit(7);
-                // let next_speed_mode = (!prev_speed_mode) & 1;
-                // key1.set_bit(7, next_speed_mode);
-                key1.toggle_bit(7);
+            // Toggle Double-Speed mode field in KEY1 reg.
+            // let prev_speed_mode = key1.bit(7);
+            // let next_speed_mode = (!prev_speed_mode) & 1;
+            // key1.set_bit(7, next_speed_mode);
+            key1.toggle_bit(7);
 
-                // Reset Switch Requested field.
-                key1.set_bit(0, 0);
-            });
+            // Reset Switch Requested field.
+            key1.set_bit(0, 0);
+            sys.mem.set_io_reg(IoReg::Key1, key1);
 
             sys.speed_ctrl.stop();
+            return 1;
         }
     }
 
+    // If a button is already held, STOP behaves like HALT instead.
+    let p1 = sys.mem.io_reg(IoReg::P1);
+    if p1.bits(3, 0) != 0xF {
+        sys.cpu_enable = false;
+    } else {
+        sys.speed_ctrl.enter_low_power_mode();
+    }
+
     return 1;
 }
 
 // Block 1 functions.
-fn ld_r8_r8(sys: &mut Sys, dst: R8, src: R8) -> u8 {
+fn ld_r8_r8<B: Bus>(sys: &mut Sys<B>, dst: R8, src: R8) -> u8 {
     let data = get_r8_data(sys, src);
     set_r8_data(sys, dst, data);
 
@@ -559,14 +574,14 @@ fn ld_r8_r8(sys: &mut Sys, dst: R8, src: R8) -> u8 {
     };
 }
 
-fn halt(sys: &mut Sys) -> u8 {
+fn halt<B: Bus>(sys: &mut Sys<B>) -> u8 {
     sys.cpu_enable = false;
 
     return 1;
 }
 
 // Block 2 functions.
-fn add_a_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn add_a_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let a = sys.regs.get_8(CpuReg8::A);
     let data = get_r8_data(sys, operand);
 
@@ -581,7 +596,7 @@ fn add_a_r8(sys: &mut Sys, operand: R8) -> u8 {
     return if operand == R8::HlMem { 2 } else { 1 };
 }
 
-fn adc_a_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn adc_a_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let a = sys.regs.get_8(CpuReg8::A);
     let data = get_r8_data(sys, operand);
     let carry = sys.regs.get_flag(CpuFlag::C).into();
@@ -597,7 +612,7 @@ fn adc_a_r8(sys: &mut Sys, operand: R8) -> u8 {
     return if operand == R8::HlMem { 2 } else { 1 };
 }
 
-fn sub_a_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn sub_a_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let a = sys.regs.get_8(CpuReg8::A);
     let data = get_r8_data(sys, operand);
 
@@ -612,7 +627,7 @@ fn sub_a_r8(sys: &mut Sys, operand: R8) -> u8 {
     return if operand == R8::HlMem { 2 } else { 1 };
 }
 
-fn sbc_a_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn sbc_a_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let a = sys.regs.get_8(CpuReg8::A);
     let data = get_r8_data(sys, operand);
     let carry = if sys.regs.get_flag(CpuFlag::C) { 1 } else { 0 };
@@ -628,7 +643,7 @@ fn sbc_a_r8(sys: &mut Sys, operand: R8) -> u8 {
     return if operand == R8::HlMem { 2 } else { 1 };
 }
 
-fn and_a_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn and_a_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let a = sys.regs.get_8(CpuReg8::A);
     let data = get_r8_data(sys, operand);
 
@@ -643,7 +658,7 @@ fn and_a_r8(sys: &mut Sys, operand: R8) -> u8 {
     return if operand == R8::HlMem { 2 } else { 1 };
 }
 
-fn xor_a_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn xor_a_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let a = sys.regs.get_8(CpuReg8::A);
     let data = get_r8_data(sys, operand);
 
@@ -658,7 +673,7 @@ fn xor_a_r8(sys: &mut Sys, operand: R8) -> u8 {
     return if operand == R8::HlMem { 2 } else { 1 };
 }
 
-fn or_a_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn or_a_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let a = sys.regs.get_8(CpuReg8::A);
     let data = get_r8_data(sys, operand);
 
@@ -673,7 +688,7 @@ fn or_a_r8(sys: &mut Sys, operand: R8) -> u8 {
     return if operand == R8::HlMem { 2 } else { 1 };
 }
 
-fn cp_a_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn cp_a_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let a = sys.regs.get_8(CpuReg8::A);
     let data = get_r8_data(sys, operand);
 
@@ -688,7 +703,7 @@ fn cp_a_r8(sys: &mut Sys, operand: R8) -> u8 {
 }
 
 // Block 3 functions.
-fn add_a_imm8(sys: &mut Sys) -> u8 {
+fn add_a_imm8<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let imm8 = take_imm_u8(sys);
     let a = sys.regs.get_8(CpuReg8::A);
 
@@ -703,7 +718,7 @@ fn add_a_imm8(sys: &mut Sys) -> u8 {
     return 2;
 }
 
-fn adc_a_imm8(sys: &mut Sys) -> u8 {
+fn adc_a_imm8<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let imm8 = take_imm_u8(sys);
     let a = sys.regs.get_8(CpuReg8::A);
     let carry = sys.regs.get_flag(CpuFlag::C).into();
@@ -719,7 +734,7 @@ fn adc_a_imm8(sys: &mut Sys) -> u8 {
     return 2;
 }
 
-fn sub_a_imm8(sys: &mut Sys) -> u8 {
+fn sub_a_imm8<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let imm8 = take_imm_u8(sys);
     let a = sys.regs.get_8(CpuReg8::A);
 
@@ -734,7 +749,7 @@ fn sub_a_imm8(sys: &mut Sys) -> u8 {
     return 2;
 }
 
-fn sbc_a_imm8(sys: &mut Sys) -> u8 {
+fn sbc_a_imm8<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let imm8 = take_imm_u8(sys);
     let a = sys.regs.get_8(CpuReg8::A);
     let carry = sys.regs.get_flag(CpuFlag::C).into();
@@ -750,7 +765,7 @@ fn sbc_a_imm8(sys: &mut Sys) -> u8 {
     return 2;
 }
 
-fn and_a_imm8(sys: &mut Sys) -> u8 {
+fn and_a_imm8<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let imm8 = take_imm_u8(sys);
     let a = sys.regs.get_8(CpuReg8::A);
 
@@ -765,7 +780,7 @@ fn and_a_imm8(sys: &mut Sys) -> u8 {
     return 2;
 }
 
-fn xor_a_imm8(sys: &mut Sys) -> u8 {
+fn xor_a_imm8<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let imm8 = take_imm_u8(sys);
     let a = sys.regs.get_8(CpuReg8::A);
 
@@ -780,7 +795,7 @@ fn xor_a_imm8(sys: &mut Sys) -> u8 {
     return 2;
 }
 
-fn or_a_imm8(sys: &mut Sys) -> u8 {
+fn or_a_imm8<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let imm8 = take_imm_u8(sys);
     let a = sys.regs.get_8(CpuReg8::A);
 
@@ -795,7 +810,7 @@ fn or_a_imm8(sys: &mut Sys) -> u8 {
     return 2;
 }
 
-fn cp_a_imm8(sys: &mut Sys) -> u8 {
+fn cp_a_imm8<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let imm8 = take_imm_u8(sys);
     let a_data = sys.regs.get_8(CpuReg8::A);
 
@@ -809,7 +824,7 @@ fn cp_a_imm8(sys: &mut Sys) -> u8 {
     return 2;
 }
 
-fn ret_cond(sys: &mut Sys, cond: Cond) -> u8 {
+fn ret_cond<B: Bus>(sys: &mut Sys<B>, cond: Cond) -> u8 {
     if is_condition_met(sys, cond) {
         ret(sys);
 
@@ -819,23 +834,27 @@ fn ret_cond(sys: &mut Sys, cond: Cond) -> u8 {
     return 2;
 }
 
-fn ret(sys: &mut Sys) -> u8 {
+fn ret<B: Bus>(sys: &mut Sys<B>) -> u8 {
+    let sp = sys.regs.sp();
     let addr = pop_16(sys);
     set_pc(sys, addr);
+    leave_routine(sys, sp);
 
     return 4;
 }
 
-fn reti(sys: &mut Sys) -> u8 {
+fn reti<B: Bus>(sys: &mut Sys<B>) -> u8 {
+    let sp = sys.regs.sp();
     let addr = pop_16(sys);
     set_pc(sys, addr);
+    leave_routine(sys, sp);
 
     sys.interrupt_master_enable = true;
 
     return 4;
 }
 
-fn jp_cond_imm16(sys: &mut Sys, cond: Cond) -> u8 {
+fn jp_cond_imm16<B: Bus>(sys: &mut Sys<B>, cond: Cond) -> u8 {
     let imm16 = take_imm_u16(sys);
     if is_condition_met(sys, cond) {
         set_pc(sys, imm16);
@@ -846,25 +865,25 @@ fn jp_cond_imm16(sys: &mut Sys, cond: Cond) -> u8 {
     return 3;
 }
 
-fn jp_imm16(sys: &mut Sys) -> u8 {
+fn jp_imm16<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let imm16 = take_imm_u16(sys);
     set_pc(sys, imm16);
 
     return 4;
 }
 
-fn jp_hl(sys: &mut Sys) -> u8 {
+fn jp_hl<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let hl = sys.regs.get_16(CpuReg16::HL);
     set_pc(sys, hl);
 
     return 1;
 }
 
-fn call_cond_imm16(sys: &mut Sys, cond: Cond) -> u8 {
+fn call_cond_imm16<B: Bus>(sys: &mut Sys<B>, cond: Cond) -> u8 {
     let imm16 = take_imm_u16(sys);
     if is_condition_met(sys, cond) {
         let pc = sys.regs.pc();
-        call(sys, pc, imm16);
+        call(sys, pc, imm16, FrameKind::Call);
 
         return 6;
     }
@@ -872,39 +891,37 @@ fn call_cond_imm16(sys: &mut Sys, cond: Cond) -> u8 {
     return 3;
 }
 
-fn call_imm16(sys: &mut Sys) -> u8 {
+fn call_imm16<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let imm16 = take_imm_u16(sys);
     let pc = sys.regs.pc();
-    call(sys, pc, imm16);
+    call(sys, pc, imm16, FrameKind::Call);
 
     return 6;
 }
 
-fn rst_tgt3(sys: &mut Sys, tgt3: u8) -> u8 {
+fn rst_tgt3<B: Bus>(sys: &mut Sys<B>, tgt3: u8) -> u8 {
     let pc = sys.regs.pc();
-    push_16(sys, pc);
-
     let tgt = (tgt3 as u16) << 3;
-    set_pc(sys, tgt);
+    call(sys, pc, tgt, FrameKind::Call);
 
     return 4;
 }
 
-fn pop_r16stk(sys: &mut Sys, reg: R16Stk) -> u8 {
+fn pop_r16stk<B: Bus>(sys: &mut Sys<B>, reg: R16Stk) -> u8 {
     let data = pop_16(sys);
     sys.regs.set_16(reg.get_reg(), data);
 
     return 3;
 }
 
-fn push_r16stk(sys: &mut Sys, reg: R16Stk) -> u8 {
+fn push_r16stk<B: Bus>(sys: &mut Sys<B>, reg: R16Stk) -> u8 {
     let data = sys.regs.get_16(reg.get_reg());
     push_16(sys, data);
 
     return 4;
 }
 
-fn ldh_cp_a(sys: &mut Sys) -> u8 {
+fn ldh_cp_a<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let a_data = sys.regs.get_8(CpuReg8::A);
     let c_data = sys.regs.get_8(CpuReg8::C);
     let addr = join_16(0xFF, c_data);
@@ -914,7 +931,7 @@ fn ldh_cp_a(sys: &mut Sys) -> u8 {
     return 2;
 }
 
-fn ldh_imm8p_a(sys: &mut Sys) -> u8 {
+fn ldh_imm8p_a<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let offset = take_imm_u8(sys);
     let a_data = sys.regs.get_8(CpuReg8::A);
     let addr = join_16(0xFF, offset);
@@ -924,7 +941,7 @@ fn ldh_imm8p_a(sys: &mut Sys) -> u8 {
     return 3;
 }
 
-fn ld_imm16p_a(sys: &mut Sys) -> u8 {
+fn ld_imm16p_a<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let imm16 = take_imm_u16(sys);
     let data = sys.regs.get_8(CpuReg8::A);
     let addr = imm16;
@@ -934,7 +951,7 @@ fn ld_imm16p_a(sys: &mut Sys) -> u8 {
     return 4;
 }
 
-fn ldh_a_cp(sys: &mut Sys) -> u8 {
+fn ldh_a_cp<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let c_data = sys.regs.get_8(CpuReg8::C);
     let addr = join_16(0xFF, c_data);
     let data = sys.mem.read(addr);
@@ -944,7 +961,7 @@ fn ldh_a_cp(sys: &mut Sys) -> u8 {
     return 2;
 }
 
-fn ldh_a_imm8p(sys: &mut Sys) -> u8 {
+fn ldh_a_imm8p<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let imm8 = take_imm_u8(sys);
     let addr = join_16(0xFF, imm8);
     let data = sys.mem.read(addr);
@@ -954,7 +971,7 @@ fn ldh_a_imm8p(sys: &mut Sys) -> u8 {
     return 4;
 }
 
-fn ld_a_imm16p(sys: &mut Sys) -> u8 {
+fn ld_a_imm16p<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let addr = take_imm_u16(sys);
     let data = sys.mem.read(addr);
 
@@ -963,7 +980,7 @@ fn ld_a_imm16p(sys: &mut Sys) -> u8 {
     return 3;
 }
 
-fn add_sp_imm8(sys: &mut Sys) -> u8 {
+fn add_sp_imm8<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let sp = sys.regs.sp();
     let s_imm8 = take_imm_i8(sys);
     let res = add_sp_i8(sp, s_imm8);
@@ -978,7 +995,7 @@ fn add_sp_imm8(sys: &mut Sys) -> u8 {
     return 4;
 }
 
-fn ld_hl_spimm8(sys: &mut Sys) -> u8 {
+fn ld_hl_spimm8<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let sp = sys.regs.sp();
     let s_imm8 = take_imm_i8(sys);
     let res = add_sp_i8(sp, s_imm8);
@@ -993,27 +1010,27 @@ fn ld_hl_spimm8(sys: &mut Sys) -> u8 {
     return 3;
 }
 
-fn ld_sp_hl(sys: &mut Sys) -> u8 {
+fn ld_sp_hl<B: Bus>(sys: &mut Sys<B>) -> u8 {
     let data = sys.regs.get_16(CpuReg16::HL);
     set_sp(sys, data);
 
     return 2;
 }
 
-fn di(sys: &mut Sys) -> u8 {
+fn di<B: Bus>(sys: &mut Sys<B>) -> u8 {
     sys.interrupt_master_enable = false;
 
     return 1;
 }
 
-fn ei(sys: &mut Sys) -> u8 {
+fn ei<B: Bus>(sys: &mut Sys<B>) -> u8 {
     sys.interrupt_master_enable = true;
 
     return 1;
 }
 
 // 0xCB prefix functions.
-fn rlc_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn rlc_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let mut data = get_r8_data(sys, operand);
     let c_ = data.bit(7);
 
@@ -1028,7 +1045,7 @@ fn rlc_r8(sys: &mut Sys, operand: R8) -> u8 {
     return 2;
 }
 
-fn rrc_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn rrc_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let mut data = get_r8_data(sys, operand);
     let c_ = data.bit(0);
 
@@ -1043,7 +1060,7 @@ fn rrc_r8(sys: &mut Sys, operand: R8) -> u8 {
     return 2;
 }
 
-fn rl_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn rl_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let mut data = get_r8_data(sys, operand);
     let c = sys.regs.get_flag(CpuFlag::C).into();
     let c_ = data.bit(7);
@@ -1060,7 +1077,7 @@ fn rl_r8(sys: &mut Sys, operand: R8) -> u8 {
     return 2;
 }
 
-fn rr_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn rr_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let mut data = get_r8_data(sys, operand);
     let c = sys.regs.get_flag(CpuFlag::C).into();
     let c_ = data.bit(0);
@@ -1077,7 +1094,7 @@ fn rr_r8(sys: &mut Sys, operand: R8) -> u8 {
     return 2;
 }
 
-fn sla_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn sla_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let mut data = get_r8_data(sys, operand);
     let c_ = data.bit(7);
 
@@ -1092,7 +1109,7 @@ fn sla_r8(sys: &mut Sys, operand: R8) -> u8 {
     return 2;
 }
 
-fn sra_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn sra_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let mut data = get_r8_data(sys, operand);
     let data7 = data.bit(7);
     let c_ = data.bit(0);
@@ -1109,7 +1126,7 @@ fn sra_r8(sys: &mut Sys, operand: R8) -> u8 {
     return 2;
 }
 
-fn swap_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn swap_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let mut data = get_r8_data(sys, operand);
 
     data = u8::rotate_left(data, 4);
@@ -1124,7 +1141,7 @@ fn swap_r8(sys: &mut Sys, operand: R8) -> u8 {
     return 2;
 }
 
-fn srl_r8(sys: &mut Sys, operand: R8) -> u8 {
+fn srl_r8<B: Bus>(sys: &mut Sys<B>, operand: R8) -> u8 {
     let mut data = get_r8_data(sys, operand);
     let c_ = data.bit(0);
 
@@ -1139,7 +1156,7 @@ fn srl_r8(sys: &mut Sys, operand: R8) -> u8 {
     return 2;
 }
 
-fn bit_b3_r8(sys: &mut Sys, b3: u8, operand: R8) -> u8 {
+fn bit_b3_r8<B: Bus>(sys: &mut Sys<B>, b3: u8, operand: R8) -> u8 {
     let data = get_r8_data(sys, operand);
     let bit = data.bit(b3);
 
@@ -1150,7 +1167,7 @@ fn bit_b3_r8(sys: &mut Sys, b3: u8, operand: R8) -> u8 {
     return if operand == R8::HlMem { 3 } else { 2 };
 }
 
-fn res_b3_r8(sys: &mut Sys, b3: u8, operand: R8) -> u8 {
+fn res_b3_r8<B: Bus>(sys: &mut Sys<B>, b3: u8, operand: R8) -> u8 {
     let mut data = get_r8_data(sys, operand);
     data.set_bit(b3, 0);
     set_r8_data(sys, operand, data);
@@ -1158,7 +1175,7 @@ fn res_b3_r8(sys: &mut Sys, b3: u8, operand: R8) -> u8 {
     return if operand == R8::HlMem { 4 } else { 2 };
 }
 
-fn set_b3_r8(sys: &mut Sys, b3: u8, operand: R8) -> u8 {
+fn set_b3_r8<B: Bus>(sys: &mut Sys<B>, b3: u8, operand: R8) -> u8 {
     let mut data = get_r8_data(sys, operand);
     data.set_bit(b3, 1);
     set_r8_data(sys, operand, data);
@@ -1167,7 +1184,7 @@ fn set_b3_r8(sys: &mut Sys, b3: u8, operand: R8) -> u8 {
 }
 
 // Misc functions.
-fn hard_lock(sys: &mut Sys, opcode: u8) -> u8 {
+fn hard_lock<B: Bus>(sys: &mut Sys<B>, opcode: u8) -> u8 {
     sys.hard_lock = true;
     debug::fail(format!("Invalid instr occurred ({:0>2X}).", opcode));
     return 1;
@@ -1,5 +1,6 @@
 use crate::{
     cpu::regs::{CpuReg16, CpuReg8},
+    mem::Addr,
     util::bits::Bits,
 };
 
@@ -142,6 +143,34 @@ impl Instr {
             _ => ImmType::None,
         }
     }
+
+    /// Size in bytes, including the 0xCB prefix and any immediate.
+    pub fn size(&self) -> u16 {
+        let opcode_size = if self.has_cb_prefix() { 2 } else { 1 };
+        let imm_size = match self.imm_type() {
+            ImmType::None => 0,
+            ImmType::Imm8 => 1,
+            ImmType::Imm16 => 2,
+        };
+        return opcode_size + imm_size;
+    }
+
+    pub fn has_cb_prefix(&self) -> bool {
+        matches!(
+            self,
+            Instr::Rlc_R8 { .. }
+                | Instr::Rrc_R8 { .. }
+                | Instr::Rl_R8 { .. }
+                | Instr::Rr_R8 { .. }
+                | Instr::Sla_R8 { .. }
+                | Instr::Sra_R8 { .. }
+                | Instr::Swap_R8 { .. }
+                | Instr::Srl_R8 { .. }
+                | Instr::Bit_B3_R8 { .. }
+                | Instr::Res_B3_R8 { .. }
+                | Instr::Set_B3_R8 { .. }
+        )
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, FromPrimitive, Debug)]
@@ -279,6 +308,15 @@ pub enum ImmType {
 
 pub type DecodeResult = Result<Instr, String>;
 
+/// Decodes the instruction at `addr`, reading memory through `read`.
+pub fn decode_at(addr: Addr, read: impl Fn(Addr) -> u8) -> DecodeResult {
+    let op = read(addr);
+    if op == Instr::CB_PREFIX {
+        return decode(read(addr.wrapping_add(1)), true);
+    }
+    return decode(op, false);
+}
+
 pub fn decode(op: u8, has_cb_prefix: bool) -> DecodeResult {
     if has_cb_prefix {
         return Ok(decode_cp_prefix_opcode(op));
@@ -3,7 +3,8 @@ use strum_macros::EnumIter;
 
 use crate::{
     debug,
-    mem::{io_regs::IoReg, Addr},
+    debugger::call_stack::FrameKind,
+    mem::{bus::Bus, io_regs::IoReg, Addr},
     sys::Sys,
     util::bits::Bits,
 };
@@ -37,16 +38,16 @@ impl InterruptType {
     }
 }
 
-pub fn request_interrupt(sys: &mut Sys, type_: InterruptType) {
+pub fn request_interrupt<B: Bus>(sys: &mut Sys<B>, type_: InterruptType) {
     //println!("Int req: {:?}", type_);
-    sys.mem.io_regs.mut_(IoReg::If, |if_| {
-        if_.set_bit(type_.flag_idx(), 1);
-    });
+    let mut if_ = sys.mem.io_reg(IoReg::If);
+    if_.set_bit(type_.flag_idx(), 1);
+    sys.mem.set_io_reg(IoReg::If, if_);
 }
 
-pub fn try_handle_interrupts(sys: &mut Sys) {
-    let ie = sys.mem.io_regs.get(IoReg::Ie);
-    let if_ = sys.mem.io_regs.get(IoReg::If);
+pub fn try_handle_interrupts<B: Bus>(sys: &mut Sys<B>) {
+    let ie = sys.mem.io_reg(IoReg::Ie);
+    let if_ = sys.mem.io_reg(IoReg::If);
     if ie & if_ != 0 {
         sys.cpu_enable = true;
     }
@@ -69,22 +70,22 @@ pub fn try_handle_interrupts(sys: &mut Sys) {
     }
 }
 
-fn handle_interrupt(sys: &mut Sys, type_: InterruptType) {
+fn handle_interrupt<B: Bus>(sys: &mut Sys<B>, type_: InterruptType) {
     debug::record_handled_interrupt(type_);
 
     sys.interrupt_master_enable = false;
     sys.cpu_enable = true;
 
-    sys.mem.io_regs.mut_(IoReg::If, |if_| {
-        if_.set_bit(type_.flag_idx(), 0);
-    });
+    let mut if_ = sys.mem.io_reg(IoReg::If);
+    if_.set_bit(type_.flag_idx(), 0);
+    sys.mem.set_io_reg(IoReg::If, if_);
 
     // 2 NOP cycles
     sys.cpu_delay_ticks += 2;
 
     let prev_pc = sys.regs.pc();
     let next_pc = type_.jump_addr();
-    call(sys, prev_pc, next_pc); // 3 cycles
+    call(sys, prev_pc, next_pc, FrameKind::Interrupt(type_)); // 3 cycles
 
     sys.cpu_delay_ticks += 3;
 }
@@ -1,3 +1,4 @@
+pub mod disasm;
 pub mod exec;
 mod exec_math;
 pub mod instr;
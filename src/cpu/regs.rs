@@ -1,6 +1,7 @@
 use crate::util::{
     bits::Bits,
     math::{join_16, split_16},
+    state::{SaveState, StateReader, StateWriter},
 };
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -177,3 +178,18 @@ impl CpuRegs {
         println!();
     }
 }
+
+impl SaveState for CpuRegs {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bytes(&self.regs8);
+        w.write_u16(self.sp);
+        w.write_u16(self.pc);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        r.read_bytes_into(&mut self.regs8)?;
+        self.sp = r.read_u16()?;
+        self.pc = r.read_u16()?;
+        return Ok(());
+    }
+}
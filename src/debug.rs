@@ -13,7 +13,8 @@ use crate::{
         interrupt::InterruptType,
         regs::CpuRegs,
     },
-    mem::{io_regs::IoReg, Addr},
+    debugger::symbols::{code_bank, Symbols},
+    mem::{bus::Bus, io_regs::IoReg, Addr},
     sys::Sys,
     util::{math::join_16, ring_buffer::RingBuffer},
 };
@@ -87,12 +88,12 @@ pub fn request_breakpoint() {
     debug_state().pending_breakpoint = true;
 }
 
-// pub fn take_pending_breakpoint() -> bool {
-//     let pending_breakpoint = debug_state().pending_breakpoint;
-//     debug_state().pending_breakpoint = false;
+pub fn take_pending_breakpoint() -> bool {
+    let pending_breakpoint = debug_state().pending_breakpoint;
+    debug_state().pending_breakpoint = false;
 
-//     return pending_breakpoint;
-// }
+    return pending_breakpoint;
+}
 
 pub fn push_serial_char(c: char) {
     debug_state().serial_out_log.push(c);
@@ -111,6 +112,7 @@ pub fn record_handled_interrupt(type_: InterruptType) {
 struct InstrRecord {
     cpu_tick_num: u64,
     addr: u16,
+    rom_bank: usize,
     instr: Instr,
     imm: ImmValue,
     regs: CpuRegs,
@@ -138,16 +140,16 @@ struct IoRegRecord {
 
 const DO_RECORD_NOP: bool = false;
 
-pub fn record_curr_instr(sys: &Sys) {
+pub fn record_curr_instr<B: Bus>(sys: &Sys<B>) {
     debug_state().total_instrs_executed += 1;
 
     let mut pc = sys.regs.pc();
     let addr = pc;
-    let mut op = sys.mem.read(pc);
+    let mut op = sys.mem.peek(pc);
     let mut has_cb_prefix = false;
     if op == Instr::CB_PREFIX {
         pc += 1;
-        op = sys.mem.read(pc);
+        op = sys.mem.peek(pc);
         has_cb_prefix = true;
     }
     let instr = match decode(op, has_cb_prefix) {
@@ -170,12 +172,12 @@ pub fn record_curr_instr(sys: &Sys) {
     let imm_value = match instr.imm_type() {
         ImmType::None => ImmValue::None,
         ImmType::Imm8 => {
-            let imm8 = sys.mem.read(pc + 1);
+            let imm8 = sys.mem.peek(pc + 1);
             ImmValue::Imm8(imm8)
         }
         ImmType::Imm16 => {
-            let lo = sys.mem.read(pc + 1);
-            let hi = sys.mem.read(pc + 2);
+            let lo = sys.mem.peek(pc + 1);
+            let hi = sys.mem.peek(pc + 2);
             let imm16 = join_16(hi, lo);
             ImmValue::Imm16(imm16)
         }
@@ -188,7 +190,7 @@ pub fn record_curr_instr(sys: &Sys) {
         let offset = range_min;
         let mut items = vec![];
         for addr in range_min..=range_max {
-            let data = sys.mem.read(addr);
+            let data = sys.mem.peek(addr);
             items.push(data);
         }
 
@@ -198,6 +200,7 @@ pub fn record_curr_instr(sys: &Sys) {
     let record = InstrRecord {
         cpu_tick_num: sys.cpu_clock.debug_total_ticks,
         addr,
+        rom_bank: sys.mem.rom_bank(),
         instr,
         imm: imm_value,
         regs: sys.regs.clone(),
@@ -270,7 +273,7 @@ pub fn print_system_state(sys: &Sys) {
             debug_state().instr_ring_buffer.len()
         );
         for record in debug_state().instr_ring_buffer.iter() {
-            print_instr_record(record);
+            print_instr_record(record, &sys.debugger.symbols);
         }
     }
 
@@ -327,17 +330,24 @@ pub fn print_system_state(sys: &Sys) {
     println!();
 }
 
-fn print_instr_record(record: &InstrRecord) {
+fn print_instr_record(record: &InstrRecord, symbols: &Symbols) {
     let InstrRecord {
         cpu_tick_num,
         addr,
+        rom_bank,
         instr,
         imm,
         regs,
         stack_record,
     } = record;
 
-    println!("  [${:0>4X} ({})] {:?}", addr, cpu_tick_num, instr);
+    match symbols.label(*addr, code_bank(*addr, *rom_bank)) {
+        Some(label) => println!(
+            "  [${:0>4X} <{}> ({})] {:?}",
+            addr, label, cpu_tick_num, instr
+        ),
+        None => println!("  [${:0>4X} ({})] {:?}", addr, cpu_tick_num, instr),
+    }
     match imm {
         ImmValue::None => {}
         ImmValue::Imm8(imm8) => println!("     imm8 = {:#02x} (u{}) (s{})", imm8, imm8, unsafe {
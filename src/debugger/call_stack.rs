@@ -0,0 +1,237 @@
+// A shadow of the call stack, kept from the calls, RSTs, interrupts and
+// returns the CPU executes, so the debugger can show a backtrace.
+//
+// Returns are matched to calls by SP rather than by order. A frame ends once
+// its return address is popped, or once SP moves back past it, so routines
+// that drop their own return address, jump tables that PUSH then RET, and
+// code that resets SP leave the stack in sync again as soon as they're done.
+
+use crate::{
+    cpu::interrupt::InterruptType,
+    mem::{bus::Bus, Addr},
+    sys::Sys,
+};
+
+use super::{
+    profiler::Profiler,
+    symbols::{code_bank, Symbols},
+};
+
+/// A routine's entry point, with the ROM bank it's in.
+#[derive(Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Routine {
+    pub addr: Addr,
+    pub bank: Option<usize>,
+}
+
+impl Routine {
+    /// The routine at `addr` while `rom_bank` is mapped.
+    pub fn new(addr: Addr, rom_bank: usize) -> Self {
+        Self {
+            addr,
+            bank: code_bank(addr, rom_bank),
+        }
+    }
+
+    /// Its symbol, or `BB:AAAA` without one.
+    pub fn name(&self, symbols: &Symbols) -> String {
+        if let Some(label) = symbols.label(self.addr, self.bank) {
+            return label;
+        }
+        return match self.bank {
+            Some(bank) => format!("{:02X}:{:04X}", bank, self.addr),
+            None => format!("${:04X}", self.addr),
+        };
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FrameKind {
+    /// `CALL` or `RST`.
+    Call,
+    Interrupt(InterruptType),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Frame {
+    pub routine: Routine,
+    pub kind: FrameKind,
+
+    /// Where it returns to, in the bank mapped when it was entered.
+    pub return_addr: Addr,
+    pub return_bank: Option<usize>,
+
+    /// SP once the return address was pushed.
+    pub sp: Addr,
+}
+
+pub struct CallStack {
+    /// The outermost frame first.
+    frames: Vec<Frame>,
+}
+
+impl CallStack {
+    pub fn new() -> Self {
+        Self { frames: vec![] }
+    }
+
+    /// The outermost frame first.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Pushes a frame, first dropping any whose return address it replaced.
+    /// Returns how many were dropped.
+    pub fn enter(&mut self, frame: Frame) -> usize {
+        let dropped = self.unwind(frame.sp);
+        self.frames.push(frame);
+        return dropped;
+    }
+
+    /// Drops the frames whose return address was at or below `sp`, i.e. the
+    /// ones a return popping from `sp` ends. Returns how many were dropped.
+    pub fn unwind(&mut self, sp: Addr) -> usize {
+        let len = self.frames.len();
+        while self.frames.last().is_some_and(|frame| frame.sp <= sp) {
+            self.frames.pop();
+        }
+        return len - self.frames.len();
+    }
+}
+
+/// Call once the CPU has pushed `return_addr` and jumped to the routine at
+/// PC.
+pub fn enter_routine<B: Bus>(sys: &mut Sys<B>, return_addr: Addr, kind: FrameKind) {
+    let rom_bank = sys.mem.rom_bank();
+    let frame = Frame {
+        routine: Routine::new(sys.regs.pc(), rom_bank),
+        kind,
+        return_addr,
+        return_bank: code_bank(return_addr, rom_bank),
+        sp: sys.regs.sp(),
+    };
+
+    let debugger = &mut sys.debugger;
+    let dropped = debugger.call_stack.enter(frame);
+    if let Some(profiler) = running_profiler(&mut debugger.profiler) {
+        profiler.leave(dropped);
+        profiler.enter(frame.routine);
+    }
+}
+
+/// Call once the CPU has popped a return address from `sp`.
+pub fn leave_routine<B: Bus>(sys: &mut Sys<B>, sp: Addr) {
+    let debugger = &mut sys.debugger;
+    let dropped = debugger.call_stack.unwind(sp);
+    if let Some(profiler) = running_profiler(&mut debugger.profiler) {
+        profiler.leave(dropped);
+    }
+}
+
+/// Empties the stack, e.g. when the system resets.
+pub fn clear_call_stack<B: Bus>(sys: &mut Sys<B>) {
+    let debugger = &mut sys.debugger;
+    let depth = debugger.call_stack.frames().len();
+    debugger.call_stack.clear();
+    if let Some(profiler) = running_profiler(&mut debugger.profiler) {
+        profiler.leave(depth);
+    }
+}
+
+fn running_profiler(profiler: &mut Option<Profiler>) -> Option<&mut Profiler> {
+    profiler.as_mut().filter(|profiler| profiler.is_running())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cpu::{exec::execute_next_instr, regs::CpuReg16},
+        other::mode::CompatibilityMode,
+        test::{
+            bus::FlatBus,
+            headless::{headless_options, initialize_headless_debug, lock_headless},
+        },
+    };
+
+    use super::*;
+
+    fn frame(addr: Addr, sp: Addr) -> Frame {
+        Frame {
+            routine: Routine::new(addr, 1),
+            kind: FrameKind::Call,
+            return_addr: 0x0150,
+            return_bank: Some(0),
+            sp,
+        }
+    }
+
+    #[test]
+    fn test_unwind() {
+        let mut stack = CallStack::new();
+        assert_eq!(stack.enter(frame(0x4000, 0xFFFC)), 0);
+        assert_eq!(stack.enter(frame(0x4100, 0xFFFA)), 0);
+        assert_eq!(stack.enter(frame(0x4200, 0xFFF8)), 0);
+
+        // A PUSH then RET below the innermost frame doesn't end it.
+        assert_eq!(stack.unwind(0xFFF6), 0);
+        assert_eq!(stack.unwind(0xFFF8), 1);
+
+        // $4100 dropped its return address, so the next call replaces it.
+        assert_eq!(stack.enter(frame(0x4300, 0xFFFA)), 1);
+        assert_eq!(
+            stack
+                .frames()
+                .iter()
+                .map(|frame| frame.routine.addr)
+                .collect::<Vec<_>>(),
+            vec![0x4000, 0x4300]
+        );
+
+        // Resetting SP ends everything.
+        assert_eq!(stack.unwind(0xFFFE), 2);
+        assert!(stack.frames().is_empty());
+    }
+
+    #[test]
+    fn test_routine_name() {
+        let symbols = Symbols::parse("01:4000 LoadLevel").unwrap();
+        assert_eq!(Routine::new(0x4000, 1).name(&symbols), "LoadLevel");
+        assert_eq!(Routine::new(0x4000, 2).name(&symbols), "02:4000");
+        assert_eq!(Routine::new(0x0040, 2).name(&symbols), "00:0040");
+        assert_eq!(Routine::new(0xC000, 2).name(&symbols), "$C000");
+    }
+
+    #[test]
+    fn test_calls_and_returns() {
+        let _lock = lock_headless();
+        initialize_headless_debug();
+        let mut bus = FlatBus::new();
+        // 0000: CALL $0010
+        // 0010: RST $18
+        // 0018: RET
+        bus.ram[0x0000..0x0003].copy_from_slice(&[0xCD, 0x10, 0x00]);
+        bus.ram[0x0010] = 0xDF;
+        bus.ram[0x0018] = 0xC9;
+        let mut sys = Sys::with_bus(headless_options(None), CompatibilityMode::DmgOnly, bus);
+        sys.regs.set_16(CpuReg16::SP, 0xFFFE);
+        sys.regs.set_16(CpuReg16::PC, 0x0000);
+
+        execute_next_instr(&mut sys);
+        execute_next_instr(&mut sys);
+        let frames = sys.debugger.call_stack.frames();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].routine, Routine::new(0x0010, 1));
+        assert_eq!(frames[0].return_addr, 0x0003);
+        assert_eq!(frames[0].sp, 0xFFFC);
+        assert_eq!(frames[1].routine, Routine::new(0x0018, 1));
+        assert_eq!(frames[1].return_addr, 0x0011);
+
+        execute_next_instr(&mut sys);
+        assert_eq!(sys.regs.pc(), 0x0011);
+        assert_eq!(sys.debugger.call_stack.frames().len(), 1);
+    }
+}
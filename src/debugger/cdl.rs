@@ -0,0 +1,271 @@
+// The code/data log (CDL) records what each ROM byte was used for while the
+// game ran. The file has a byte of flags per ROM byte, in ROM order:
+//
+//   $01  Executed as an opcode (a $CB prefix and the opcode after it both)
+//   $02  Read as an operand
+//   $04  Read as data
+//   $08  Read by OAM or VRAM DMA
+//
+// A byte can have several, e.g. an operand that's also read as data. Logging
+// into an existing file adds to it, so coverage builds up over several runs.
+// Bytes with no flags were never used, which is what the coverage report
+// lists: the banks, and the ROM symbols, the game never touched.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{
+    cart::{cart::Cart, consts::ROM_BANK_SIZE},
+    mem::sections::MemSection,
+    sys::Sys,
+};
+
+use super::symbols::{Symbol, Symbols};
+
+pub const OPCODE: u8 = 0x01;
+pub const OPERAND: u8 = 0x02;
+pub const DATA: u8 = 0x04;
+pub const DMA: u8 = 0x08;
+
+/// What a read is for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReadUse {
+    Opcode,
+    Operand,
+    Data,
+    Dma,
+}
+
+impl ReadUse {
+    pub fn flag(self) -> u8 {
+        match self {
+            ReadUse::Opcode => OPCODE,
+            ReadUse::Operand => OPERAND,
+            ReadUse::Data => DATA,
+            ReadUse::Dma => DMA,
+        }
+    }
+}
+
+/// Is the byte code, going by its flags?
+pub fn is_code(flags: u8) -> bool {
+    flags & (OPCODE | OPERAND) != 0
+}
+
+/// Is the byte only ever read as data, going by its flags?
+pub fn is_data(flags: u8) -> bool {
+    !is_code(flags) && flags & (DATA | DMA) != 0
+}
+
+/// How much of a bank was used.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BankCoverage {
+    pub bank: usize,
+    pub code_bytes: usize,
+    pub data_bytes: usize,
+    pub unused_bytes: usize,
+}
+
+pub struct CodeDataLog {
+    flags: Vec<u8>,
+}
+
+impl CodeDataLog {
+    pub fn new(rom_size: usize) -> Self {
+        Self {
+            flags: vec![0; rom_size],
+        }
+    }
+
+    /// Reads a log made for a ROM of `rom_size` bytes.
+    pub fn load(path: &Path, rom_size: usize) -> Result<Self, String> {
+        let flags =
+            fs::read(path).map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+        if flags.len() != rom_size {
+            return Err(format!(
+                "{} is for a ROM of {} bytes, not {}.",
+                path.display(),
+                flags.len(),
+                rom_size
+            ));
+        }
+        return Ok(Self { flags });
+    }
+
+    /// Reads the log at `path` if there is one, or starts a new one.
+    pub fn load_or_new(path: &Path, rom_size: usize) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::new(rom_size));
+        }
+        return Self::load(path, rom_size);
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        return fs::write(path, &self.flags)
+            .map_err(|err| format!("Unable to write {}: {}", path.display(), err));
+    }
+
+    /// The flags for each ROM byte, in ROM order.
+    pub fn flags(&self) -> &[u8] {
+        &self.flags
+    }
+
+    #[inline]
+    pub fn log(&mut self, offset: usize, use_: ReadUse) {
+        if let Some(flags) = self.flags.get_mut(offset) {
+            *flags |= use_.flag();
+        }
+    }
+
+    pub fn bank_coverage(&self) -> Vec<BankCoverage> {
+        return self
+            .flags
+            .chunks(ROM_BANK_SIZE)
+            .enumerate()
+            .map(|(bank, flags)| {
+                let code_bytes = flags.iter().filter(|&&flags| is_code(flags)).count();
+                let data_bytes = flags.iter().filter(|&&flags| is_data(flags)).count();
+                BankCoverage {
+                    bank,
+                    code_bytes,
+                    data_bytes,
+                    unused_bytes: flags.len() - code_bytes - data_bytes,
+                }
+            })
+            .collect();
+    }
+
+    /// The ROM symbols at bytes in `cart`'s ROM that were never used.
+    pub fn unused_symbols<'a>(&self, symbols: &'a Symbols, cart: &Cart) -> Vec<&'a Symbol> {
+        return symbols
+            .list()
+            .iter()
+            .filter(|symbol| MemSection::from_abs_addr(symbol.addr) == MemSection::CartRom)
+            .filter(|symbol| {
+                let offset = cart.banked_rom_offset(symbol.bank, symbol.addr);
+                self.flags.get(offset) == Some(&0)
+            })
+            .collect();
+    }
+
+    /// Writes a line per bank with how much of it was used, then the
+    /// symbols that weren't.
+    pub fn write_report(
+        &self,
+        symbols: &Symbols,
+        cart: &Cart,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        let percent = |bytes: usize| 100.0 * bytes as f64 / ROM_BANK_SIZE as f64;
+
+        writeln!(out, "  Bank    Code    Data  Unused")?;
+        for coverage in self.bank_coverage() {
+            writeln!(
+                out,
+                "    {:02X}  {:>5.1}%  {:>5.1}%  {:>5.1}%{}",
+                coverage.bank,
+                percent(coverage.code_bytes),
+                percent(coverage.data_bytes),
+                percent(coverage.unused_bytes),
+                if coverage.unused_bytes == ROM_BANK_SIZE {
+                    "  never used"
+                } else {
+                    ""
+                }
+            )?;
+        }
+
+        let unused = self.unused_symbols(symbols, cart);
+        if !unused.is_empty() {
+            writeln!(out, "Symbols never used:")?;
+            for symbol in unused {
+                writeln!(
+                    out,
+                    "  {:02X}:{:04X}  {}",
+                    symbol.bank, symbol.addr, symbol.name
+                )?;
+            }
+        }
+        return Ok(());
+    }
+}
+
+/// Starts logging into `path`, adding to the log there if there is one.
+pub fn start_cdl(sys: &mut Sys, path: &Path) -> Result<(), String> {
+    let log = CodeDataLog::load_or_new(path, sys.mem.cart.rom_size())?;
+    sys.mem.code_data_log = Some(log);
+    return Ok(());
+}
+
+/// Saves the log to `path`.
+pub fn save_cdl(sys: &Sys, path: &Path) -> Result<(), String> {
+    match &sys.mem.code_data_log {
+        Some(log) => log.save(path),
+        None => Err("Not logging code and data.".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::test::headless::{create_headless_sys, lock_headless};
+
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let cart = Cart::load_from("assets/files/custom_roms/ld_r8_r8/rom.gb", false).unwrap();
+        let mut log = CodeDataLog::new(2 * ROM_BANK_SIZE);
+        log.log(0x0150, ReadUse::Opcode);
+        log.log(0x0151, ReadUse::Operand);
+        log.log(0x0151, ReadUse::Data);
+        log.log(0x0200, ReadUse::Dma);
+        log.log(2 * ROM_BANK_SIZE, ReadUse::Data);
+        assert_eq!(log.flags()[0x0151], OPERAND | DATA);
+
+        let symbols =
+            Symbols::parse("00:0150 Main\n00:0160 Unused\n01:4000 Far\n00:C000 wRam").unwrap();
+        let mut out = vec![];
+        log.write_report(&symbols, &cart, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "  Bank    Code    Data  Unused
+    00    0.0%    0.0%  100.0%
+    01    0.0%    0.0%  100.0%  never used
+Symbols never used:
+  00:0160  Unused
+  01:4000  Far
+"
+        );
+        assert_eq!(
+            log.bank_coverage()[0],
+            BankCoverage {
+                bank: 0,
+                code_bytes: 2,
+                data_bytes: 1,
+                unused_bytes: ROM_BANK_SIZE - 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_log_rom() {
+        let _lock = lock_headless();
+        let rom = Path::new("assets/files/custom_roms/ld_r8_r8/rom.gb");
+        let mut sys = create_headless_sys(rom, None).unwrap();
+        sys.mem.code_data_log = Some(CodeDataLog::new(sys.mem.cart.rom_size()));
+        for _ in 0..100 {
+            sys.run_one_m_cycle();
+        }
+
+        // `ld b, 1` at $0100.
+        let flags = sys.mem.code_data_log.as_ref().unwrap().flags();
+        assert_eq!(flags[0x0100], OPCODE);
+        assert_eq!(flags[0x0101], OPERAND);
+        assert_eq!(flags[0x3FFF], 0);
+    }
+}
@@ -0,0 +1,857 @@
+// The debugger is driven by commands typed into the terminal. They're read
+// on a separate thread, so the window keeps running while waiting for one.
+// Addresses, banks and lengths are hex, with or without a `$` or `0x`
+// prefix. IO registers can be named instead of an address (e.g. `lcdc`), and
+// so can symbols from the ROM's symbol file, with an optional hex offset
+// (e.g. `Main` or `Main+10`). A breakpoint on a symbol in switchable ROM is
+// limited to the symbol's bank.
+// Conditions and tracepoint messages are expressions, as described in
+// expr.rs, where numbers are decimal unless prefixed.
+
+use std::{
+    io,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use strum::IntoEnumIterator;
+
+use crate::{
+    cpu::{
+        disasm::{disassemble, disassemble_around},
+        interrupt::InterruptType,
+        regs::{CpuFlag, CpuReg16},
+    },
+    mem::{io_regs::IoReg, Addr},
+    sys::Sys,
+};
+
+use super::{
+    call_stack::FrameKind,
+    cdl::{save_cdl, CodeDataLog},
+    debugger::{resume, BreakReason, Breakpoint, Step},
+    expr::{Expr, Message},
+    profiler::{save_profile, start_profile},
+    symbols::{code_bank, label_at, mapped_bank, Symbols},
+    trace::{start_trace, stop_trace},
+    watch::{AccessKind, Watchpoint},
+};
+
+const HELP: &str = "\
+Debugger commands:
+  b, break [BANK:]ADDR [if COND]
+                               Break when PC reaches ADDR (in ROM bank BANK)
+                               and COND is true, e.g. if a == 3 && ly >= 144
+  t, trace [BANK:]ADDR MSG     Print MSG when PC reaches ADDR, without breaking.
+                               Values go in braces, e.g. HL={hl:x} LY={ly}
+  cond N [COND]                Set or clear the condition on breakpoint N
+  ignore N COUNT               Skip the next COUNT hits on breakpoint N
+  w, watch ADDR|REG [r|w|rw]   Break when ADDR or an IO register is accessed [default: rw]
+  l, list                      List breakpoints and watchpoints
+  d, delete b N | w N | all    Delete a breakpoint, a watchpoint, or all of them
+  c, continue                  Resume running
+  p, pause                     Pause
+  s, step                      Run one instruction
+  n, next                      Run one instruction, stepping over calls
+  o, out                       Run until the current function returns
+  u, until ADDR                Run until PC reaches ADDR
+  r, regs                      Show the CPU and PPU state
+  m, mem ADDR [LEN]            Show memory [default LEN: 40]
+  io                           Show the IO registers
+  disasm [ADDR] [N]            Disassemble N instructions from ADDR [default: around PC, 10]
+  swbreak on|off               Break when the ROM executes LD B,B
+  tracelog [PATH|off]          Log every instruction to PATH, stop logging, or show the log
+  bt, backtrace                Show the routines running now, from the calls made
+  profile on|off               Start counting cycles per routine, or stop
+  profile                      Show the routines that took the most cycles
+  profile save PATH            Save the profile as folded stacks, for flame graphs
+  cdl on                       Start logging which ROM bytes are code and which are data
+  cdl                          Show how much of each ROM bank has been used
+  cdl save PATH                Save the code/data log
+  h, help                      Show this message
+
+ADDR can also be a symbol from the ROM's .sym file, e.g. Main or Main+10.";
+
+const DEFAULT_DUMP_LEN: usize = 0x40;
+const DUMP_ROW_LEN: usize = 0x10;
+const DEFAULT_DISASM_COUNT: usize = 10;
+const DISASM_BEFORE_PC: usize = 3;
+const PROFILE_REPORT_LEN: usize = 20;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Command {
+    Break(Breakpoint),
+    Condition {
+        idx: usize,
+        condition: Option<Expr>,
+    },
+    Ignore {
+        idx: usize,
+        count: u32,
+    },
+    Watch(Watchpoint),
+    List,
+    DeleteBreakpoint(usize),
+    DeleteWatchpoint(usize),
+    DeleteAll,
+    Continue,
+    Pause,
+    Step(Step),
+    Regs,
+    Mem {
+        addr: Addr,
+        len: usize,
+    },
+    Io,
+    Disasm {
+        addr: Option<Addr>,
+        count: usize,
+    },
+    SoftwareBreak(bool),
+    /// Starts a trace log at the path, or stops it.
+    TraceLog(Option<PathBuf>),
+    TraceLogStatus,
+    Backtrace,
+    Profile(bool),
+    ProfileReport,
+    ProfileSave(PathBuf),
+    StartCdl,
+    CdlReport,
+    SaveCdl(PathBuf),
+    Help,
+}
+
+/// Reads debugger commands from the terminal.
+pub struct Console {
+    lines: Receiver<String>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        println!("Debugger ready. Type help for a list of commands.");
+        return Self { lines };
+    }
+
+    /// Runs the commands typed since the last update.
+    pub fn update(&self, sys: &mut Sys) {
+        while let Ok(line) = self.lines.try_recv() {
+            match parse_command(&line, &sys.debugger.symbols) {
+                Ok(Some(command)) => run_command(sys, command),
+                Ok(None) => {}
+                Err(msg) => println!("{}", msg),
+            }
+        }
+    }
+}
+
+/// Prints why the system stopped, and where.
+pub fn print_break(sys: &Sys, reason: BreakReason) {
+    match reason {
+        BreakReason::Breakpoint(idx) => {
+            let breakpoint = &sys.debugger.breakpoints[idx];
+            println!(
+                "Breakpoint b{} at {}, hit {} times.",
+                idx,
+                format_location(sys, breakpoint),
+                breakpoint.hit_count
+            );
+        }
+        BreakReason::Watchpoint(hit) => {
+            let kind = match hit.kind {
+                AccessKind::Read => "Read",
+                AccessKind::Write => "Write",
+            };
+            println!(
+                "{} of ${:02X} at {}.",
+                kind,
+                hit.data,
+                format_addr(sys, hit.addr)
+            );
+        }
+        BreakReason::Software => println!("LD B,B."),
+        BreakReason::Step => {}
+    }
+
+    print_location(sys);
+}
+
+fn run_command(sys: &mut Sys, command: Command) {
+    match command {
+        Command::Break(breakpoint) => {
+            println!(
+                "Breakpoint b{}: {}",
+                sys.debugger.breakpoints.len(),
+                format_breakpoint(sys, &breakpoint)
+            );
+            sys.debugger.breakpoints.push(breakpoint);
+        }
+        Command::Condition { idx, condition } => match sys.debugger.breakpoints.get_mut(idx) {
+            Some(breakpoint) => breakpoint.condition = condition,
+            None => println!("No breakpoint b{}.", idx),
+        },
+        Command::Ignore { idx, count } => match sys.debugger.breakpoints.get_mut(idx) {
+            Some(breakpoint) => breakpoint.ignore_count = count,
+            None => println!("No breakpoint b{}.", idx),
+        },
+        Command::Watch(watchpoint) => {
+            sys.mem.watchpoints.add(watchpoint);
+            println!(
+                "Watchpoint w{} on {}.",
+                sys.mem.watchpoints.list().len() - 1,
+                format_watchpoint(sys, watchpoint)
+            );
+        }
+        Command::List => {
+            for (idx, breakpoint) in sys.debugger.breakpoints.iter().enumerate() {
+                println!("  b{}  {}", idx, format_breakpoint(sys, breakpoint));
+            }
+            for (idx, watchpoint) in sys.mem.watchpoints.list().iter().enumerate() {
+                println!("  w{}  {}", idx, format_watchpoint(sys, *watchpoint));
+            }
+        }
+        Command::DeleteBreakpoint(idx) => {
+            if idx < sys.debugger.breakpoints.len() {
+                sys.debugger.breakpoints.remove(idx);
+            } else {
+                println!("No breakpoint b{}.", idx);
+            }
+        }
+        Command::DeleteWatchpoint(idx) => {
+            if sys.mem.watchpoints.remove(idx).is_none() {
+                println!("No watchpoint w{}.", idx);
+            }
+        }
+        Command::DeleteAll => {
+            sys.debugger.breakpoints.clear();
+            sys.mem.watchpoints.clear();
+        }
+        Command::Continue => resume(sys, None),
+        Command::Pause => {
+            sys.emu.pause();
+            print_location(sys);
+        }
+        Command::Step(step) => resume(sys, Some(step)),
+        Command::Regs => print_regs(sys),
+        Command::Mem { addr, len } => print_mem(sys, addr, len),
+        Command::Io => print_io_regs(sys),
+        Command::Disasm { addr, count } => print_disassembly(sys, addr, count),
+        Command::SoftwareBreak(is_enabled) => sys.debugger.break_on_software = is_enabled,
+        Command::TraceLog(Some(path)) => match start_trace(sys, &path) {
+            Ok(()) => println!("Tracing to {}.", path.display()),
+            Err(msg) => println!("{}", msg),
+        },
+        Command::TraceLog(None) => {
+            print_trace_log(sys);
+            stop_trace(sys);
+        }
+        Command::TraceLogStatus => print_trace_log(sys),
+        Command::Backtrace => print_backtrace(sys),
+        Command::Profile(true) => {
+            start_profile(sys);
+            println!("Profiling.");
+        }
+        Command::Profile(false) => match &mut sys.debugger.profiler {
+            Some(profiler) => {
+                profiler.stop();
+                print_profile(sys);
+            }
+            None => println!("Not profiling."),
+        },
+        Command::ProfileReport => print_profile(sys),
+        Command::ProfileSave(path) => match save_profile(sys, &path) {
+            Ok(()) => println!("Saved the profile to {}.", path.display()),
+            Err(msg) => println!("{}", msg),
+        },
+        Command::StartCdl => {
+            let log = CodeDataLog::new(sys.mem.cart.rom_size());
+            sys.mem.code_data_log = Some(log);
+            println!("Logging code and data.");
+        }
+        Command::CdlReport => match &sys.mem.code_data_log {
+            Some(log) => {
+                if let Err(err) =
+                    log.write_report(&sys.debugger.symbols, &sys.mem.cart, &mut io::stdout())
+                {
+                    println!("{}", err);
+                }
+            }
+            None => println!("Not logging code and data."),
+        },
+        Command::SaveCdl(path) => match save_cdl(sys, &path) {
+            Ok(()) => println!("Saved the code/data log to {}.", path.display()),
+            Err(msg) => println!("{}", msg),
+        },
+        Command::Help => println!("{}", HELP),
+    }
+}
+
+/// Parses a line typed into the console. Blank lines are ignored.
+fn parse_command(line: &str, symbols: &Symbols) -> Result<Option<Command>, String> {
+    let mut words = line.split_whitespace();
+    let Some(name) = words.next() else {
+        return Ok(None);
+    };
+    let args = words.collect::<Vec<_>>();
+
+    let command = match (name.to_lowercase().as_str(), args.as_slice()) {
+        ("b" | "break", [location]) => Command::Break(parse_breakpoint(location, symbols)?),
+        ("b" | "break", [location, "if", condition @ ..]) => {
+            let mut breakpoint = parse_breakpoint(location, symbols)?;
+            breakpoint.condition = Some(Expr::parse(&condition.join(" "))?);
+            Command::Break(breakpoint)
+        }
+        ("t" | "trace", [location, message @ ..]) if !message.is_empty() => {
+            let mut breakpoint = parse_breakpoint(location, symbols)?;
+            breakpoint.message = Some(Message::parse(&message.join(" "))?);
+            Command::Break(breakpoint)
+        }
+        ("cond", [idx]) => Command::Condition {
+            idx: parse_dec(idx)?,
+            condition: None,
+        },
+        ("cond", [idx, condition @ ..]) => Command::Condition {
+            idx: parse_dec(idx)?,
+            condition: Some(Expr::parse(&condition.join(" "))?),
+        },
+        ("ignore", [idx, count]) => Command::Ignore {
+            idx: parse_dec(idx)?,
+            count: parse_dec(count)? as u32,
+        },
+        ("w" | "watch", [target, kind @ ..]) if kind.len() <= 1 => {
+            let (on_read, on_write) = match kind.first().copied() {
+                None | Some("rw") => (true, true),
+                Some("r") => (true, false),
+                Some("w") => (false, true),
+                Some(kind) => return Err(format!("Unknown access: {}.", kind)),
+            };
+            Command::Watch(Watchpoint {
+                addr: parse_target(target, symbols)?,
+                on_read,
+                on_write,
+            })
+        }
+        ("l" | "list", []) => Command::List,
+        ("d" | "delete", ["all"]) => Command::DeleteAll,
+        ("d" | "delete", ["b", idx]) => Command::DeleteBreakpoint(parse_dec(idx)?),
+        ("d" | "delete", ["w", idx]) => Command::DeleteWatchpoint(parse_dec(idx)?),
+        ("c" | "continue", []) => Command::Continue,
+        ("p" | "pause", []) => Command::Pause,
+        ("s" | "step", []) => Command::Step(Step::Into),
+        ("n" | "next", []) => Command::Step(Step::Over),
+        ("o" | "out", []) => Command::Step(Step::Out),
+        ("u" | "until", [addr]) => Command::Step(Step::To(parse_location(addr, symbols)?.0)),
+        ("r" | "regs", []) => Command::Regs,
+        ("m" | "mem", [addr]) => Command::Mem {
+            addr: parse_target(addr, symbols)?,
+            len: DEFAULT_DUMP_LEN,
+        },
+        ("m" | "mem", [addr, len]) => Command::Mem {
+            addr: parse_target(addr, symbols)?,
+            len: parse_hex(len)? as usize,
+        },
+        ("io", []) => Command::Io,
+        ("disasm", []) => Command::Disasm {
+            addr: None,
+            count: DEFAULT_DISASM_COUNT,
+        },
+        ("disasm", [addr]) => Command::Disasm {
+            addr: Some(parse_location(addr, symbols)?.0),
+            count: DEFAULT_DISASM_COUNT,
+        },
+        ("disasm", [addr, count]) => Command::Disasm {
+            addr: Some(parse_location(addr, symbols)?.0),
+            count: parse_dec(count)?,
+        },
+        ("swbreak", ["on"]) => Command::SoftwareBreak(true),
+        ("swbreak", ["off"]) => Command::SoftwareBreak(false),
+        ("tracelog", []) => Command::TraceLogStatus,
+        ("tracelog", ["off"]) => Command::TraceLog(None),
+        ("tracelog", path) => Command::TraceLog(Some(PathBuf::from(path.join(" ")))),
+        ("bt" | "backtrace", []) => Command::Backtrace,
+        ("profile", []) => Command::ProfileReport,
+        ("profile", ["on"]) => Command::Profile(true),
+        ("profile", ["off"]) => Command::Profile(false),
+        ("profile", ["save", path @ ..]) if !path.is_empty() => {
+            Command::ProfileSave(PathBuf::from(path.join(" ")))
+        }
+        ("cdl", []) => Command::CdlReport,
+        ("cdl", ["on"]) => Command::StartCdl,
+        ("cdl", ["save", path @ ..]) if !path.is_empty() => {
+            Command::SaveCdl(PathBuf::from(path.join(" ")))
+        }
+        ("h" | "help", []) => Command::Help,
+        _ => {
+            return Err(format!(
+                "Invalid command: {}. Type help for a list.",
+                line.trim()
+            ))
+        }
+    };
+
+    return Ok(Some(command));
+}
+
+/// A location, or `BANK:ADDR` for an address in switchable ROM.
+fn parse_breakpoint(s: &str, symbols: &Symbols) -> Result<Breakpoint, String> {
+    let Some((bank, addr)) = s.split_once(':') else {
+        let (addr, bank) = parse_location(s, symbols)?;
+        let bank = bank.filter(|_| (0x4000..=0x7FFF).contains(&addr));
+        return Ok(Breakpoint::new(addr, bank));
+    };
+
+    let addr = parse_addr(addr)?;
+    if !(0x4000..=0x7FFF).contains(&addr) {
+        return Err(format!(
+            "${:04X} isn't in switchable ROM, so it has no bank.",
+            addr
+        ));
+    }
+    return Ok(Breakpoint::new(addr, Some(parse_hex(bank)? as usize)));
+}
+
+/// A location, or the name of an IO register.
+fn parse_target(s: &str, symbols: &Symbols) -> Result<Addr, String> {
+    let reg = IoReg::iter().find(|reg| format!("{:?}", reg).eq_ignore_ascii_case(s));
+    return match reg {
+        Some(reg) => Ok(reg.as_addr()),
+        None => Ok(parse_location(s, symbols)?.0),
+    };
+}
+
+/// A symbol, with an optional offset, or an address. The bank is the
+/// symbol's.
+pub fn parse_location(s: &str, symbols: &Symbols) -> Result<(Addr, Option<usize>), String> {
+    let (name, offset) = match s.split_once('+') {
+        Some((name, offset)) => (name, parse_hex(offset)?),
+        None => (s, 0),
+    };
+    let Some(symbol) = symbols.find(name) else {
+        return Ok((parse_addr(s)?, None));
+    };
+
+    let addr = Addr::try_from(symbol.addr as u32 + offset)
+        .map_err(|_| format!("{} is past the end of memory.", s))?;
+    return Ok((addr, Some(symbol.bank)));
+}
+
+fn parse_addr(s: &str) -> Result<Addr, String> {
+    let value = parse_hex(s)?;
+    return Addr::try_from(value).map_err(|_| format!("Invalid address: {}.", s));
+}
+
+fn parse_hex(s: &str) -> Result<u32, String> {
+    let digits = s
+        .strip_prefix('$')
+        .or_else(|| s.strip_prefix("0x"))
+        .unwrap_or(s);
+    return u32::from_str_radix(digits, 16).map_err(|_| format!("Invalid hex number: {}.", s));
+}
+
+fn parse_dec(s: &str) -> Result<usize, String> {
+    s.parse().map_err(|_| format!("Invalid number: {}.", s))
+}
+
+/// The address, and its symbol if there is one.
+fn format_location(sys: &Sys, breakpoint: &Breakpoint) -> String {
+    let Breakpoint { addr, bank, .. } = *breakpoint;
+    let s = match bank {
+        Some(bank) => format!("{:02X}:{:04X}", bank, addr),
+        None => format!("${:04X}", addr),
+    };
+
+    // Without a bank, there's no telling which symbol in switchable ROM.
+    let label = match (bank, addr) {
+        (None, 0x4000..=0x7FFF) => None,
+        _ => sys
+            .debugger
+            .symbols
+            .label(addr, bank.or(code_bank(addr, 0))),
+    };
+    return match label {
+        Some(label) => format!("{} <{}>", s, label),
+        None => s,
+    };
+}
+
+/// The location, and whatever's been set on the breakpoint.
+fn format_breakpoint(sys: &Sys, breakpoint: &Breakpoint) -> String {
+    let mut s = format_location(sys, breakpoint);
+    if let Some(condition) = &breakpoint.condition {
+        s += &format!(" if {}", condition);
+    }
+    if let Some(message) = &breakpoint.message {
+        s += &format!(" trace \"{}\"", message);
+    }
+    if breakpoint.hit_count > 0 {
+        s += &format!(", hit {} times", breakpoint.hit_count);
+    }
+    if breakpoint.ignore_count > 0 {
+        s += &format!(", ignoring {} more", breakpoint.ignore_count);
+    }
+    return s;
+}
+
+fn format_watchpoint(sys: &Sys, watchpoint: Watchpoint) -> String {
+    let kind = match (watchpoint.on_read, watchpoint.on_write) {
+        (true, true) => "rw",
+        (true, false) => "r",
+        _ => "w",
+    };
+    return format!("{} ({})", format_addr(sys, watchpoint.addr), kind);
+}
+
+/// The address, and the IO register or symbol there if there is one.
+fn format_addr(sys: &Sys, addr: Addr) -> String {
+    if let Some(reg) = IoReg::iter().find(|reg| reg.as_addr() == addr) {
+        return format!("${:04X} {:?}", addr, reg);
+    }
+    return match label_at(sys, addr) {
+        Some(label) => format!("${:04X} <{}>", addr, label),
+        None => format!("${:04X}", addr),
+    };
+}
+
+/// Prints the instruction at PC, with the ROM bank it's in and the symbol
+/// it's in.
+fn print_location(sys: &Sys) {
+    let pc = sys.regs.pc();
+    let disasm = disassemble(pc, |addr| sys.mem.peek(addr), |addr| label_at(sys, addr));
+    match label_at(sys, pc) {
+        Some(label) => println!(
+            "  {} <{}>  {}",
+            format_code_addr(sys, pc),
+            label,
+            disasm.text
+        ),
+        None => println!("  {}  {}", format_code_addr(sys, pc), disasm.text),
+    }
+}
+
+/// Prints `count` instructions from `addr`, or the ones around PC.
+fn print_disassembly(sys: &Sys, addr: Option<Addr>, count: usize) {
+    let read = |addr: Addr| sys.mem.peek(addr);
+    let label = |addr: Addr| label_at(sys, addr);
+    let lines = match addr {
+        Some(mut addr) => (0..count)
+            .map(|_| {
+                let disasm = disassemble(addr, read, label);
+                let line = (addr, disasm);
+                addr = addr.wrapping_add(line.1.size);
+                line
+            })
+            .collect(),
+        None => disassemble_around(sys.regs.pc(), DISASM_BEFORE_PC, count, read, label),
+    };
+
+    for (addr, disasm) in lines {
+        if let Some(name) = sys.debugger.symbols.name(addr, mapped_bank(sys, addr)) {
+            println!("{}:", name);
+        }
+
+        let marker = if addr == sys.regs.pc() { ">" } else { " " };
+        println!(
+            "{} {}  {}",
+            marker,
+            format_code_addr(sys, addr),
+            disasm.text
+        );
+    }
+}
+
+/// `BB:AAAA` for an address in ROM, with the bank mapped there.
+fn format_code_addr(sys: &Sys, addr: Addr) -> String {
+    match addr {
+        0x0000..=0x3FFF => format!("00:{:04X}", addr),
+        0x4000..=0x7FFF => format!("{:02X}:{:04X}", sys.mem.cart.rom_bank(), addr),
+        _ => format!("${:04X}", addr),
+    }
+}
+
+fn print_regs(sys: &Sys) {
+    use CpuReg16::*;
+
+    let regs = &sys.regs;
+    let flags = [
+        (CpuFlag::Z, 'Z'),
+        (CpuFlag::N, 'N'),
+        (CpuFlag::H, 'H'),
+        (CpuFlag::C, 'C'),
+    ]
+    .map(|(flag, name)| if regs.get_flag(flag) { name } else { '-' })
+    .iter()
+    .collect::<String>();
+
+    println!(
+        "  AF={:04X} BC={:04X} DE={:04X} HL={:04X} SP={:04X} PC={:04X} {}",
+        regs.get_16(AF),
+        regs.get_16(BC),
+        regs.get_16(DE),
+        regs.get_16(HL),
+        regs.sp(),
+        regs.pc(),
+        flags
+    );
+    println!(
+        "  IME={} IE={:02X} IF={:02X} LY={:02X} PPU={:?} ROM={:02X}",
+        sys.interrupt_master_enable as u8,
+        sys.mem.io_regs.get(IoReg::Ie),
+        sys.mem.io_regs.get(IoReg::If),
+        sys.mem.io_regs.get(IoReg::Ly),
+        sys.ppu.mode(),
+        sys.mem.cart.rom_bank()
+    );
+    print_location(sys);
+}
+
+fn print_mem(sys: &Sys, addr: Addr, len: usize) {
+    let addrs = (addr as usize..addr as usize + len).take_while(|addr| *addr <= 0xFFFF);
+    let addrs = addrs.collect::<Vec<_>>();
+
+    for row in addrs.chunks(DUMP_ROW_LEN) {
+        let bytes = row
+            .iter()
+            .map(|addr| format!("{:02X}", sys.mem.peek(*addr as Addr)))
+            .collect::<Vec<_>>();
+        println!("  {:04X}: {}", row[0], bytes.join(" "));
+    }
+}
+
+fn print_trace_log(sys: &Sys) {
+    match &sys.debugger.trace {
+        Some(trace) => println!(
+            "Tracing to {}, {} instructions so far.",
+            trace.path().display(),
+            trace.line_count()
+        ),
+        None => println!("Not tracing."),
+    }
+}
+
+/// Prints PC, then where each routine running returns to, innermost first.
+fn print_backtrace(sys: &Sys) {
+    let symbols = &sys.debugger.symbols;
+    let pc = sys.regs.pc();
+    print_frame_line(symbols, 0, pc, code_bank(pc, sys.mem.cart.rom_bank()), None);
+
+    let frames = sys.debugger.call_stack.frames();
+    for (idx, frame) in frames.iter().rev().enumerate() {
+        let interrupt = match frame.kind {
+            FrameKind::Call => None,
+            FrameKind::Interrupt(type_) => Some(type_),
+        };
+        print_frame_line(
+            symbols,
+            idx + 1,
+            frame.return_addr,
+            frame.return_bank,
+            interrupt,
+        );
+    }
+}
+
+fn print_frame_line(
+    symbols: &Symbols,
+    idx: usize,
+    addr: Addr,
+    bank: Option<usize>,
+    interrupt: Option<InterruptType>,
+) {
+    let mut line = match bank {
+        Some(bank) => format!("  #{:<3} {:02X}:{:04X}", idx, bank, addr),
+        None => format!("  #{:<3} ${:04X}", idx, addr),
+    };
+    if let Some(label) = symbols.label(addr, bank) {
+        line += &format!(" <{}>", label);
+    }
+    if let Some(type_) = interrupt {
+        line += &format!("  (interrupted by {:?})", type_);
+    }
+    println!("{}", line);
+}
+
+/// Prints the routines that spent the most cycles running themselves.
+fn print_profile(sys: &Sys) {
+    let Some(profiler) = &sys.debugger.profiler else {
+        println!("Not profiling.");
+        return;
+    };
+
+    let total = profiler.total_cycles();
+    let percent = |cycles: u64| 100.0 * cycles as f64 / total.max(1) as f64;
+    println!(
+        "{} M-cycles profiled{}.",
+        total,
+        if profiler.is_running() { " so far" } else { "" }
+    );
+    println!("   Exclusive          Inclusive         Routine");
+    for routine in profiler.routines().iter().take(PROFILE_REPORT_LEN) {
+        println!(
+            "  {:>10} {:>5.1}%  {:>10} {:>5.1}%  {}",
+            routine.exclusive,
+            percent(routine.exclusive),
+            routine.inclusive,
+            percent(routine.inclusive),
+            routine.name(&sys.debugger.symbols)
+        );
+    }
+}
+
+fn print_io_regs(sys: &Sys) {
+    let regs = IoReg::iter()
+        .map(|reg| {
+            format!(
+                "{:<5} {:02X}",
+                format!("{:?}", reg),
+                sys.mem.io_regs.get(reg)
+            )
+        })
+        .collect::<Vec<_>>();
+
+    for row in regs.chunks(6) {
+        println!("  {}", row.join("   "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Option<Command>, String> {
+        parse_command(line, &Symbols::new())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("  "), Ok(None));
+        assert_eq!(
+            parse("b $0150"),
+            Ok(Some(Command::Break(Breakpoint::new(0x0150, None))))
+        );
+        assert_eq!(
+            parse("break 1A:4123"),
+            Ok(Some(Command::Break(Breakpoint::new(0x4123, Some(0x1A)))))
+        );
+        assert_eq!(
+            parse("b 0150 if a == $10 && [hl] > 3"),
+            Ok(Some(Command::Break(Breakpoint {
+                condition: Some(Expr::parse("a == $10 && [hl] > 3").unwrap()),
+                ..Breakpoint::new(0x0150, None)
+            })))
+        );
+        assert_eq!(
+            parse("trace 4000 HL is {hl:x}"),
+            Ok(Some(Command::Break(Breakpoint {
+                message: Some(Message::parse("HL is {hl:x}").unwrap()),
+                ..Breakpoint::new(0x4000, None)
+            })))
+        );
+        assert_eq!(
+            parse("cond 1"),
+            Ok(Some(Command::Condition {
+                idx: 1,
+                condition: None
+            }))
+        );
+        assert_eq!(
+            parse("ignore 0 10"),
+            Ok(Some(Command::Ignore { idx: 0, count: 10 }))
+        );
+        assert_eq!(
+            parse("watch LCDC w"),
+            Ok(Some(Command::Watch(Watchpoint {
+                addr: 0xFF40,
+                on_read: false,
+                on_write: true
+            })))
+        );
+        assert_eq!(
+            parse("m 0xC000 10"),
+            Ok(Some(Command::Mem {
+                addr: 0xC000,
+                len: 0x10
+            }))
+        );
+        assert_eq!(
+            parse("disasm $4000 5"),
+            Ok(Some(Command::Disasm {
+                addr: Some(0x4000),
+                count: 5
+            }))
+        );
+        assert_eq!(parse("d w 2"), Ok(Some(Command::DeleteWatchpoint(2))));
+        assert_eq!(parse("u 2000"), Ok(Some(Command::Step(Step::To(0x2000)))));
+        assert_eq!(
+            parse("tracelog logs/cpu.log"),
+            Ok(Some(Command::TraceLog(Some(PathBuf::from("logs/cpu.log")))))
+        );
+        assert_eq!(parse("tracelog off"), Ok(Some(Command::TraceLog(None))));
+        assert_eq!(parse("bt"), Ok(Some(Command::Backtrace)));
+        assert_eq!(parse("cdl on"), Ok(Some(Command::StartCdl)));
+        assert_eq!(
+            parse("cdl save game.cdl"),
+            Ok(Some(Command::SaveCdl(PathBuf::from("game.cdl"))))
+        );
+        assert_eq!(parse("profile on"), Ok(Some(Command::Profile(true))));
+        assert_eq!(
+            parse("profile save cpu.folded"),
+            Ok(Some(Command::ProfileSave(PathBuf::from("cpu.folded"))))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("frobnicate").is_err());
+        assert!(parse("b").is_err());
+        assert!(parse("b 10000").is_err());
+        assert!(parse("b 2:0150").is_err());
+        assert!(parse("w C000 x").is_err());
+        assert!(parse("w C000 r w").is_err());
+        assert!(parse("b 0150 if").is_err());
+        assert!(parse("b 0150 if a ==").is_err());
+        assert!(parse("trace 0150").is_err());
+        assert!(parse("ignore 0").is_err());
+        assert!(parse("profile save").is_err());
+    }
+
+    #[test]
+    fn test_parse_symbols() {
+        let symbols = Symbols::parse("00:0150 Main\n01:4000 LoadLevel\n00:C0A0 wPlayerX").unwrap();
+        let parse = |line: &str| parse_command(line, &symbols);
+
+        assert_eq!(
+            parse("b Main"),
+            Ok(Some(Command::Break(Breakpoint::new(0x0150, None))))
+        );
+        assert_eq!(
+            parse("b LoadLevel+10"),
+            Ok(Some(Command::Break(Breakpoint::new(0x4010, Some(1)))))
+        );
+        assert_eq!(
+            parse("w wPlayerX r"),
+            Ok(Some(Command::Watch(Watchpoint {
+                addr: 0xC0A0,
+                on_read: true,
+                on_write: false
+            })))
+        );
+        assert_eq!(
+            parse("u Main+$3"),
+            Ok(Some(Command::Step(Step::To(0x0153))))
+        );
+        assert!(parse("b Mian").is_err());
+        assert!(parse("b Main+x").is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{
+    cpu::instr::{decode_at, Instr},
+    debug,
+    mem::{bus::Bus, Addr},
+    sys::Sys,
+};
+
+use super::{
+    call_stack::CallStack,
+    expr::{Expr, Message},
+    mem_viewer::MemViewer,
+    profiler::Profiler,
+    symbols::Symbols,
+    trace::TraceLog,
+    watch::WatchHit,
+};
+
+/// Breaks when PC reaches `addr`. In switchable ROM ($4000-$7FFF), it can
+/// be limited to one bank.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Breakpoint {
+    pub addr: Addr,
+    pub bank: Option<usize>,
+
+    /// Only counts as hit when this is true.
+    pub condition: Option<Expr>,
+
+    /// This many hits are skipped before it breaks.
+    pub ignore_count: u32,
+
+    /// Makes it a tracepoint, which prints this instead of breaking.
+    pub message: Option<Message>,
+
+    pub hit_count: u32,
+}
+
+impl Breakpoint {
+    pub fn new(addr: Addr, bank: Option<usize>) -> Self {
+        Self {
+            addr,
+            bank,
+            condition: None,
+            ignore_count: 0,
+            message: None,
+            hit_count: 0,
+        }
+    }
+
+    fn is_at(&self, pc: Addr, rom_bank: usize) -> bool {
+        pc == self.addr && self.bank.is_none_or(|bank| bank == rom_bank)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BreakReason {
+    /// The index of the breakpoint hit.
+    Breakpoint(usize),
+    Watchpoint(WatchHit),
+
+    /// The ROM executed `LD B,B`.
+    Software,
+    Step,
+}
+
+/// How far to run before breaking again.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Step {
+    /// One instruction.
+    Into,
+
+    /// One instruction, running any call it makes to completion.
+    Over,
+
+    /// Until the current function returns.
+    Out,
+
+    /// Until PC reaches the address.
+    To(Addr),
+}
+
+/// Where a step ends.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum StepTarget {
+    NextInstr,
+    Return { addr: Addr, sp: Addr },
+    Out { sp: Addr },
+    Addr(Addr),
+}
+
+pub struct Debugger {
+    pub breakpoints: Vec<Breakpoint>,
+
+    /// Break when the ROM executes `LD B,B`, which test ROMs and homebrew
+    /// use as a breakpoint.
+    pub break_on_software: bool,
+
+    /// Names for addresses, from the ROM's symbol file.
+    pub symbols: Symbols,
+
+    /// Logs every instruction while set.
+    pub trace: Option<TraceLog>,
+
+    /// The routines running now, from the calls and returns executed.
+    pub call_stack: CallStack,
+
+    /// Counts cycles per routine while set.
+    pub profiler: Option<Profiler>,
+
+    /// The debug UI's memory panel.
+    pub mem_viewer: MemViewer,
+
+    step: Option<StepTarget>,
+    pending_break: Option<BreakReason>,
+
+    /// The instruction here runs without checking for breakpoints, so that
+    /// resuming from a breakpoint doesn't hit it again.
+    skip_pc: Option<Addr>,
+
+    /// Was the last instruction a return? Only tracked while stepping out.
+    was_ret: bool,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self {
+            breakpoints: vec![],
+            break_on_software: false,
+            symbols: Symbols::new(),
+            trace: None,
+            call_stack: CallStack::new(),
+            profiler: None,
+            mem_viewer: MemViewer::new(),
+            step: None,
+            pending_break: None,
+            skip_pc: None,
+            was_ret: false,
+        }
+    }
+
+    /// Has something asked to break? Run loops should stop once it has.
+    pub fn is_breaking(&self) -> bool {
+        self.pending_break.is_some()
+    }
+
+    pub fn take_break(&mut self) -> Option<BreakReason> {
+        self.pending_break.take()
+    }
+
+    /// The first reason to break is kept, and any step ends.
+    fn request_break(&mut self, reason: BreakReason) {
+        self.pending_break.get_or_insert(reason);
+        self.step = None;
+    }
+}
+
+/// Unpauses, running until `step` is done if given.
+pub fn resume(sys: &mut Sys, step: Option<Step>) {
+    let pc = sys.regs.pc();
+    let sp = sys.regs.sp();
+
+    sys.debugger.step = step.map(|step| match step {
+        Step::Into => StepTarget::NextInstr,
+        Step::Over => match decode_at(pc, |addr| sys.mem.peek(addr)) {
+            Ok(instr) if is_call(instr) => StepTarget::Return {
+                addr: pc.wrapping_add(instr.size()),
+                sp,
+            },
+            _ => StepTarget::NextInstr,
+        },
+        Step::Out => StepTarget::Out { sp },
+        Step::To(addr) => StepTarget::Addr(addr),
+    });
+    sys.debugger.skip_pc = Some(pc);
+    sys.debugger.was_ret = false;
+    sys.emu.resume();
+}
+
+/// Call before the CPU executes the instruction at PC. Returns whether to
+/// break instead. The instruction is then held until the CPU's next tick.
+pub fn check_before_instr(sys: &mut Sys) -> bool {
+    let debugger = &mut sys.debugger;
+    let pc = sys.regs.pc();
+    if debugger.skip_pc.take() == Some(pc) {
+        update_was_ret(sys);
+        return false;
+    }
+    if debugger.breakpoints.is_empty() && debugger.step.is_none() {
+        return false;
+    }
+
+    let breakpoint = check_breakpoints(sys);
+    let debugger = &mut sys.debugger;
+    let sp = sys.regs.sp();
+
+    let reason = match (breakpoint, debugger.step) {
+        (Some(idx), _) => Some(BreakReason::Breakpoint(idx)),
+        (None, Some(StepTarget::NextInstr)) => Some(BreakReason::Step),
+        (None, Some(StepTarget::Return { addr, sp: call_sp })) if pc == addr && sp >= call_sp => {
+            Some(BreakReason::Step)
+        }
+        (None, Some(StepTarget::Out { sp: start_sp })) if debugger.was_ret && sp > start_sp => {
+            Some(BreakReason::Step)
+        }
+        (None, Some(StepTarget::Addr(addr))) if pc == addr => Some(BreakReason::Step),
+        _ => None,
+    };
+
+    if let Some(reason) = reason {
+        debugger.request_break(reason);
+        debugger.skip_pc = Some(pc);
+        return true;
+    }
+
+    update_was_ret(sys);
+    return false;
+}
+
+/// Counts a hit on each breakpoint at PC whose condition is true, and prints
+/// any tracepoint messages. Returns the first breakpoint to break on.
+fn check_breakpoints(sys: &mut Sys) -> Option<usize> {
+    let pc = sys.regs.pc();
+    let rom_bank = sys.mem.cart.rom_bank();
+    let breakpoints = &sys.debugger.breakpoints;
+    if !breakpoints
+        .iter()
+        .any(|breakpoint| breakpoint.is_at(pc, rom_bank))
+    {
+        return None;
+    }
+
+    // Conditions need the whole system, so the list is taken out meanwhile.
+    let mut breakpoints = std::mem::take(&mut sys.debugger.breakpoints);
+    let mut hit = None;
+
+    for (idx, breakpoint) in breakpoints.iter_mut().enumerate() {
+        let is_hit = breakpoint.is_at(pc, rom_bank)
+            && breakpoint
+                .condition
+                .as_ref()
+                .is_none_or(|condition| condition.is_true(sys));
+        if !is_hit {
+            continue;
+        }
+
+        breakpoint.hit_count += 1;
+        if breakpoint.ignore_count > 0 {
+            breakpoint.ignore_count -= 1;
+            continue;
+        }
+
+        match &breakpoint.message {
+            Some(message) => println!("{}", message.format(sys)),
+            None => {
+                hit.get_or_insert(idx);
+            }
+        }
+    }
+
+    sys.debugger.breakpoints = breakpoints;
+    return hit;
+}
+
+/// Call at the end of each M-cycle, to break on what happened during it.
+pub fn update_debugger(sys: &mut Sys) {
+    if debug::take_pending_breakpoint() && sys.debugger.break_on_software {
+        sys.debugger.request_break(BreakReason::Software);
+    }
+
+    if let Some(hit) = sys.mem.watchpoints.take_hit() {
+        sys.debugger.request_break(BreakReason::Watchpoint(hit));
+    }
+}
+
+fn update_was_ret(sys: &mut Sys) {
+    if let Some(StepTarget::Out { .. }) = sys.debugger.step {
+        let instr = decode_at(sys.regs.pc(), |addr| sys.mem.peek(addr));
+        sys.debugger.was_ret = instr.is_ok_and(is_ret);
+    }
+}
+
+fn is_call(instr: Instr) -> bool {
+    matches!(
+        instr,
+        Instr::Call_Imm16 | Instr::Call_Cond_Imm16 { .. } | Instr::Rst_Tgt3 { .. }
+    )
+}
+
+fn is_ret(instr: Instr) -> bool {
+    matches!(instr, Instr::Ret | Instr::Reti | Instr::Ret_Cond { .. })
+}
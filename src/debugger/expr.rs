@@ -0,0 +1,770 @@
+// Expressions are used for breakpoint conditions and tracepoint messages.
+// They work like C expressions on unsigned 32-bit values, and are true when
+// non-zero:
+//
+//   Numbers:    Decimal, or hex with a `$` or `0x` prefix.
+//   Registers:  a f b c d e h l, af bc de hl sp pc
+//   Flags:      zf nf hf cf, and ime
+//   Memory:     [ADDR] is the byte at ADDR, and word[ADDR] the little-endian
+//               word. [REGION:ADDR] also checks that ADDR is in the region
+//               (rom vram sram wram oam io hram), and [wramN:ADDR] or
+//               [vramN:ADDR] reads from bank N, whichever bank is mapped.
+//   IO regs:    By name, e.g. lcdc, ly, stat.
+//   Other:      bank (the ROM bank at $4000-$7FFF), mode (the PPU mode, 0-3
+//               as in STAT)
+//   Operators:  || && | ^ & == != < <= > >= << >> + - * / % and unary ! - ~
+//               with C's precedence. Dividing by zero gives 0.
+//
+// For example: pc == $4123 && [wram:$C0A0] > 3 && ly == 144
+//
+// Tracepoint messages are text with expressions in braces, printed in
+// decimal, or in hex with `:x`. For example: "HL is {hl:x}, LY is {ly}".
+
+use std::fmt;
+
+use strum::IntoEnumIterator;
+
+use crate::{
+    cpu::regs::{CpuFlag, CpuReg16, CpuReg8},
+    mem::{io_regs::IoReg, Addr},
+    sys::Sys,
+};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Expr {
+    Num(u32),
+    Reg8(CpuReg8),
+    Reg16(CpuReg16),
+    Flag(CpuFlag),
+    Ime,
+    IoReg(IoReg),
+    RomBank,
+    PpuMode,
+    Byte(Box<Expr>),
+    Word(Box<Expr>),
+    RegionByte {
+        region: Region,
+        bank: Option<usize>,
+        addr: Addr,
+    },
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Region {
+    Rom,
+    Vram,
+    Sram,
+    Wram,
+    Oam,
+    Io,
+    Hram,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnaryOp {
+    Not,
+    Neg,
+    BitNot,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BinaryOp {
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl Region {
+    const ALL: [Region; 7] = [
+        Region::Rom,
+        Region::Vram,
+        Region::Sram,
+        Region::Wram,
+        Region::Oam,
+        Region::Io,
+        Region::Hram,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Region::Rom => "rom",
+            Region::Vram => "vram",
+            Region::Sram => "sram",
+            Region::Wram => "wram",
+            Region::Oam => "oam",
+            Region::Io => "io",
+            Region::Hram => "hram",
+        }
+    }
+
+    fn contains(self, addr: Addr) -> bool {
+        match self {
+            Region::Rom => addr <= 0x7FFF,
+            Region::Vram => (0x8000..=0x9FFF).contains(&addr),
+            Region::Sram => (0xA000..=0xBFFF).contains(&addr),
+            Region::Wram => (0xC000..=0xDFFF).contains(&addr),
+            Region::Oam => (0xFE00..=0xFE9F).contains(&addr),
+            Region::Io => (0xFF00..=0xFF7F).contains(&addr),
+            Region::Hram => (0xFF80..=0xFFFE).contains(&addr),
+        }
+    }
+}
+
+/// Each binary operator's symbol, and how tightly it binds.
+const BINARY_OPS: [(&str, BinaryOp, u8); 18] = [
+    ("||", BinaryOp::Or, 1),
+    ("&&", BinaryOp::And, 2),
+    ("|", BinaryOp::BitOr, 3),
+    ("^", BinaryOp::BitXor, 4),
+    ("&", BinaryOp::BitAnd, 5),
+    ("==", BinaryOp::Eq, 6),
+    ("!=", BinaryOp::Ne, 6),
+    ("<", BinaryOp::Lt, 7),
+    ("<=", BinaryOp::Le, 7),
+    (">", BinaryOp::Gt, 7),
+    (">=", BinaryOp::Ge, 7),
+    ("<<", BinaryOp::Shl, 8),
+    (">>", BinaryOp::Shr, 8),
+    ("+", BinaryOp::Add, 9),
+    ("-", BinaryOp::Sub, 9),
+    ("*", BinaryOp::Mul, 10),
+    ("/", BinaryOp::Div, 10),
+    ("%", BinaryOp::Rem, 10),
+];
+
+impl BinaryOp {
+    fn from_symbol(symbol: &str) -> Option<(Self, u8)> {
+        BINARY_OPS
+            .iter()
+            .find(|(op_symbol, _, _)| *op_symbol == symbol)
+            .map(|(_, op, prec)| (*op, *prec))
+    }
+
+    fn symbol(self) -> &'static str {
+        let &(symbol, _, _) = BINARY_OPS.iter().find(|(_, op, _)| *op == self).unwrap();
+        return symbol;
+    }
+
+    fn apply(self, lhs: u32, rhs: u32) -> u32 {
+        match self {
+            BinaryOp::Or => (lhs != 0 || rhs != 0) as u32,
+            BinaryOp::And => (lhs != 0 && rhs != 0) as u32,
+            BinaryOp::BitOr => lhs | rhs,
+            BinaryOp::BitXor => lhs ^ rhs,
+            BinaryOp::BitAnd => lhs & rhs,
+            BinaryOp::Eq => (lhs == rhs) as u32,
+            BinaryOp::Ne => (lhs != rhs) as u32,
+            BinaryOp::Lt => (lhs < rhs) as u32,
+            BinaryOp::Le => (lhs <= rhs) as u32,
+            BinaryOp::Gt => (lhs > rhs) as u32,
+            BinaryOp::Ge => (lhs >= rhs) as u32,
+            BinaryOp::Shl => lhs.checked_shl(rhs).unwrap_or(0),
+            BinaryOp::Shr => lhs.checked_shr(rhs).unwrap_or(0),
+            BinaryOp::Add => lhs.wrapping_add(rhs),
+            BinaryOp::Sub => lhs.wrapping_sub(rhs),
+            BinaryOp::Mul => lhs.wrapping_mul(rhs),
+            BinaryOp::Div => lhs.checked_div(rhs).unwrap_or(0),
+            BinaryOp::Rem => lhs.checked_rem(rhs).unwrap_or(0),
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+        let expr = parser.parse_binary(0)?;
+        return match parser.next_token() {
+            None => Ok(expr),
+            Some(token) => Err(format!("Unexpected {} in expression.", token)),
+        };
+    }
+
+    /// Evaluates the expression without side effects, e.g. reading an IO
+    /// register never clears it.
+    pub fn eval(&self, sys: &Sys) -> u32 {
+        match self {
+            Expr::Num(value) => *value,
+            Expr::Reg8(reg) => sys.regs.get_8(*reg) as u32,
+            Expr::Reg16(reg) => sys.regs.get_16(*reg) as u32,
+            Expr::Flag(flag) => sys.regs.get_flag(*flag) as u32,
+            Expr::Ime => sys.interrupt_master_enable as u32,
+            Expr::IoReg(reg) => sys.mem.io_regs.get(*reg) as u32,
+            Expr::RomBank => sys.mem.cart.rom_bank() as u32,
+            Expr::PpuMode => sys.ppu.mode() as u32,
+            Expr::Byte(addr) => sys.mem.peek(addr.eval(sys) as Addr) as u32,
+            Expr::Word(addr) => {
+                let addr = addr.eval(sys) as Addr;
+                let lo = sys.mem.peek(addr) as u32;
+                let hi = sys.mem.peek(addr.wrapping_add(1)) as u32;
+                (hi << 8) | lo
+            }
+            Expr::RegionByte { region, bank, addr } => {
+                read_banked(sys, *region, *bank, *addr).unwrap_or(0) as u32
+            }
+            Expr::Unary(op, expr) => {
+                let value = expr.eval(sys);
+                match op {
+                    UnaryOp::Not => (value == 0) as u32,
+                    UnaryOp::Neg => value.wrapping_neg(),
+                    UnaryOp::BitNot => !value,
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                // Short-circuits like C, though nothing here has side effects.
+                let lhs = lhs.eval(sys);
+                match (op, lhs != 0) {
+                    (BinaryOp::And, false) => 0,
+                    (BinaryOp::Or, true) => 1,
+                    _ => op.apply(lhs, rhs.eval(sys)),
+                }
+            }
+        }
+    }
+
+    pub fn is_true(&self, sys: &Sys) -> bool {
+        self.eval(sys) != 0
+    }
+}
+
+/// Writes the expression back out as it would be typed, with each nested
+/// operation in parentheses.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Num(value) if *value < 10 => write!(f, "{}", value),
+            Expr::Num(value) => write!(f, "${:X}", value),
+            Expr::Reg8(reg) => f.write_str(&format!("{:?}", reg).to_lowercase()),
+            Expr::Reg16(reg) => f.write_str(&format!("{:?}", reg).to_lowercase()),
+            Expr::Flag(flag) => write!(f, "{}f", flag_name(*flag)),
+            Expr::Ime => write!(f, "ime"),
+            Expr::IoReg(reg) => f.write_str(&format!("{:?}", reg).to_lowercase()),
+            Expr::RomBank => write!(f, "bank"),
+            Expr::PpuMode => write!(f, "mode"),
+            Expr::Byte(addr) => write!(f, "[{}]", addr),
+            Expr::Word(addr) => write!(f, "word[{}]", addr),
+            Expr::RegionByte { region, bank, addr } => match bank {
+                Some(bank) => write!(f, "[{}{}:${:04X}]", region.name(), bank, addr),
+                None => write!(f, "[{}:${:04X}]", region.name(), addr),
+            },
+            Expr::Unary(op, expr) => {
+                let symbol = match op {
+                    UnaryOp::Not => "!",
+                    UnaryOp::Neg => "-",
+                    UnaryOp::BitNot => "~",
+                };
+                write!(f, "{}", symbol)?;
+                write_operand(f, expr)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                write_operand(f, lhs)?;
+                write!(f, " {} ", op.symbol())?;
+                write_operand(f, rhs)
+            }
+        }
+    }
+}
+
+fn flag_name(flag: CpuFlag) -> char {
+    match flag {
+        CpuFlag::Z => 'z',
+        CpuFlag::N => 'n',
+        CpuFlag::H => 'h',
+        CpuFlag::C => 'c',
+    }
+}
+
+fn write_operand(f: &mut fmt::Formatter<'_>, expr: &Expr) -> fmt::Result {
+    match expr {
+        Expr::Binary(..) => write!(f, "({})", expr),
+        _ => write!(f, "{}", expr),
+    }
+}
+
+/// Reads from a specific WRAM or VRAM bank, if the system has it.
+fn read_banked(sys: &Sys, region: Region, bank: Option<usize>, addr: Addr) -> Option<u8> {
+    let Some(bank) = bank else {
+        return Some(sys.mem.peek(addr));
+    };
+
+    return match region {
+        Region::Wram => {
+            let data = sys.mem.wram.banks().nth(bank)?;
+            Some(data[addr as usize % 0x1000])
+        }
+        Region::Vram => (bank < sys.mem.vram.num_banks()).then(|| sys.mem.vram.get(bank, addr)),
+        _ => None,
+    };
+}
+
+/// A tracepoint message: text with expressions to fill in.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Message {
+    parts: Vec<MessagePart>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum MessagePart {
+    Text(String),
+    Value { expr: Expr, is_hex: bool },
+}
+
+impl Message {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut parts = vec![];
+        let mut rest = s;
+
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                return Err(format!("Unclosed {{ in message: {}", s));
+            };
+            if start > 0 {
+                parts.push(MessagePart::Text(rest[..start].to_owned()));
+            }
+
+            let value = &rest[start + 1..start + len];
+            let (value, is_hex) = match value.strip_suffix(":x") {
+                Some(value) => (value, true),
+                None => (value, false),
+            };
+            parts.push(MessagePart::Value {
+                expr: Expr::parse(value)?,
+                is_hex,
+            });
+            rest = &rest[start + len + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(MessagePart::Text(rest.to_owned()));
+        }
+
+        return Ok(Self { parts });
+    }
+
+    pub fn format(&self, sys: &Sys) -> String {
+        let mut msg = String::new();
+        for part in &self.parts {
+            match part {
+                MessagePart::Text(text) => msg.push_str(text),
+                MessagePart::Value { expr, is_hex } => {
+                    let value = expr.eval(sys);
+                    match (*is_hex, value > 0xFF) {
+                        (true, true) => msg.push_str(&format!("${:04X}", value)),
+                        (true, false) => msg.push_str(&format!("${:02X}", value)),
+                        (false, _) => msg.push_str(&value.to_string()),
+                    }
+                }
+            }
+        }
+
+        return msg;
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                MessagePart::Text(text) => write!(f, "{}", text)?,
+                MessagePart::Value { expr, is_hex: true } => write!(f, "{{{}:x}}", expr)?,
+                MessagePart::Value {
+                    expr,
+                    is_hex: false,
+                } => write!(f, "{{{}}}", expr)?,
+            }
+        }
+        return Ok(());
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Token {
+    Num(u32),
+    Ident(String),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Num(value) => write!(f, "{}", value),
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+/// Longer symbols come first, so that `<=` isn't read as `<` then `=`.
+const SYMBOLS: [&str; 24] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<<", ">>", "|", "^", "&", "<", ">", "+", "-", "*", "/",
+    "%", "!", "~", "(", ")", "[", "]",
+];
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut rest = s.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let len = if c == '$' || c.is_ascii_alphanumeric() || c == '_' {
+            let len = rest[1..]
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .map_or(rest.len(), |len| len + 1);
+            tokens.push(parse_word(&rest[..len])?);
+            len
+        } else if c == ':' {
+            tokens.push(Token::Symbol(":"));
+            1
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+            tokens.push(Token::Symbol(symbol));
+            symbol.len()
+        } else {
+            return Err(format!("Unexpected {} in expression.", c));
+        };
+
+        rest = rest[len..].trim_start();
+    }
+
+    return Ok(tokens);
+}
+
+fn parse_word(word: &str) -> Result<Token, String> {
+    let hex = word.strip_prefix('$').or_else(|| word.strip_prefix("0x"));
+    let value = match hex {
+        Some(digits) => u32::from_str_radix(digits, 16),
+        None if word.starts_with(|c: char| c.is_ascii_digit()) => word.parse(),
+        None => return Ok(Token::Ident(word.to_lowercase())),
+    };
+
+    return value
+        .map(Token::Num)
+        .map_err(|_| format!("Invalid number: {}.", word));
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        return token;
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        match self.next_token() {
+            Some(Token::Symbol(next)) if next == symbol => Ok(()),
+            Some(token) => Err(format!("Expected {} but found {}.", symbol, token)),
+            None => Err(format!("Expected {} at the end of the expression.", symbol)),
+        }
+    }
+
+    /// Parses operators that bind at least as tightly as `min_prec`.
+    fn parse_binary(&mut self, min_prec: u8) -> Result<Expr, String> {
+        let mut lhs = self.parse_unary()?;
+
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol(symbol)) => BinaryOp::from_symbol(symbol),
+                _ => None,
+            };
+            let Some((op, prec)) = op.filter(|(_, prec)| *prec >= min_prec) else {
+                return Ok(lhs);
+            };
+
+            self.pos += 1;
+            let rhs = self.parse_binary(prec + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        let op = match self.peek() {
+            Some(Token::Symbol("!")) => UnaryOp::Not,
+            Some(Token::Symbol("-")) => UnaryOp::Neg,
+            Some(Token::Symbol("~")) => UnaryOp::BitNot,
+            _ => return self.parse_primary(),
+        };
+
+        self.pos += 1;
+        return Ok(Expr::Unary(op, Box::new(self.parse_unary()?)));
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.next_token() {
+            Some(Token::Num(value)) => Ok(Expr::Num(value)),
+            Some(Token::Symbol("(")) => {
+                let expr = self.parse_binary(0)?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(Token::Symbol("[")) => self.parse_mem(),
+            Some(Token::Ident(name)) if name == "word" => {
+                self.expect("[")?;
+                let addr = self.parse_binary(0)?;
+                self.expect("]")?;
+                Ok(Expr::Word(Box::new(addr)))
+            }
+            Some(Token::Ident(name)) => parse_ident(&name),
+            Some(token) => Err(format!("Unexpected {} in expression.", token)),
+            None => Err("Unexpected end of expression.".into()),
+        }
+    }
+
+    /// Parses what follows a `[`.
+    fn parse_mem(&mut self) -> Result<Expr, String> {
+        let is_region = matches!(self.tokens.get(self.pos + 1), Some(Token::Symbol(":")));
+        let Some(Token::Ident(name)) = self.peek().filter(|_| is_region).cloned() else {
+            let addr = self.parse_binary(0)?;
+            self.expect("]")?;
+            return Ok(Expr::Byte(Box::new(addr)));
+        };
+        self.pos += 2;
+
+        let (region, bank) = parse_region(&name)?;
+        let Some(Token::Num(addr)) = self.next_token() else {
+            return Err(format!("Expected an address after {}:.", name));
+        };
+        let addr = Addr::try_from(addr).map_err(|_| format!("Invalid address: {}.", addr))?;
+        self.expect("]")?;
+
+        let is_bank_valid = match (region, bank) {
+            (_, None) => true,
+            (Region::Vram, Some(0 | 1)) => true,
+            (Region::Wram, Some(0)) => addr <= 0xCFFF,
+            (Region::Wram, Some(1..=7)) => addr >= 0xD000,
+            _ => false,
+        };
+        if !region.contains(addr) || !is_bank_valid {
+            return Err(format!("${:04X} isn't in {}.", addr, name));
+        }
+
+        return Ok(Expr::RegionByte { region, bank, addr });
+    }
+}
+
+/// A region name, with a bank number after it for WRAM and VRAM.
+fn parse_region(name: &str) -> Result<(Region, Option<usize>), String> {
+    for region in Region::ALL {
+        let Some(bank) = name.strip_prefix(region.name()) else {
+            continue;
+        };
+        if bank.is_empty() {
+            return Ok((region, None));
+        }
+        if matches!(region, Region::Wram | Region::Vram) {
+            if let Ok(bank) = bank.parse() {
+                return Ok((region, Some(bank)));
+            }
+        }
+    }
+
+    return Err(format!("Unknown memory region: {}.", name));
+}
+
+fn parse_ident(name: &str) -> Result<Expr, String> {
+    use CpuReg16::*;
+    use CpuReg8::*;
+
+    let expr = match name {
+        "a" => Expr::Reg8(A),
+        "f" => Expr::Reg8(F),
+        "b" => Expr::Reg8(B),
+        "c" => Expr::Reg8(C),
+        "d" => Expr::Reg8(D),
+        "e" => Expr::Reg8(E),
+        "h" => Expr::Reg8(H),
+        "l" => Expr::Reg8(L),
+        "af" => Expr::Reg16(AF),
+        "bc" => Expr::Reg16(BC),
+        "de" => Expr::Reg16(DE),
+        "hl" => Expr::Reg16(HL),
+        "sp" => Expr::Reg16(SP),
+        "pc" => Expr::Reg16(PC),
+        "zf" => Expr::Flag(CpuFlag::Z),
+        "nf" => Expr::Flag(CpuFlag::N),
+        "hf" => Expr::Flag(CpuFlag::H),
+        "cf" => Expr::Flag(CpuFlag::C),
+        "ime" => Expr::Ime,
+        "bank" => Expr::RomBank,
+        "mode" => Expr::PpuMode,
+        _ => {
+            let reg = IoReg::iter().find(|reg| format!("{:?}", reg).eq_ignore_ascii_case(name));
+            match reg {
+                Some(reg) => Expr::IoReg(reg),
+                None => return Err(format!("Unknown name in expression: {}.", name)),
+            }
+        }
+    };
+
+    return Ok(expr);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num(value: u32) -> Box<Expr> {
+        Box::new(Expr::Num(value))
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        assert_eq!(
+            Expr::parse("1 + 2 * 3 == 7"),
+            Ok(Expr::Binary(
+                BinaryOp::Eq,
+                Box::new(Expr::Binary(
+                    BinaryOp::Add,
+                    num(1),
+                    Box::new(Expr::Binary(BinaryOp::Mul, num(2), num(3)))
+                )),
+                num(7)
+            ))
+        );
+        assert_eq!(
+            Expr::parse("-(1 - 2) - 3"),
+            Ok(Expr::Binary(
+                BinaryOp::Sub,
+                Box::new(Expr::Unary(
+                    UnaryOp::Neg,
+                    Box::new(Expr::Binary(BinaryOp::Sub, num(1), num(2)))
+                )),
+                num(3)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_operands() {
+        assert_eq!(
+            Expr::parse("pc == $4123 && [wram:$C0A0] > 3 && LY == 144"),
+            Ok(Expr::Binary(
+                BinaryOp::And,
+                Box::new(Expr::Binary(
+                    BinaryOp::And,
+                    Box::new(Expr::Binary(
+                        BinaryOp::Eq,
+                        Box::new(Expr::Reg16(CpuReg16::PC)),
+                        num(0x4123)
+                    )),
+                    Box::new(Expr::Binary(
+                        BinaryOp::Gt,
+                        Box::new(Expr::RegionByte {
+                            region: Region::Wram,
+                            bank: None,
+                            addr: 0xC0A0
+                        }),
+                        num(3)
+                    ))
+                )),
+                Box::new(Expr::Binary(
+                    BinaryOp::Eq,
+                    Box::new(Expr::IoReg(IoReg::Ly)),
+                    num(144)
+                ))
+            ))
+        );
+        assert_eq!(
+            Expr::parse("word[hl + 0x10]"),
+            Ok(Expr::Word(Box::new(Expr::Binary(
+                BinaryOp::Add,
+                Box::new(Expr::Reg16(CpuReg16::HL)),
+                num(0x10)
+            ))))
+        );
+        assert_eq!(
+            Expr::parse("[vram1:$9800]"),
+            Ok(Expr::RegionByte {
+                region: Region::Vram,
+                bank: Some(1),
+                addr: 0x9800
+            })
+        );
+        assert_eq!(Expr::parse("cf"), Ok(Expr::Flag(CpuFlag::C)));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Expr::parse("").is_err());
+        assert!(Expr::parse("1 +").is_err());
+        assert!(Expr::parse("(1").is_err());
+        assert!(Expr::parse("1 2").is_err());
+        assert!(Expr::parse("frob").is_err());
+        assert!(Expr::parse("$xyz").is_err());
+        assert!(Expr::parse("[wram:$8000]").is_err());
+        assert!(Expr::parse("[wram0:$D000]").is_err());
+        assert!(Expr::parse("[wram8:$D000]").is_err());
+        assert!(Expr::parse("[vram2:$8000]").is_err());
+        assert!(Expr::parse("[oam1:$FE00]").is_err());
+        assert!(Expr::parse("a = 1").is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        for s in [
+            "pc == $4123 && [wram:$C0A0] > 3 && ly == $90",
+            "((a + 1) * 2) - [hl]",
+            "!zf || ~word[$FF80 + c]",
+            "[vram1:$9800] != bank % mode",
+        ] {
+            let expr = Expr::parse(s).unwrap();
+            assert_eq!(Expr::parse(&expr.to_string()), Ok(expr));
+        }
+        assert_eq!(Expr::parse("a+1==2").unwrap().to_string(), "(a + 1) == 2");
+    }
+
+    #[test]
+    fn test_apply() {
+        assert_eq!(BinaryOp::Sub.apply(1, 2), u32::MAX);
+        assert_eq!(BinaryOp::Div.apply(7, 0), 0);
+        assert_eq!(BinaryOp::Shl.apply(1, 40), 0);
+        assert_eq!(BinaryOp::Le.apply(3, 3), 1);
+        assert_eq!(BinaryOp::Or.apply(0, 5), 1);
+    }
+
+    #[test]
+    fn test_parse_message() {
+        let message = Message::parse("HL is {hl:x}, {ly}").unwrap();
+        assert_eq!(
+            message.parts,
+            vec![
+                MessagePart::Text("HL is ".into()),
+                MessagePart::Value {
+                    expr: Expr::Reg16(CpuReg16::HL),
+                    is_hex: true
+                },
+                MessagePart::Text(", ".into()),
+                MessagePart::Value {
+                    expr: Expr::IoReg(IoReg::Ly),
+                    is_hex: false
+                },
+            ]
+        );
+        assert!(Message::parse("{hl").is_err());
+        assert!(Message::parse("{frob}").is_err());
+    }
+}
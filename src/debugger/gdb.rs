@@ -0,0 +1,596 @@
+// A stub for GDB's remote serial protocol, so that GDB, or an IDE driving
+// it, can debug the running game over TCP:
+//
+//   (gdb) target remote localhost:2159
+//
+// GDB has no SM83 target, so the registers are laid out like the first six
+// of its z80 target: AF, BC, DE, HL, SP and PC, each 16 bits, little-endian.
+// Addresses are the CPU's, in whatever banks are mapped. Memory is read
+// without side effects, and writes to ROM patch it instead of going to the
+// MBC.
+//
+// Packets handled: ? g G p P m M c s Z0-Z4 z0-z4 k D, the queries GDB makes
+// while connecting, and Ctrl-C (a lone 0x03 byte) to pause. Anything else
+// gets the empty reply, which tells GDB it isn't supported. The system is
+// paused while GDB is connected, except after `c` or `s`, and the stop is
+// reported when the debugger next breaks.
+
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{cpu::regs::CpuReg16, mem::Addr, sys::Sys};
+
+use super::{
+    debugger::{resume, BreakReason, Breakpoint, Step},
+    watch::{AccessKind, Watchpoint},
+};
+
+const REGS: [CpuReg16; 6] = [
+    CpuReg16::AF,
+    CpuReg16::BC,
+    CpuReg16::DE,
+    CpuReg16::HL,
+    CpuReg16::SP,
+    CpuReg16::PC,
+];
+
+const INTERRUPT: u8 = 0x03;
+
+/// Signals given in stop replies.
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+
+const SUPPORTED: &str = "PacketSize=4000;QStartNoAckMode+";
+
+pub struct GdbStub {
+    listener: TcpListener,
+    client: Option<Client>,
+}
+
+struct Client {
+    stream: TcpStream,
+    rx: Vec<u8>,
+
+    /// Set once GDB turns off acknowledgements.
+    no_ack: bool,
+
+    /// GDB is waiting to hear that the system stopped.
+    is_running: bool,
+}
+
+/// What GDB sent.
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Incoming {
+    Packet(String),
+    BadChecksum,
+    Interrupt,
+}
+
+impl GdbStub {
+    /// Starts listening for GDB at `addr`, e.g. `127.0.0.1:2159`.
+    pub fn listen(addr: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(addr)
+            .and_then(|listener| {
+                listener.set_nonblocking(true)?;
+                Ok(listener)
+            })
+            .map_err(|err| format!("Unable to listen for GDB on {}: {}", addr, err))?;
+
+        println!("Listening for GDB on {}.", addr);
+        return Ok(Self {
+            listener,
+            client: None,
+        });
+    }
+
+    #[cfg(test)]
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        return self.listener.local_addr();
+    }
+
+    /// Accepts GDB if it's connecting, and handles what it sent since the
+    /// last update.
+    pub fn update(&mut self, sys: &mut Sys) {
+        if self.client.is_none() {
+            self.accept(sys);
+        }
+        let Some(client) = &mut self.client else {
+            return;
+        };
+
+        if let Err(err) = client.receive() {
+            println!("GDB disconnected: {}", err);
+            self.client = None;
+            return;
+        }
+
+        while let Some(incoming) = client.next_incoming() {
+            let result = match incoming {
+                Incoming::Packet(packet) => {
+                    if !client.no_ack {
+                        client.send_raw(b"+");
+                    }
+                    handle_packet(sys, client, &packet)
+                }
+                Incoming::BadChecksum => {
+                    client.send_raw(b"-");
+                    Ok(())
+                }
+                Incoming::Interrupt => {
+                    sys.emu.pause();
+                    if client.is_running {
+                        client.is_running = false;
+                        client.send(&format!("S{:02x}", SIGINT));
+                    }
+                    Ok(())
+                }
+            };
+
+            if let Err(msg) = result {
+                println!("{}", msg);
+                self.client = None;
+                return;
+            }
+        }
+    }
+
+    /// Tells GDB why the system stopped, if it's waiting to hear.
+    pub fn report_break(&mut self, reason: BreakReason) {
+        let Some(client) = &mut self.client else {
+            return;
+        };
+        if client.is_running {
+            client.is_running = false;
+            client.send(&stop_reply(Some(reason)));
+        }
+    }
+
+    fn accept(&mut self, sys: &mut Sys) {
+        let stream = match self.listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => return,
+            Err(err) => {
+                println!("Unable to accept GDB: {}", err);
+                return;
+            }
+        };
+        if let Err(err) = stream
+            .set_nonblocking(true)
+            .and_then(|_| stream.set_nodelay(true))
+        {
+            println!("Unable to set up the GDB connection: {}", err);
+            return;
+        }
+
+        // GDB expects the target to be stopped when it attaches.
+        sys.emu.pause();
+        println!("GDB connected.");
+        self.client = Some(Client {
+            stream,
+            rx: vec![],
+            no_ack: false,
+            is_running: false,
+        });
+    }
+}
+
+impl Client {
+    /// Reads whatever has arrived. Fails once GDB has gone.
+    fn receive(&mut self) -> io::Result<()> {
+        let mut buf = [0; 1024];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Err(io::Error::new(ErrorKind::UnexpectedEof, "closed")),
+                Ok(n) => self.rx.extend_from_slice(&buf[..n]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Takes the next complete packet or interrupt off what's been read.
+    /// Acknowledgements are skipped, as nothing is resent.
+    fn next_incoming(&mut self) -> Option<Incoming> {
+        loop {
+            match *self.rx.first()? {
+                b'$' => break,
+                INTERRUPT => {
+                    self.rx.remove(0);
+                    return Some(Incoming::Interrupt);
+                }
+                _ => {
+                    self.rx.remove(0);
+                }
+            }
+        }
+
+        let end = self.rx.iter().position(|byte| *byte == b'#')?;
+        if self.rx.len() < end + 3 {
+            return None;
+        }
+
+        let frame = self.rx.drain(..end + 3).collect::<Vec<_>>();
+        let data = &frame[1..end];
+        let checksum = std::str::from_utf8(&frame[end + 1..])
+            .ok()
+            .and_then(|checksum| u8::from_str_radix(checksum, 16).ok());
+        if checksum != Some(checksum_of(data)) {
+            return Some(Incoming::BadChecksum);
+        }
+
+        return Some(Incoming::Packet(String::from_utf8_lossy(data).into_owned()));
+    }
+
+    fn send(&mut self, data: &str) {
+        let packet = format!("${}#{:02x}", data, checksum_of(data.as_bytes()));
+        self.send_raw(packet.as_bytes());
+    }
+
+    fn send_raw(&mut self, bytes: &[u8]) {
+        // The stream is non-blocking, but replies are small enough that the
+        // socket's buffer takes them whole.
+        if let Err(err) = self.stream.write_all(bytes) {
+            println!("Unable to send to GDB: {}", err);
+        }
+    }
+}
+
+/// Handles a packet, replying unless the reply waits for the system to
+/// stop. Fails when the session is over.
+fn handle_packet(sys: &mut Sys, client: &mut Client, packet: &str) -> Result<(), String> {
+    let (kind, args) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+
+    let reply = match kind {
+        "?" => stop_reply(None),
+        "g" => REGS
+            .iter()
+            .map(|reg| format_le16(sys.regs.get_16(*reg)))
+            .collect(),
+        "G" => match parse_regs(args) {
+            Some(values) => {
+                for (reg, value) in REGS.iter().zip(values) {
+                    sys.regs.set_16(*reg, value);
+                }
+                "OK".to_owned()
+            }
+            None => error(),
+        },
+        "p" => match parse_hex(args).and_then(|idx| REGS.get(idx as usize)) {
+            Some(reg) => format_le16(sys.regs.get_16(*reg)),
+            None => error(),
+        },
+        "P" => {
+            let reg = args
+                .split_once('=')
+                .and_then(|(idx, value)| Some((REGS.get(parse_hex(idx)? as usize)?, value)))
+                .and_then(|(reg, value)| Some((reg, parse_le16(value)?)));
+            match reg {
+                Some((reg, value)) => {
+                    sys.regs.set_16(*reg, value);
+                    "OK".to_owned()
+                }
+                None => error(),
+            }
+        }
+        "m" => match parse_range(args) {
+            Some((addr, len)) => (addr..addr.saturating_add(len).min(0x10000))
+                .map(|addr| format!("{:02x}", sys.mem.peek(addr as Addr)))
+                .collect(),
+            None => error(),
+        },
+        "M" => {
+            let write = args.split_once(':').and_then(|(range, data)| {
+                let (addr, len) = parse_range(range)?;
+                let bytes = parse_bytes(data)?;
+                (bytes.len() == len as usize && addr + len <= 0x10000).then_some((addr, bytes))
+            });
+            match write {
+                Some((addr, bytes)) => {
+                    for (offset, byte) in bytes.into_iter().enumerate() {
+                        sys.mem.poke((addr as usize + offset) as Addr, byte);
+                    }
+                    "OK".to_owned()
+                }
+                None => error(),
+            }
+        }
+        "c" | "s" => {
+            if !args.is_empty() {
+                match parse_hex(args) {
+                    Some(addr) => sys.regs.set_16(CpuReg16::PC, addr as Addr),
+                    None => {
+                        client.send(&error());
+                        return Ok(());
+                    }
+                }
+            }
+            let step = (kind == "s").then_some(Step::Into);
+            resume(sys, step);
+            client.is_running = true;
+            return Ok(());
+        }
+        "Z" | "z" => match parse_point(args) {
+            Some((type_, addr, len)) => {
+                let is_done = if kind == "Z" {
+                    insert_point(sys, type_, addr, len)
+                } else {
+                    remove_point(sys, type_, addr, len)
+                };
+                if is_done {
+                    "OK".to_owned()
+                } else {
+                    String::new()
+                }
+            }
+            None => error(),
+        },
+        "k" => {
+            resume(sys, None);
+            return Err("GDB killed the session.".to_owned());
+        }
+        "D" => {
+            client.send("OK");
+            resume(sys, None);
+            return Err("GDB detached.".to_owned());
+        }
+        "H" => "OK".to_owned(),
+        "q" => match args.split(':').next().unwrap_or("") {
+            "Supported" => SUPPORTED.to_owned(),
+            "Attached" => "1".to_owned(),
+            "C" => "QC1".to_owned(),
+            "fThreadInfo" => "m1".to_owned(),
+            "sThreadInfo" => "l".to_owned(),
+            _ => String::new(),
+        },
+        "Q" if args == "StartNoAckMode" => {
+            client.send("OK");
+            client.no_ack = true;
+            return Ok(());
+        }
+        _ => String::new(),
+    };
+
+    client.send(&reply);
+    return Ok(());
+}
+
+/// `S05`, or `T05watch:ADDR;` and the like for a watchpoint.
+fn stop_reply(reason: Option<BreakReason>) -> String {
+    match reason {
+        Some(BreakReason::Watchpoint(hit)) => {
+            let kind = match hit.kind {
+                AccessKind::Read => "rwatch",
+                AccessKind::Write => "watch",
+            };
+            format!("T{:02x}{}:{:04x};", SIGTRAP, kind, hit.addr)
+        }
+        _ => format!("S{:02x}", SIGTRAP),
+    }
+}
+
+/// Adds a breakpoint (types 0 and 1), or a watchpoint on each byte in the
+/// range for writes (2), reads (3) or both (4). Returns false for any
+/// other type.
+fn insert_point(sys: &mut Sys, type_: u32, addr: u32, len: u32) -> bool {
+    match type_ {
+        0 | 1 => {
+            let breakpoint = Breakpoint::new(addr as Addr, None);
+            if !sys.debugger.breakpoints.contains(&breakpoint) {
+                sys.debugger.breakpoints.push(breakpoint);
+            }
+        }
+        2..=4 => {
+            for watchpoint in watchpoints(type_, addr, len) {
+                sys.mem.watchpoints.add(watchpoint);
+            }
+        }
+        _ => return false,
+    }
+    return true;
+}
+
+/// Removes what `insert_point` added.
+fn remove_point(sys: &mut Sys, type_: u32, addr: u32, len: u32) -> bool {
+    match type_ {
+        0 | 1 => {
+            let breakpoint = Breakpoint::new(addr as Addr, None);
+            sys.debugger
+                .breakpoints
+                .retain(|existing| *existing != breakpoint);
+        }
+        2..=4 => {
+            for watchpoint in watchpoints(type_, addr, len) {
+                let list = sys.mem.watchpoints.list();
+                if let Some(idx) = list.iter().position(|existing| *existing == watchpoint) {
+                    sys.mem.watchpoints.remove(idx);
+                }
+            }
+        }
+        _ => return false,
+    }
+    return true;
+}
+
+fn watchpoints(type_: u32, addr: u32, len: u32) -> impl Iterator<Item = Watchpoint> {
+    let end = addr.saturating_add(len.max(1)).min(0x10000);
+    (addr..end).map(move |addr| Watchpoint {
+        addr: addr as Addr,
+        on_read: type_ != 2,
+        on_write: type_ != 3,
+    })
+}
+
+fn error() -> String {
+    "E01".to_owned()
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
+
+fn format_le16(value: u16) -> String {
+    let [lo, hi] = value.to_le_bytes();
+    return format!("{:02x}{:02x}", lo, hi);
+}
+
+fn parse_hex(s: &str) -> Option<u32> {
+    u32::from_str_radix(s, 16).ok()
+}
+
+fn parse_bytes(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    return (0..s.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(s.get(idx..idx + 2)?, 16).ok())
+        .collect();
+}
+
+fn parse_le16(s: &str) -> Option<u16> {
+    match parse_bytes(s)?.as_slice() {
+        [lo, hi] => Some(u16::from_le_bytes([*lo, *hi])),
+        _ => None,
+    }
+}
+
+/// All the registers, as sent by `g`.
+fn parse_regs(s: &str) -> Option<Vec<u16>> {
+    let bytes = parse_bytes(s)?;
+    if bytes.len() < 2 * REGS.len() {
+        return None;
+    }
+    return Some(
+        bytes
+            .chunks(2)
+            .take(REGS.len())
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect(),
+    );
+}
+
+/// `ADDR,LEN`, where the address is in the CPU's address space.
+fn parse_range(s: &str) -> Option<(u32, u32)> {
+    let (addr, len) = s.split_once(',')?;
+    let addr = parse_hex(addr).filter(|addr| *addr <= 0xFFFF)?;
+    return Some((addr, parse_hex(len)?));
+}
+
+/// `TYPE,ADDR,KIND`, where the kind is the length for watchpoints.
+fn parse_point(s: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = s.split(',');
+    let type_ = parse_hex(parts.next()?)?;
+    let (addr, len) = parse_range(&parts.collect::<Vec<_>>().join(","))?;
+    return Some((type_, addr, len));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::Duration};
+
+    use crate::test::headless::{create_headless_sys, lock_headless};
+
+    use super::*;
+
+    /// GDB's side of the connection.
+    struct TestClient {
+        stream: TcpStream,
+    }
+
+    impl TestClient {
+        fn request(&mut self, stub: &mut GdbStub, sys: &mut Sys, data: &str) -> String {
+            let packet = format!("${}#{:02x}", data, checksum_of(data.as_bytes()));
+            self.stream.write_all(packet.as_bytes()).unwrap();
+            return self.reply(stub, sys);
+        }
+
+        /// Updates the stub until a whole reply has come back.
+        fn reply(&mut self, stub: &mut GdbStub, sys: &mut Sys) -> String {
+            let mut received = vec![];
+            let mut buf = [0; 256];
+            loop {
+                stub.update(sys);
+                if let Ok(n) = self.stream.read(&mut buf) {
+                    received.extend_from_slice(&buf[..n]);
+                }
+
+                let text = String::from_utf8_lossy(&received).into_owned();
+                let text = text.trim_start_matches('+');
+                if let Some((data, checksum)) =
+                    text.strip_prefix('$').and_then(|s| s.split_once('#'))
+                {
+                    if checksum.len() == 2 {
+                        return data.to_owned();
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_gdb_session() {
+        let _lock = lock_headless();
+        let rom = Path::new("assets/files/custom_roms/ld_r8_r8/rom.gb");
+        let mut sys = create_headless_sys(rom, None).unwrap();
+        let mut stub = GdbStub::listen("127.0.0.1:0").unwrap();
+
+        let stream = TcpStream::connect(stub.local_addr().unwrap()).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+        let mut client = TestClient { stream };
+        let mut request = |sys: &mut Sys, data: &str| client.request(&mut stub, sys, data);
+
+        assert!(request(&mut sys, "qSupported:swbreak+").contains("QStartNoAckMode+"));
+        assert!(sys.emu.is_paused);
+        assert_eq!(request(&mut sys, "?"), "S05");
+
+        sys.regs.set_16(CpuReg16::BC, 0x1234);
+        sys.regs.set_16(CpuReg16::PC, 0x0150);
+        let regs = request(&mut sys, "g");
+        assert_eq!(&regs[4..8], "3412");
+        assert_eq!(&regs[20..24], "5001");
+        assert_eq!(request(&mut sys, "P1=7856"), "OK");
+        assert_eq!(sys.regs.get_16(CpuReg16::BC), 0x5678);
+        assert_eq!(request(&mut sys, "p1"), "7856");
+
+        assert_eq!(request(&mut sys, "Mc000,3:0a0b0c"), "OK");
+        assert_eq!(request(&mut sys, "mc000,4"), "0a0b0c00");
+        assert_eq!(request(&mut sys, "m10000,1"), "E01");
+
+        assert_eq!(request(&mut sys, "Z0,150,1"), "OK");
+        assert_eq!(sys.debugger.breakpoints, [Breakpoint::new(0x0150, None)]);
+        assert_eq!(request(&mut sys, "z0,150,1"), "OK");
+        assert!(sys.debugger.breakpoints.is_empty());
+        assert_eq!(request(&mut sys, "Z2,c000,2"), "OK");
+        assert_eq!(sys.mem.watchpoints.list().len(), 2);
+        assert_eq!(request(&mut sys, "z2,c000,2"), "OK");
+        assert!(sys.mem.watchpoints.list().is_empty());
+
+        assert_eq!(request(&mut sys, "vMustReplyEmpty"), "");
+    }
+
+    #[test]
+    fn test_next_incoming() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut client = Client {
+            stream,
+            rx: b"+$g#67\x03$m0,1#00$?#3".to_vec(),
+            no_ack: false,
+            is_running: false,
+        };
+
+        assert_eq!(
+            client.next_incoming(),
+            Some(Incoming::Packet("g".to_owned()))
+        );
+        assert_eq!(client.next_incoming(), Some(Incoming::Interrupt));
+        assert_eq!(client.next_incoming(), Some(Incoming::BadChecksum));
+        assert_eq!(client.next_incoming(), None);
+        assert_eq!(client.rx, b"$?#3");
+    }
+}
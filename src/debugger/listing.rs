@@ -0,0 +1,386 @@
+// Disassembles a whole ROM (or one bank of it) into a listing:
+//
+//   ; Bank $01
+//
+//   L_01_4000:
+//     01:4000  F0 44     ldh a, [$FF44]
+//     01:4002  FE 90     cp a, $90
+//   .l_4004:
+//     01:4004  20 FE     jr nz, .l_4004
+//
+// Bank 0 is at $0000-$3FFF, and every other bank at $4000-$7FFF. Targets of
+// `jp`, `call` and `rst` get global labels named after their bank and
+// address, and targets only reached by `jr` get local ones. The ROM is
+// decoded linearly, so data between code shows up as (nonsense)
+// instructions, except for the cartridge header, which is listed as bytes.
+// Given a code/data log, bytes it only saw read as data are listed as bytes
+// too, and an instruction it didn't see run is cut short rather than run
+// into bytes it saw used otherwise.
+//
+// When there's a symbol file next to the ROM, its names are used instead,
+// and for the memory operands it has names for.
+
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, BufWriter, Write},
+};
+
+use crate::{
+    cpu::{
+        disasm::{branch_target, disassemble},
+        instr::{decode_at, Instr},
+    },
+    mem::Addr,
+    other::cli::DisasmArgs,
+};
+
+use super::{
+    cdl::{is_data, CodeDataLog, OPCODE},
+    symbols::{Symbol, Symbols},
+};
+
+const BANK_LEN: usize = 0x4000;
+const HEADER_DATA: std::ops::Range<Addr> = 0x0104..0x0150;
+const DB_ROW_LEN: u16 = 8;
+
+/// Runs the `disasm` subcommand.
+pub fn run_disasm(args: &DisasmArgs) -> Result<(), String> {
+    let rom = fs::read(&args.rom_path)
+        .map_err(|err| format!("Unable to read {}: {}", args.rom_path.display(), err))?;
+
+    let symbols = Symbols::load_for_rom(&args.rom_path)?.unwrap_or_else(Symbols::new);
+    let cdl = match &args.cdl_path {
+        Some(path) => Some(CodeDataLog::load(path, rom.len())?),
+        None => None,
+    };
+    let cdl_flags = cdl.as_ref().map(|cdl| cdl.flags());
+
+    let bank_count = rom.len().div_ceil(BANK_LEN);
+    let banks = match args.bank {
+        Some(bank) if bank >= bank_count => {
+            return Err(format!(
+                "The ROM only has {} banks, so it has no bank {}.",
+                bank_count, bank
+            ));
+        }
+        Some(bank) => bank..bank + 1,
+        None => 0..bank_count,
+    };
+
+    let result = match &args.out_path {
+        Some(path) => {
+            let file = fs::File::create(path)
+                .map_err(|err| format!("Unable to create {}: {}", path.display(), err))?;
+            write_listing(&rom, banks, &symbols, cdl_flags, &mut BufWriter::new(file))
+        }
+        None => write_listing(&rom, banks, &symbols, cdl_flags, &mut io::stdout().lock()),
+    };
+
+    return result.map_err(|err| format!("Unable to write the listing: {}", err));
+}
+
+/// Writes the listing for `banks`. `cdl_flags` are the flags from a
+/// code/data log of the ROM, if there is one.
+pub fn write_listing(
+    rom: &[u8],
+    banks: std::ops::Range<usize>,
+    symbols: &Symbols,
+    cdl_flags: Option<&[u8]>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    for bank in banks {
+        let start = bank * BANK_LEN;
+        let end = usize::min(start + BANK_LEN, rom.len());
+        let view = BankView {
+            bank,
+            base: if bank == 0 { 0x0000 } else { 0x4000 },
+            data: &rom[start..end],
+            cdl_flags: cdl_flags.and_then(|flags| flags.get(start..end)),
+        };
+        write_bank(&view, symbols, out)?;
+    }
+
+    return out.flush();
+}
+
+/// The bank's contents as they appear in the address space.
+struct BankView<'a> {
+    bank: usize,
+    base: Addr,
+    data: &'a [u8],
+    cdl_flags: Option<&'a [u8]>,
+}
+
+impl BankView<'_> {
+    fn end(&self) -> Addr {
+        self.base + self.data.len() as Addr
+    }
+
+    fn read(&self, addr: Addr) -> u8 {
+        let offset = addr.wrapping_sub(self.base) as usize;
+        return self.data.get(offset).copied().unwrap_or(0xFF);
+    }
+
+    /// What the code/data log saw `addr` used for, or nothing without one.
+    fn cdl_flags(&self, addr: Addr) -> u8 {
+        let offset = addr.wrapping_sub(self.base) as usize;
+        return self
+            .cdl_flags
+            .and_then(|flags| flags.get(offset))
+            .copied()
+            .unwrap_or(0);
+    }
+
+    /// The symbol at `addr`, or the nearest one before it. In RAM, symbols
+    /// from any bank are used.
+    fn symbol<'a>(&self, symbols: &'a Symbols, addr: Addr) -> Option<&'a Symbol> {
+        let bank = match addr {
+            0x0000..=0x3FFF => Some(0),
+            // From bank 0, there's no telling which bank is mapped here.
+            0x4000..=0x7FFF if self.bank == 0 => return None,
+            0x4000..=0x7FFF => Some(self.bank),
+            _ => None,
+        };
+        return symbols.nearest(addr, bank);
+    }
+
+    fn global_label(&self, addr: Addr) -> Option<String> {
+        match addr {
+            0x0000..=0x3FFF => Some(format!("L_00_{:04X}", addr)),
+            0x4000..=0x7FFF if self.bank > 0 => Some(format!("L_{:02X}_{:04X}", self.bank, addr)),
+            _ => None,
+        }
+    }
+}
+
+/// An instruction, or a row of bytes that aren't code.
+struct Item {
+    addr: Addr,
+    size: u16,
+    is_data: bool,
+}
+
+fn write_bank(view: &BankView, symbols: &Symbols, out: &mut impl Write) -> io::Result<()> {
+    let bank = view.bank;
+    let read = |addr: Addr| view.read(addr);
+    let items = split_items(view);
+
+    // Labels are found first, so they can be written before the
+    // instructions they're on.
+    let mut globals = HashSet::new();
+    let mut locals = HashSet::new();
+    for item in items.iter().filter(|item| !item.is_data) {
+        let Ok(instr) = decode_at(item.addr, read) else {
+            continue;
+        };
+        let Some(target) = branch_target(instr, item.addr, read) else {
+            continue;
+        };
+        match instr {
+            Instr::Jr_Imm8 | Instr::Jr_Cond_Imm8 { .. } => locals.insert(target),
+            _ => globals.insert(target),
+        };
+    }
+
+    let is_in_bank = |addr: Addr| (view.base..view.end()).contains(&addr);
+    let symbol_name = |addr: Addr| {
+        let symbol = view.symbol(symbols, addr)?;
+        return (symbol.addr == addr).then_some(symbol.name.as_str());
+    };
+    let label = |addr: Addr| {
+        let is_target = globals.contains(&addr) || locals.contains(&addr);
+        if is_target && symbol_name(addr).is_none() {
+            if locals.contains(&addr) && !globals.contains(&addr) && is_in_bank(addr) {
+                return Some(format!(".l_{:04X}", addr));
+            }
+            return view.global_label(addr);
+        }
+        return view.symbol(symbols, addr).map(|symbol| symbol.label(addr));
+    };
+
+    writeln!(out, "; Bank ${:02X}", bank)?;
+    writeln!(out)?;
+
+    for item in items {
+        if let Some(name) = symbol_name(item.addr) {
+            writeln!(out, "{}:", name)?;
+        } else if globals.contains(&item.addr) || locals.contains(&item.addr) {
+            if let Some(label) = label(item.addr) {
+                writeln!(out, "{}:", label)?;
+            }
+        }
+
+        let bytes = (0..item.size)
+            .map(|idx| read(item.addr + idx))
+            .collect::<Vec<_>>();
+        let text = if item.is_data {
+            format_db(&bytes)
+        } else {
+            disassemble(item.addr, read, label).text
+        };
+        let hex = bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            out,
+            "  {:02X}:{:04X}  {:<8}  {}",
+            bank, item.addr, hex, text
+        )?;
+    }
+
+    return writeln!(out);
+}
+
+/// Splits the bank into instructions. The cartridge header, bytes the
+/// code/data log only saw read as data, and an instruction cut off by the
+/// end of the bank, are rows of bytes instead.
+fn split_items(view: &BankView) -> Vec<Item> {
+    let mut items = vec![];
+    let mut addr = view.base;
+
+    while addr < view.end() {
+        let remaining = view.end() - addr;
+        let item = if view.bank == 0 && HEADER_DATA.contains(&addr) {
+            Item {
+                addr,
+                size: u16::min(DB_ROW_LEN, HEADER_DATA.end - addr),
+                is_data: true,
+            }
+        } else if is_data(view.cdl_flags(addr)) {
+            let size = (addr..view.end())
+                .take(DB_ROW_LEN as usize)
+                .take_while(|&addr| is_data(view.cdl_flags(addr)))
+                .count();
+            Item {
+                addr,
+                size: size as u16,
+                is_data: true,
+            }
+        } else {
+            let size = match decode_at(addr, |addr| view.read(addr)) {
+                Ok(instr) => instr.size(),
+                Err(_) => 1,
+            };
+            let is_cut_short = view.cdl_flags(addr) & OPCODE == 0
+                && (1..size).any(|idx| {
+                    let flags = view.cdl_flags(addr.wrapping_add(idx));
+                    flags & OPCODE != 0 || is_data(flags)
+                });
+            Item {
+                addr,
+                size: if is_cut_short {
+                    1
+                } else {
+                    u16::min(size, remaining)
+                },
+                is_data: is_cut_short || size > remaining,
+            }
+        };
+
+        addr += item.size;
+        items.push(item);
+    }
+
+    return items;
+}
+
+fn format_db(bytes: &[u8]) -> String {
+    let bytes = bytes
+        .iter()
+        .map(|byte| format!("${:02X}", byte))
+        .collect::<Vec<_>>();
+    return format!("db {}", bytes.join(", "));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debugger::cdl::ReadUse;
+
+    use super::*;
+
+    #[test]
+    fn test_write_listing() {
+        let mut rom = vec![0x00; 2 * BANK_LEN];
+        rom[0x0100..0x0104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+        rom[0x0150..0x0155].copy_from_slice(&[0xCD, 0x00, 0x40, 0x18, 0xFE]);
+        rom[0x4000..0x4003].copy_from_slice(&[0x20, 0xFE, 0xC9]);
+        rom[2 * BANK_LEN - 1] = 0xCD;
+
+        let mut out = vec![];
+        write_listing(&rom, 0..2, &Symbols::new(), None, &mut out).unwrap();
+        let listing = String::from_utf8(out).unwrap();
+
+        for line in [
+            "; Bank $00",
+            "  00:0101  C3 50 01  jp L_00_0150",
+            "  00:0104  00 00 00 00 00 00 00 00  db $00, $00, $00, $00, $00, $00, $00, $00",
+            "L_00_0150:",
+            "  00:0150  CD 00 40  call $4000",
+            ".l_0153:",
+            "  00:0153  18 FE     jr .l_0153",
+            "; Bank $01",
+            ".l_4000:",
+            "  01:4000  20 FE     jr nz, .l_4000",
+            "  01:4002  C9        ret",
+            "  01:7FFF  CD        db $CD",
+        ] {
+            assert!(listing.lines().any(|l| l == line), "Missing: {}", line);
+        }
+    }
+
+    #[test]
+    fn test_write_listing_with_symbols() {
+        let mut rom = vec![0x00; 2 * BANK_LEN];
+        rom[0x0150..0x0156].copy_from_slice(&[0xCD, 0x00, 0x40, 0xFA, 0xA1, 0xC0]);
+        rom[0x4000..0x4005].copy_from_slice(&[0x20, 0xFE, 0xCD, 0x53, 0x01]);
+        let symbols = Symbols::parse("00:0150 Main\n01:4000 LoadLevel\n00:C0A0 wPlayerX").unwrap();
+
+        let mut out = vec![];
+        write_listing(&rom, 0..2, &symbols, None, &mut out).unwrap();
+        let listing = String::from_utf8(out).unwrap();
+
+        for line in [
+            "Main:",
+            "  00:0150  CD 00 40  call $4000",
+            "  00:0153  FA A1 C0  ld a, [wPlayerX+$1]",
+            "LoadLevel:",
+            "  01:4000  20 FE     jr nz, LoadLevel",
+            "  01:4002  CD 53 01  call L_00_0153",
+        ] {
+            assert!(listing.lines().any(|l| l == line), "Missing: {}", line);
+        }
+    }
+
+    #[test]
+    fn test_write_listing_with_cdl() {
+        let mut rom = vec![0x00; BANK_LEN];
+        // $0150 runs, then $0153-$0156 is only read as data. $0157 never
+        // ran, and reads into $0158, which did.
+        rom[0x0150..0x015A]
+            .copy_from_slice(&[0x21, 0x53, 0x01, 0x3E, 0x01, 0xC9, 0xFF, 0xFA, 0x00, 0xC9]);
+        let mut cdl = CodeDataLog::new(BANK_LEN);
+        cdl.log(0x0150, ReadUse::Opcode);
+        cdl.log(0x0151, ReadUse::Operand);
+        cdl.log(0x0152, ReadUse::Operand);
+        for offset in 0x0153..0x0157 {
+            cdl.log(offset, ReadUse::Data);
+        }
+        cdl.log(0x0158, ReadUse::Opcode);
+
+        let mut out = vec![];
+        write_listing(&rom, 0..1, &Symbols::new(), Some(cdl.flags()), &mut out).unwrap();
+        let listing = String::from_utf8(out).unwrap();
+
+        for line in [
+            "  00:0150  21 53 01  ld hl, $0153",
+            "  00:0153  3E 01 C9 FF  db $3E, $01, $C9, $FF",
+            "  00:0157  FA        db $FA",
+            "  00:0158  00        nop",
+        ] {
+            assert!(listing.lines().any(|l| l == line), "Missing: {}", line);
+        }
+    }
+}
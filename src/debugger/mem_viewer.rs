@@ -0,0 +1,403 @@
+// The memory panel in the debug UI shows the whole address space as hex, a
+// row of 16 bytes at a time. ROM, VRAM, cartridge RAM and WRAM can each be
+// shown in any of their banks instead of the one mapped. Bytes are read with
+// `Mem::peek_banked`, which has no side effects, so looking at IO registers
+// doesn't change them and watchpoints don't fire.
+//
+// Bytes the CPU or DMA wrote in the last RECENT_WRITE_FRAMES frames are
+// highlighted. While paused, the byte at the cursor can be edited by typing
+// two hex digits, which moves the cursor on to the next byte.
+
+use crate::{
+    cart::consts::ROM_BANK_SIZE,
+    mem::{mem::Mem, Addr},
+};
+
+use super::{console::parse_location, symbols::Symbols};
+
+pub const ROW_LEN: usize = 0x10;
+pub const VIEW_ROWS: usize = 16;
+
+/// How far a notch of the mouse wheel scrolls.
+pub const WHEEL_ROWS: i32 = 3;
+
+/// The last row that can be shown at the top, so the view never runs past
+/// $FFFF.
+const MAX_TOP: Addr = (0x10000 - VIEW_ROWS * ROW_LEN) as Addr;
+
+/// How long a write stays highlighted, in frames.
+const RECENT_WRITE_FRAMES: u32 = 60;
+
+/// When each address was last written, counted in frames.
+pub struct RecentWrites {
+    /// The frame of each address's last write, or 0 if it hasn't been
+    /// written.
+    frames: Vec<u32>,
+    now: u32,
+}
+
+impl RecentWrites {
+    pub fn new() -> Self {
+        Self {
+            frames: vec![0; 0x10000],
+            now: 1,
+        }
+    }
+
+    /// Call once per frame.
+    pub fn next_frame(&mut self) {
+        self.now += 1;
+    }
+
+    #[inline]
+    pub fn record(&mut self, addr: Addr) {
+        self.frames[addr as usize] = self.now;
+    }
+
+    pub fn is_recent(&self, addr: Addr) -> bool {
+        let frame = self.frames[addr as usize];
+        return frame != 0 && self.now - frame < RECENT_WRITE_FRAMES;
+    }
+}
+
+/// A part of the address space that's switched between banks.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BankedSection {
+    Rom,
+    Vram,
+    CartRam,
+    Wram,
+}
+
+impl BankedSection {
+    /// The section `addr` is in. ROM at $0000-$3FFF and WRAM at $C000-$CFFF
+    /// are always bank 0, so they aren't in one.
+    pub fn at(addr: Addr) -> Option<Self> {
+        match addr {
+            0x4000..=0x7FFF => Some(BankedSection::Rom),
+            0x8000..=0x9FFF => Some(BankedSection::Vram),
+            0xA000..=0xBFFF => Some(BankedSection::CartRam),
+            0xD000..=0xDFFF => Some(BankedSection::Wram),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BankedSection::Rom => "ROM",
+            BankedSection::Vram => "VRAM",
+            BankedSection::CartRam => "SRAM",
+            BankedSection::Wram => "WRAM",
+        }
+    }
+
+    /// The lowest bank that can be mapped here.
+    fn first_bank(self) -> usize {
+        match self {
+            BankedSection::Rom | BankedSection::Wram => 1,
+            BankedSection::Vram | BankedSection::CartRam => 0,
+        }
+    }
+
+    fn bank_count(self, mem: &Mem) -> usize {
+        match self {
+            BankedSection::Rom => mem.cart.rom_size() / ROM_BANK_SIZE,
+            BankedSection::Vram => mem.vram.num_banks(),
+            BankedSection::CartRam => mem.cart.ram_bank_count(),
+            BankedSection::Wram => mem.wram.num_banks(),
+        }
+    }
+
+    fn idx(self) -> usize {
+        self as usize
+    }
+}
+
+/// What the keyboard is being used for.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Input {
+    /// Typing an address or symbol to go to.
+    Goto(String),
+
+    /// Typing a byte at the cursor. Holds the high digit once it's typed.
+    Edit(Option<u8>),
+}
+
+pub struct MemViewer {
+    /// The first address shown, at the start of a row.
+    top: Addr,
+
+    /// Bank changes and edits apply here.
+    cursor: Addr,
+
+    /// The bank shown for each banked section, or `None` for the one mapped.
+    banks: [Option<usize>; 4],
+
+    input: Option<Input>,
+
+    /// Why the last goto failed, until the next one.
+    pub error: Option<String>,
+}
+
+impl MemViewer {
+    pub fn new() -> Self {
+        Self {
+            top: 0x0000,
+            cursor: 0x0000,
+            banks: [None; 4],
+            input: None,
+            error: None,
+        }
+    }
+
+    pub fn top(&self) -> Addr {
+        self.top
+    }
+
+    pub fn cursor(&self) -> Addr {
+        self.cursor
+    }
+
+    pub fn input(&self) -> Option<&Input> {
+        self.input.as_ref()
+    }
+
+    pub fn is_editing(&self) -> bool {
+        matches!(self.input, Some(Input::Edit(_)))
+    }
+
+    /// The bank shown for `section`, or `None` for the one mapped.
+    pub fn bank(&self, section: BankedSection) -> Option<usize> {
+        self.banks[section.idx()]
+    }
+
+    /// The bank to read `addr` from, or `None` for the one mapped.
+    pub fn bank_at(&self, addr: Addr) -> Option<usize> {
+        BankedSection::at(addr).and_then(|section| self.bank(section))
+    }
+
+    /// Moves the view by `rows`, taking the cursor along.
+    pub fn scroll(&mut self, rows: i32) {
+        let top = self.top as i32 + rows * ROW_LEN as i32;
+        let top = top.clamp(0, MAX_TOP as i32) as Addr;
+        self.cursor = self.cursor - self.top + top;
+        self.top = top;
+    }
+
+    /// Moves the cursor by `delta` bytes, scrolling to keep it in view.
+    pub fn move_cursor(&mut self, delta: i32) {
+        self.cursor = (self.cursor as i32 + delta).clamp(0, 0xFFFF) as Addr;
+        let row = self.cursor - self.cursor % ROW_LEN as Addr;
+        let last_row = self.top as usize + (VIEW_ROWS - 1) * ROW_LEN;
+        if self.cursor < self.top {
+            self.top = row;
+        } else if self.cursor as usize > last_row + ROW_LEN - 1 {
+            self.top = row - ((VIEW_ROWS - 1) * ROW_LEN) as Addr;
+        }
+        self.clear_high_digit();
+    }
+
+    /// Shows `addr` in the top row, in `bank` if given and `mem` has it.
+    pub fn go_to(&mut self, addr: Addr, bank: Option<usize>, mem: &Mem) {
+        if let (Some(section), Some(bank)) = (BankedSection::at(addr), bank) {
+            if (section.first_bank()..section.bank_count(mem)).contains(&bank) {
+                self.banks[section.idx()] = Some(bank);
+            }
+        }
+        self.cursor = addr;
+        self.top = Addr::min(addr - addr % ROW_LEN as Addr, MAX_TOP);
+        self.clear_high_digit();
+    }
+
+    /// Switches the section at the cursor to its next bank, then back to
+    /// following the mapped one after the last.
+    pub fn cycle_bank(&mut self, mem: &Mem) {
+        let Some(section) = BankedSection::at(self.cursor) else {
+            return;
+        };
+        let next = match self.bank(section) {
+            Some(bank) => bank + 1,
+            None => section.first_bank(),
+        };
+        self.banks[section.idx()] = Some(next).filter(|&bank| bank < section.bank_count(mem));
+    }
+
+    /// A digit typed for the byte at the old cursor doesn't carry over.
+    fn clear_high_digit(&mut self) {
+        if let Some(Input::Edit(high)) = &mut self.input {
+            *high = None;
+        }
+    }
+
+    pub fn start_goto(&mut self) {
+        self.input = Some(Input::Goto(String::new()));
+        self.error = None;
+    }
+
+    pub fn start_edit(&mut self) {
+        self.input = Some(Input::Edit(None));
+        self.error = None;
+    }
+
+    pub fn cancel_input(&mut self) {
+        self.input = None;
+    }
+
+    /// Adds to the goto text, or to the byte being edited, which is written
+    /// to `mem` once both digits are typed.
+    pub fn type_char(&mut self, c: char, mem: &mut Mem) {
+        match &mut self.input {
+            Some(Input::Goto(text)) => text.push(c),
+            Some(Input::Edit(high)) => {
+                let Some(digit) = c.to_digit(16) else {
+                    return;
+                };
+                let Some(high) = high.take() else {
+                    *high = Some(digit as u8);
+                    return;
+                };
+                mem.poke_banked(
+                    self.cursor,
+                    self.bank_at(self.cursor),
+                    high << 4 | digit as u8,
+                );
+                self.move_cursor(1);
+            }
+            None => {}
+        }
+    }
+
+    /// Deletes the last goto character, or the high digit being edited.
+    pub fn backspace(&mut self) {
+        match &mut self.input {
+            Some(Input::Goto(text)) => {
+                text.pop();
+            }
+            Some(Input::Edit(high)) => *high = None,
+            None => {}
+        }
+    }
+
+    /// Goes to the address or symbol typed, or ends editing.
+    pub fn confirm(&mut self, symbols: &Symbols, mem: &Mem) {
+        if let Some(Input::Goto(text)) = self.input.take() {
+            match parse_location(text.trim(), symbols) {
+                Ok((addr, bank)) => self.go_to(addr, bank, mem),
+                Err(err) => self.error = Some(err),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        sys::Sys,
+        test::headless::{create_headless_sys, lock_headless},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_scroll_and_move_cursor() {
+        let mut viewer = MemViewer::new();
+        viewer.move_cursor(-1);
+        assert_eq!((viewer.top(), viewer.cursor()), (0x0000, 0x0000));
+
+        // Past the last row shown.
+        viewer.move_cursor(0x0100);
+        assert_eq!((viewer.top(), viewer.cursor()), (0x0010, 0x0100));
+
+        viewer.scroll(2);
+        assert_eq!((viewer.top(), viewer.cursor()), (0x0030, 0x0120));
+        viewer.scroll(0x1000);
+        assert_eq!((viewer.top(), viewer.cursor()), (MAX_TOP, 0xFFF0));
+        viewer.move_cursor(0x100);
+        assert_eq!(viewer.cursor(), 0xFFFF);
+    }
+
+    fn create_sys() -> Sys {
+        let rom = Path::new("assets/files/custom_roms/ld_r8_r8/rom.gb");
+        return create_headless_sys(rom, None).unwrap();
+    }
+
+    #[test]
+    fn test_goto() {
+        let _lock = lock_headless();
+        let mut sys = create_sys();
+        let symbols = Symbols::parse("01:4A20 LoadLevel\n00:D000 Bad\n01:9800 Map").unwrap();
+        let mut viewer = MemViewer::new();
+
+        viewer.start_goto();
+        for c in "LoadLevel+3".chars() {
+            viewer.type_char(c, &mut sys.mem);
+        }
+        viewer.confirm(&symbols, &sys.mem);
+        assert_eq!((viewer.top(), viewer.cursor()), (0x4A20, 0x4A23));
+        assert_eq!(viewer.bank(BankedSection::Rom), Some(1));
+        assert_eq!(viewer.input(), None);
+
+        viewer.start_goto();
+        viewer.type_char('X', &mut sys.mem);
+        viewer.confirm(&symbols, &sys.mem);
+        assert!(viewer.error.is_some());
+        assert_eq!(viewer.cursor(), 0x4A23);
+
+        // Banks the system doesn't have are left to follow the mapped one.
+        for name in ["Bad", "Map"] {
+            viewer.start_goto();
+            for c in name.chars() {
+                viewer.type_char(c, &mut sys.mem);
+            }
+            viewer.confirm(&symbols, &sys.mem);
+        }
+        assert_eq!(viewer.bank(BankedSection::Wram), None);
+        assert_eq!(viewer.bank(BankedSection::Vram), None);
+        assert_eq!(sys.mem.peek_banked(0xD000, Some(0)), 0xFF);
+        assert_eq!(sys.mem.peek_banked(0x9800, Some(1)), 0xFF);
+
+        viewer.go_to(0xFFFE, None, &sys.mem);
+        assert_eq!((viewer.top(), viewer.cursor()), (MAX_TOP, 0xFFFE));
+    }
+
+    #[test]
+    fn test_banks_and_edits() {
+        let _lock = lock_headless();
+        let mut sys = create_sys();
+        let mut viewer = MemViewer::new();
+
+        // DMG WRAM has only bank 1 at $D000.
+        viewer.go_to(0xD000, None, &sys.mem);
+        viewer.cycle_bank(&sys.mem);
+        assert_eq!(viewer.bank_at(0xD000), Some(1));
+        viewer.cycle_bank(&sys.mem);
+        assert_eq!(viewer.bank_at(0xD000), None);
+
+        viewer.cycle_bank(&sys.mem);
+        viewer.start_edit();
+        for c in "4a2".chars() {
+            viewer.type_char(c, &mut sys.mem);
+        }
+        assert_eq!(sys.mem.read(0xD000), 0x4A);
+        assert_eq!(viewer.cursor(), 0xD001);
+        assert_eq!(viewer.input(), Some(&Input::Edit(Some(2))));
+
+        // Writes to ROM change the ROM.
+        viewer.go_to(0x0100, None, &sys.mem);
+        viewer.start_edit();
+        viewer.type_char('0', &mut sys.mem);
+        viewer.type_char('0', &mut sys.mem);
+        assert_eq!(sys.mem.peek_banked(0x0100, None), 0x00);
+
+        let mut recent = RecentWrites::new();
+        recent.record(0xC000);
+        assert!(recent.is_recent(0xC000));
+        assert!(!recent.is_recent(0xC001));
+        for _ in 0..RECENT_WRITE_FRAMES {
+            recent.next_frame();
+        }
+        assert!(!recent.is_recent(0xC000));
+    }
+}
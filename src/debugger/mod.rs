@@ -0,0 +1,12 @@
+pub mod call_stack;
+pub mod cdl;
+pub mod console;
+pub mod debugger;
+pub mod expr;
+pub mod gdb;
+pub mod listing;
+pub mod mem_viewer;
+pub mod profiler;
+pub mod symbols;
+pub mod trace;
+pub mod watch;
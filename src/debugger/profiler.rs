@@ -0,0 +1,359 @@
+// Counts the M-cycles spent in each routine. Cycles are kept per call path,
+// in a tree that follows the shadow call stack, so a routine's exclusive
+// cycles (spent in it directly) and inclusive cycles (also spent in what it
+// calls) both come from the same counts.
+//
+// Profiles are saved as folded stacks, which flamegraph.pl, inferno and
+// speedscope read. Each line is a call path, outermost first, then the
+// M-cycles spent directly in its last routine:
+//
+//   (root);Main;UpdateActors;01:4A20 1520
+//
+// Routines are named by their symbol, or by their bank and entry address.
+// The cycles of an instruction count towards the routine it starts in, so a
+// CALL counts towards the caller and a RET towards the callee. Time spent
+// halted or dispatching interrupts isn't counted.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::sys::Sys;
+
+use super::{call_stack::Routine, symbols::Symbols};
+
+const ROOT_NAME: &str = "(root)";
+
+struct Node {
+    /// `None` for the root, where code not called from anywhere runs.
+    routine: Option<Routine>,
+    parent: usize,
+    children: HashMap<Routine, usize>,
+    cycles: u64,
+}
+
+/// The cycles counted for one routine, across every path it was called by.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RoutineCycles {
+    pub routine: Option<Routine>,
+    pub inclusive: u64,
+    pub exclusive: u64,
+}
+
+impl RoutineCycles {
+    pub fn name(&self, symbols: &Symbols) -> String {
+        match self.routine {
+            Some(routine) => routine.name(symbols),
+            None => ROOT_NAME.to_owned(),
+        }
+    }
+}
+
+pub struct Profiler {
+    /// The root is first.
+    nodes: Vec<Node>,
+    node: usize,
+
+    /// Where the instruction running now started.
+    instr_node: usize,
+    is_running: bool,
+}
+
+impl Profiler {
+    /// Starts profiling inside `routines`, the outermost first.
+    pub fn new(routines: impl IntoIterator<Item = Routine>) -> Self {
+        let root = Node {
+            routine: None,
+            parent: 0,
+            children: HashMap::new(),
+            cycles: 0,
+        };
+        let mut profiler = Self {
+            nodes: vec![root],
+            node: 0,
+            instr_node: 0,
+            is_running: true,
+        };
+        for routine in routines {
+            profiler.enter(routine);
+        }
+        return profiler;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.is_running
+    }
+
+    /// Stops counting. What's counted so far is kept.
+    pub fn stop(&mut self) {
+        self.is_running = false;
+    }
+
+    pub fn enter(&mut self, routine: Routine) {
+        let next_idx = self.nodes.len();
+        let node = &mut self.nodes[self.node];
+        let child = *node.children.entry(routine).or_insert(next_idx);
+        if child == next_idx {
+            self.nodes.push(Node {
+                routine: Some(routine),
+                parent: self.node,
+                children: HashMap::new(),
+                cycles: 0,
+            });
+        }
+        self.node = child;
+    }
+
+    /// Returns from `count` routines, stopping at the root.
+    pub fn leave(&mut self, count: usize) {
+        for _ in 0..count {
+            self.node = self.nodes[self.node].parent;
+        }
+    }
+
+    /// Call before the CPU executes an instruction.
+    #[inline]
+    pub fn begin_instr(&mut self) {
+        self.instr_node = self.node;
+    }
+
+    /// Call after the CPU executes an instruction, with how long it took.
+    #[inline]
+    pub fn end_instr(&mut self, cycles: u32) {
+        self.nodes[self.instr_node].cycles += cycles as u64;
+    }
+
+    pub fn total_cycles(&self) -> u64 {
+        self.nodes.iter().map(|node| node.cycles).sum()
+    }
+
+    /// Every routine that ran, the most exclusive cycles first.
+    pub fn routines(&self) -> Vec<RoutineCycles> {
+        let mut totals: HashMap<Option<Routine>, (u64, u64)> = HashMap::new();
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if node.cycles == 0 {
+                continue;
+            }
+
+            totals.entry(node.routine).or_default().1 += node.cycles;
+
+            // A recursive routine is on the path more than once, but its
+            // inclusive cycles only count once.
+            let mut path = self.path(idx);
+            path.sort();
+            path.dedup();
+            for routine in path {
+                totals.entry(routine).or_default().0 += node.cycles;
+            }
+        }
+
+        let mut routines = totals
+            .into_iter()
+            .map(|(routine, (inclusive, exclusive))| RoutineCycles {
+                routine,
+                inclusive,
+                exclusive,
+            })
+            .collect::<Vec<_>>();
+        routines.sort_by_key(|routine| {
+            (
+                u64::MAX - routine.exclusive,
+                u64::MAX - routine.inclusive,
+                routine.routine,
+            )
+        });
+        return routines;
+    }
+
+    /// Writes a line per call path that spent any cycles.
+    pub fn write_folded(&self, symbols: &Symbols, out: &mut impl Write) -> io::Result<()> {
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if node.cycles == 0 {
+                continue;
+            }
+
+            let names = self
+                .path(idx)
+                .into_iter()
+                .map(|routine| match routine {
+                    Some(routine) => routine.name(symbols),
+                    None => ROOT_NAME.to_owned(),
+                })
+                .collect::<Vec<_>>();
+            writeln!(out, "{} {}", names.join(";"), node.cycles)?;
+        }
+        return Ok(());
+    }
+
+    /// The routines from the root to the node, the root first.
+    fn path(&self, mut idx: usize) -> Vec<Option<Routine>> {
+        let mut path = vec![self.nodes[idx].routine];
+        while idx != 0 {
+            idx = self.nodes[idx].parent;
+            path.push(self.nodes[idx].routine);
+        }
+        path.reverse();
+        return path;
+    }
+}
+
+/// Starts a new profile, from inside the routines running now.
+pub fn start_profile(sys: &mut Sys) {
+    let frames = sys.debugger.call_stack.frames();
+    let profiler = Profiler::new(frames.iter().map(|frame| frame.routine));
+    sys.debugger.profiler = Some(profiler);
+}
+
+/// Call before the CPU executes the instruction at PC.
+#[inline]
+pub fn begin_profiled_instr(sys: &mut Sys) {
+    if let Some(profiler) = &mut sys.debugger.profiler {
+        profiler.begin_instr();
+    }
+}
+
+/// Call after the CPU executes an instruction, with how long it took.
+#[inline]
+pub fn end_profiled_instr(sys: &mut Sys, cycles: u32) {
+    if let Some(profiler) = &mut sys.debugger.profiler {
+        if profiler.is_running() {
+            profiler.end_instr(cycles);
+        }
+    }
+}
+
+/// Saves the profile as folded stacks.
+pub fn save_profile(sys: &Sys, path: &Path) -> Result<(), String> {
+    let Some(profiler) = &sys.debugger.profiler else {
+        return Err("Not profiling.".to_owned());
+    };
+
+    let write_err = |err: io::Error| format!("Unable to write {}: {}", path.display(), err);
+    let file = File::create(path).map_err(write_err)?;
+    let mut out = BufWriter::new(file);
+    profiler
+        .write_folded(&sys.debugger.symbols, &mut out)
+        .map_err(write_err)?;
+    out.flush().map_err(write_err)?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIN: Routine = Routine {
+        addr: 0x0150,
+        bank: Some(0),
+    };
+    const UPDATE: Routine = Routine {
+        addr: 0x4000,
+        bank: Some(1),
+    };
+    const DRAW: Routine = Routine {
+        addr: 0x4000,
+        bank: Some(2),
+    };
+
+    fn run(profiler: &mut Profiler, cycles: u32) {
+        profiler.begin_instr();
+        profiler.end_instr(cycles);
+    }
+
+    /// Main calls Update, which calls Draw, which calls itself, then Main
+    /// calls Draw.
+    fn profile() -> Profiler {
+        let mut profiler = Profiler::new([MAIN]);
+        run(&mut profiler, 1);
+
+        // The CALL counts towards the caller.
+        profiler.begin_instr();
+        profiler.enter(UPDATE);
+        profiler.end_instr(6);
+
+        run(&mut profiler, 10);
+        profiler.enter(DRAW);
+        run(&mut profiler, 20);
+        profiler.enter(DRAW);
+        run(&mut profiler, 30);
+        profiler.leave(2);
+        run(&mut profiler, 3);
+
+        // The RET counts towards the callee.
+        profiler.begin_instr();
+        profiler.leave(1);
+        profiler.end_instr(4);
+
+        profiler.enter(DRAW);
+        run(&mut profiler, 50);
+        profiler.leave(1);
+        return profiler;
+    }
+
+    #[test]
+    fn test_routines() {
+        let profiler = profile();
+        assert_eq!(profiler.total_cycles(), 124);
+        assert_eq!(
+            profiler.routines(),
+            vec![
+                RoutineCycles {
+                    routine: Some(DRAW),
+                    inclusive: 100,
+                    exclusive: 100,
+                },
+                RoutineCycles {
+                    routine: Some(UPDATE),
+                    inclusive: 67,
+                    exclusive: 17,
+                },
+                RoutineCycles {
+                    routine: Some(MAIN),
+                    inclusive: 124,
+                    exclusive: 7,
+                },
+                RoutineCycles {
+                    routine: None,
+                    inclusive: 124,
+                    exclusive: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_leave_stops_at_root() {
+        let mut profiler = Profiler::new([]);
+        profiler.enter(MAIN);
+        profiler.leave(3);
+        run(&mut profiler, 5);
+        assert_eq!(
+            profiler.routines(),
+            vec![RoutineCycles {
+                routine: None,
+                inclusive: 5,
+                exclusive: 5,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_write_folded() {
+        let symbols = Symbols::parse("00:0150 Main\n01:4000 Update").unwrap();
+        let mut out = vec![];
+        profile().write_folded(&symbols, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+(root);Main 7
+(root);Main;Update 17
+(root);Main;Update;02:4000 20
+(root);Main;Update;02:4000;02:4000 30
+(root);Main;02:4000 50
+"
+        );
+    }
+}
@@ -0,0 +1,241 @@
+// Symbols are read from the `.sym` file next to the ROM, as written by
+// `rgblink -n` and read by no$gmb, BGB and Emulicious:
+//
+//   ; File generated by rgblink
+//   00:0150 Main
+//   00:0158 Main.loop
+//   01:4000 LoadLevel
+//   00:C0A0 wPlayerX
+//   02:D000 wLevelData
+//
+// Each line is a bank and an address in hex, then the name. The bank is the
+// ROM bank for $0000-$7FFF, the VRAM bank for $8000-$9FFF, the SRAM bank for
+// $A000-$BFFF and the WRAM bank for $C000-$DFFF, and 0 elsewhere. Anything
+// after a `;` is a comment.
+//
+// An address without a symbol of its own is shown as an offset from the
+// nearest symbol before it in the same bank and memory section, e.g.
+// `Main+$3`.
+
+use std::{fs, path::Path};
+
+use crate::{
+    mem::{sections::MemSection, Addr},
+    sys::Sys,
+};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Symbol {
+    pub bank: usize,
+    pub addr: Addr,
+    pub name: String,
+}
+
+impl Symbol {
+    /// The name, with the offset from it to `addr` if there is one.
+    pub fn label(&self, addr: Addr) -> String {
+        match addr - self.addr {
+            0 => self.name.clone(),
+            offset => format!("{}+${:X}", self.name, offset),
+        }
+    }
+}
+
+pub struct Symbols {
+    /// Sorted by address. Symbols at the same address stay in file order.
+    list: Vec<Symbol>,
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self { list: vec![] }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut list = vec![];
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.split(';').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let symbol = parse_symbol(line)
+                .ok_or_else(|| format!("Invalid symbol on line {}: {}", idx + 1, line))?;
+            list.push(symbol);
+        }
+
+        list.sort_by_key(|symbol| symbol.addr);
+        return Ok(Self { list });
+    }
+
+    /// Loads the `.sym` file next to the ROM, if there is one.
+    pub fn load_for_rom(rom_path: &Path) -> Result<Option<Self>, String> {
+        let path = rom_path.with_extension("sym");
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let text = fs::read_to_string(&path)
+            .map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+        let symbols = Self::parse(&text).map_err(|msg| format!("{}: {}", path.display(), msg))?;
+        return Ok(Some(symbols));
+    }
+
+    pub fn count(&self) -> usize {
+        self.list.len()
+    }
+
+    /// Every symbol, sorted by address.
+    pub fn list(&self) -> &[Symbol] {
+        &self.list
+    }
+
+    /// The symbol with this name.
+    pub fn find(&self, name: &str) -> Option<&Symbol> {
+        self.list.iter().find(|symbol| symbol.name == name)
+    }
+
+    /// The name of the symbol at `addr`, if it has one. A bank of `None`
+    /// matches symbols in any bank.
+    pub fn name(&self, addr: Addr, bank: Option<usize>) -> Option<&str> {
+        let symbol = self.nearest(addr, bank)?;
+        return (symbol.addr == addr).then_some(symbol.name.as_str());
+    }
+
+    /// The name of the symbol at `addr`, or the nearest one before it with
+    /// an offset.
+    pub fn label(&self, addr: Addr, bank: Option<usize>) -> Option<String> {
+        return Some(self.nearest(addr, bank)?.label(addr));
+    }
+
+    /// The first symbol at the highest address up to `addr`, in the same
+    /// memory section.
+    pub fn nearest(&self, addr: Addr, bank: Option<usize>) -> Option<&Symbol> {
+        let section = MemSection::from_abs_addr(addr);
+        let end = self.list.partition_point(|symbol| symbol.addr <= addr);
+
+        let mut nearest: Option<&Symbol> = None;
+        for symbol in self.list[..end].iter().rev() {
+            if MemSection::from_abs_addr(symbol.addr) != section {
+                break;
+            }
+            if nearest.is_some_and(|nearest| nearest.addr != symbol.addr) {
+                break;
+            }
+            if bank.is_none_or(|bank| bank == symbol.bank) {
+                nearest = Some(symbol);
+            }
+        }
+
+        return nearest;
+    }
+}
+
+/// `BB:AAAA Name`
+fn parse_symbol(line: &str) -> Option<Symbol> {
+    let (location, name) = line.split_once(char::is_whitespace)?;
+    let (bank, addr) = location.split_once(':')?;
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+
+    return Some(Symbol {
+        bank: usize::from_str_radix(bank, 16).ok()?,
+        addr: Addr::from_str_radix(addr, 16).ok()?,
+        name: name.to_owned(),
+    });
+}
+
+/// The bank mapped at `addr`, as numbered in symbol files. It's `None` for
+/// SRAM, where symbols from any bank are used.
+pub fn mapped_bank(sys: &Sys, addr: Addr) -> Option<usize> {
+    let io_regs = &sys.mem.io_regs;
+    return match MemSection::from_abs_addr(addr) {
+        MemSection::CartRom => code_bank(addr, sys.mem.cart.rom_bank()),
+        MemSection::Vram => Some(sys.mem.vram.get_bank(io_regs)),
+        MemSection::ExtRam => None,
+        MemSection::Wram => Some(sys.mem.wram.get_bank(io_regs, addr)),
+        _ => Some(0),
+    };
+}
+
+/// The bank of code at `addr` while `rom_bank` is mapped. It's `None` when
+/// the code is in RAM, where the bank isn't known.
+pub fn code_bank(addr: Addr, rom_bank: usize) -> Option<usize> {
+    match addr {
+        0x0000..=0x3FFF => Some(0),
+        0x4000..=0x7FFF => Some(rom_bank),
+        _ => None,
+    }
+}
+
+/// Names `addr` with the symbols loaded for the system's ROM, using the
+/// banks mapped right now.
+pub fn label_at(sys: &Sys, addr: Addr) -> Option<String> {
+    sys.debugger.symbols.label(addr, mapped_bank(sys, addr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYM_FILE: &str = "\
+; File generated by rgblink
+00:0150 Main
+00:0150 Start
+00:0158 Main.loop
+01:4000 LoadLevel
+02:4000 DrawLevel
+
+00:C0A0 wPlayerX ; in WRAM0
+02:D000 wLevelData
+";
+
+    #[test]
+    fn test_parse() {
+        let symbols = Symbols::parse(SYM_FILE).unwrap();
+        assert_eq!(symbols.count(), 7);
+        assert_eq!(
+            symbols.find("wLevelData"),
+            Some(&Symbol {
+                bank: 2,
+                addr: 0xD000,
+                name: "wLevelData".to_owned()
+            })
+        );
+        assert_eq!(symbols.find("wPlayerY"), None);
+
+        assert!(Symbols::parse("00:0150").is_err());
+        assert!(Symbols::parse("0150 Main").is_err());
+        assert!(Symbols::parse("00:XYZW Main").is_err());
+    }
+
+    #[test]
+    fn test_label() {
+        let symbols = Symbols::parse(SYM_FILE).unwrap();
+        assert_eq!(symbols.label(0x0150, Some(0)), Some("Main".to_owned()));
+        assert_eq!(symbols.label(0x0153, Some(0)), Some("Main+$3".to_owned()));
+        assert_eq!(
+            symbols.label(0x015A, Some(0)),
+            Some("Main.loop+$2".to_owned())
+        );
+        assert_eq!(symbols.label(0x0100, Some(0)), None);
+
+        assert_eq!(
+            symbols.label(0x4010, Some(2)),
+            Some("DrawLevel+$10".to_owned())
+        );
+        assert_eq!(symbols.label(0x4010, Some(3)), None);
+        assert_eq!(symbols.label(0x4000, None), Some("LoadLevel".to_owned()));
+
+        // Offsets don't reach into another memory section.
+        assert_eq!(symbols.label(0x8000, Some(0)), None);
+        assert_eq!(
+            symbols.label(0xC0A1, Some(0)),
+            Some("wPlayerX+$1".to_owned())
+        );
+        assert_eq!(symbols.name(0xD000, Some(2)), Some("wLevelData"));
+        assert_eq!(symbols.name(0xD001, Some(2)), None);
+    }
+}
@@ -0,0 +1,160 @@
+// An execution trace, with a line per instruction giving the CPU's state
+// before it runs. It's in the format Gameboy Doctor and other tools compare
+// logs in, so it can be diffed against reference emulators:
+//
+//   A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02
+//
+// PCMEM is the 4 bytes from PC on. While no trace is running, the only cost
+// is checking for one before each instruction.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cpu::regs::CpuReg8,
+    mem::{bus::Bus, Addr},
+    sys::Sys,
+};
+
+pub struct TraceLog {
+    path: PathBuf,
+    out: BufWriter<File>,
+    line_count: u64,
+}
+
+impl TraceLog {
+    /// Starts a trace in a new file, replacing any there.
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let file = File::create(path)
+            .map_err(|err| format!("Unable to create {}: {}", path.display(), err))?;
+        return Ok(Self {
+            path: path.to_owned(),
+            out: BufWriter::new(file),
+            line_count: 0,
+        });
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn line_count(&self) -> u64 {
+        self.line_count
+    }
+}
+
+/// Starts tracing to `path`, ending any trace already running.
+pub fn start_trace(sys: &mut Sys, path: &Path) -> Result<(), String> {
+    stop_trace(sys);
+    sys.debugger.trace = Some(TraceLog::create(path)?);
+    return Ok(());
+}
+
+/// Ends the trace, if there is one, writing out what's buffered.
+pub fn stop_trace(sys: &mut Sys) {
+    if let Some(mut trace) = sys.debugger.trace.take() {
+        if let Err(err) = trace.out.flush() {
+            println!("Unable to write {}: {}", trace.path.display(), err);
+        }
+    }
+}
+
+/// Call before the CPU executes the instruction at PC.
+#[inline]
+pub fn update_trace(sys: &mut Sys) {
+    // The line is written from the whole system, so the trace is taken out
+    // meanwhile.
+    let Some(mut trace) = sys.debugger.trace.take() else {
+        return;
+    };
+
+    match write_trace_line(sys, &mut trace.out) {
+        Ok(()) => {
+            trace.line_count += 1;
+            sys.debugger.trace = Some(trace);
+        }
+        Err(err) => println!(
+            "Unable to write {}, so tracing stopped: {}",
+            trace.path.display(),
+            err
+        ),
+    }
+}
+
+/// Writes out what's buffered, so the file is complete up to now.
+pub fn flush_trace(sys: &mut Sys) {
+    let Some(trace) = &mut sys.debugger.trace else {
+        return;
+    };
+
+    if let Err(err) = trace.out.flush() {
+        println!(
+            "Unable to write {}, so tracing stopped: {}",
+            trace.path.display(),
+            err
+        );
+        sys.debugger.trace = None;
+    }
+}
+
+fn write_trace_line<B: Bus>(sys: &Sys<B>, out: &mut impl Write) -> io::Result<()> {
+    use CpuReg8::*;
+
+    let regs = &sys.regs;
+    let pc = regs.pc();
+    let mem = |offset: Addr| sys.mem.peek(pc.wrapping_add(offset));
+
+    return writeln!(
+        out,
+        "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+        regs.get_8(A),
+        regs.get_8(F),
+        regs.get_8(B),
+        regs.get_8(C),
+        regs.get_8(D),
+        regs.get_8(E),
+        regs.get_8(H),
+        regs.get_8(L),
+        regs.sp(),
+        pc,
+        mem(0),
+        mem(1),
+        mem(2),
+        mem(3),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cpu::regs::CpuReg16,
+        other::mode::CompatibilityMode,
+        test::{bus::FlatBus, headless::headless_options},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_write_trace_line() {
+        let mut bus = FlatBus::new();
+        bus.ram[0xFFFF] = 0x01;
+        bus.ram[0x0000..0x0003].copy_from_slice(&[0x00, 0xC3, 0x13]);
+        let mut sys = Sys::with_bus(headless_options(None), CompatibilityMode::DmgOnly, bus);
+        sys.regs.set_16(CpuReg16::AF, 0x01B0);
+        sys.regs.set_16(CpuReg16::BC, 0x0013);
+        sys.regs.set_16(CpuReg16::DE, 0x00D8);
+        sys.regs.set_16(CpuReg16::HL, 0x014D);
+        sys.regs.set_16(CpuReg16::SP, 0xFFFE);
+        sys.regs.set_16(CpuReg16::PC, 0xFFFF);
+
+        let mut out = vec![];
+        write_trace_line(&sys, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:FFFF PCMEM:01,00,C3,13\n"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use crate::mem::Addr;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// Breaks when the CPU or DMA accesses `addr` in one of the watched ways.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Watchpoint {
+    pub addr: Addr,
+    pub on_read: bool,
+    pub on_write: bool,
+}
+
+impl Watchpoint {
+    fn is_hit(&self, addr: Addr, kind: AccessKind) -> bool {
+        let is_watched = match kind {
+            AccessKind::Read => self.on_read,
+            AccessKind::Write => self.on_write,
+        };
+        return is_watched && addr == self.addr;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WatchHit {
+    pub addr: Addr,
+    pub kind: AccessKind,
+    pub data: u8,
+}
+
+/// The watchpoints set on a bus, and the first one hit since the debugger
+/// last looked.
+pub struct Watchpoints {
+    list: Vec<Watchpoint>,
+    hit: Option<WatchHit>,
+}
+
+impl Watchpoints {
+    pub fn new() -> Self {
+        Self {
+            list: vec![],
+            hit: None,
+        }
+    }
+
+    pub fn list(&self) -> &[Watchpoint] {
+        &self.list
+    }
+
+    pub fn add(&mut self, watchpoint: Watchpoint) {
+        self.list.push(watchpoint);
+    }
+
+    pub fn remove(&mut self, idx: usize) -> Option<Watchpoint> {
+        (idx < self.list.len()).then(|| self.list.remove(idx))
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
+
+    /// Call on every access. Cheap while nothing is watched.
+    #[inline]
+    pub fn check(&mut self, addr: Addr, kind: AccessKind, data: u8) {
+        if self.list.is_empty() || self.hit.is_some() {
+            return;
+        }
+
+        if self.list.iter().any(|watch| watch.is_hit(addr, kind)) {
+            self.hit = Some(WatchHit { addr, kind, data });
+        }
+    }
+
+    pub fn take_hit(&mut self) -> Option<WatchHit> {
+        self.hit.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchpoints() {
+        let mut watchpoints = Watchpoints::new();
+        watchpoints.add(Watchpoint {
+            addr: 0xC000,
+            on_read: false,
+            on_write: true,
+        });
+
+        watchpoints.check(0xC000, AccessKind::Read, 0x12);
+        watchpoints.check(0xC001, AccessKind::Write, 0x34);
+        assert_eq!(watchpoints.take_hit(), None);
+
+        // Only the first hit is kept.
+        watchpoints.check(0xC000, AccessKind::Write, 0x56);
+        watchpoints.check(0xC000, AccessKind::Write, 0x78);
+        assert_eq!(
+            watchpoints.take_hit(),
+            Some(WatchHit {
+                addr: 0xC000,
+                kind: AccessKind::Write,
+                data: 0x56
+            })
+        );
+        assert_eq!(watchpoints.take_hit(), None);
+    }
+}
@@ -0,0 +1,7 @@
+/// Something that can exchange light with the CGB infrared port
+/// (another CGB, an IR cart, etc.).
+pub trait IrDevice {
+    /// Called every M-cycle with the state of the Game Boy's LED.
+    /// Returns true if light from the device is hitting the receiver.
+    fn update(&mut self, led_on: bool) -> bool;
+}
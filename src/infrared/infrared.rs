@@ -0,0 +1,142 @@
+use crate::{
+    mem::io_regs::IoReg,
+    sys::Sys,
+    util::{
+        bits::Bits,
+        state::{SaveState, StateReader, StateWriter},
+    },
+};
+
+use super::device::IrDevice;
+
+/// M-cycles the light hitting the receiver has to stay on (or off) before
+/// RP reports the change. The photodiode doesn't respond instantly.
+const RESPONSE_MCYCLES: u32 = 3;
+
+/// Represents the CGB infrared port state.
+pub struct Infrared {
+    device: Option<Box<dyn IrDevice>>,
+
+    /// Whether the receiver currently detects light.
+    is_receiving: bool,
+
+    /// M-cycles since the incoming light last changed.
+    mcycles_stable: u32,
+    last_incoming: bool,
+}
+
+impl Infrared {
+    pub fn new() -> Self {
+        Self {
+            device: None,
+            is_receiving: false,
+            mcycles_stable: 0,
+            last_incoming: false,
+        }
+    }
+
+    /// Returns the receiver to its power-on state. The device stays in place.
+    pub fn reset(&mut self) {
+        self.is_receiving = false;
+        self.mcycles_stable = 0;
+        self.last_incoming = false;
+    }
+
+    /// Points `device` at the infrared port, replacing any previous device.
+    pub fn connect(&mut self, device: Box<dyn IrDevice>) {
+        self.device = Some(device);
+    }
+}
+
+impl SaveState for Infrared {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.is_receiving);
+        w.write_u32(self.mcycles_stable);
+        w.write_bool(self.last_incoming);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.is_receiving = r.read_bool()?;
+        self.mcycles_stable = r.read_u32()?;
+        self.last_incoming = r.read_bool()?;
+        return Ok(());
+    }
+}
+
+/// Advances the infrared port state by one M-Cycle.
+pub fn update_infrared(sys: &mut Sys) {
+    if !sys.is_cgb_mode() {
+        return;
+    }
+
+    let rp = sys.mem.io_regs.get(IoReg::Rp);
+    let led_on = rp.bit(0) == 1;
+    let read_enable = rp.bits(7, 6) == 0b11;
+
+    let ir = &mut sys.infrared;
+    let incoming = match &mut ir.device {
+        Some(device) => device.update(led_on),
+        None => false,
+    };
+
+    if incoming == ir.last_incoming {
+        ir.mcycles_stable = u32::saturating_add(ir.mcycles_stable, 1);
+    } else {
+        ir.last_incoming = incoming;
+        ir.mcycles_stable = 0;
+    }
+    if ir.mcycles_stable >= RESPONSE_MCYCLES {
+        ir.is_receiving = incoming;
+    }
+
+    // Bit 1 reads 0 while light is received, but only if reading is enabled.
+    let is_receiving = read_enable && ir.is_receiving;
+    sys.mem.io_regs.mut_(IoReg::Rp, |rp| {
+        rp.set_bit(1, (!is_receiving).into());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        cart::cart::Cart, mem::mem::Mem, other::mode::CompatibilityMode,
+        test::headless::headless_options,
+    };
+
+    use super::*;
+
+    /// A light that's always on.
+    struct Lamp;
+
+    impl IrDevice for Lamp {
+        fn update(&mut self, _: bool) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_read_enable() {
+        let rom = Path::new("assets/files/custom_roms/ld_r8_r8/rom.gb");
+        let cart = Cart::load_from(rom, false).unwrap();
+        let mem = Mem::new(cart, true, None);
+        let mut sys = Sys::with_bus(headless_options(None), CompatibilityMode::CgbOnly, mem);
+        sys.infrared.connect(Box::new(Lamp));
+
+        // The light is seen, but RP doesn't report it while reading is off.
+        sys.mem.io_regs.set(IoReg::Rp, 0x00);
+        for _ in 0..=RESPONSE_MCYCLES {
+            update_infrared(&mut sys);
+        }
+        assert_eq!(sys.mem.io_regs.get(IoReg::Rp).bit(1), 1);
+
+        sys.mem.io_regs.set(IoReg::Rp, 0xC0);
+        update_infrared(&mut sys);
+        assert_eq!(sys.mem.io_regs.get(IoReg::Rp).bit(1), 0);
+
+        sys.mem.io_regs.set(IoReg::Rp, 0x40);
+        update_infrared(&mut sys);
+        assert_eq!(sys.mem.io_regs.get(IoReg::Rp).bit(1), 1);
+    }
+}
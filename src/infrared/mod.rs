@@ -0,0 +1,3 @@
+pub mod device;
+pub mod infrared;
+pub mod link;
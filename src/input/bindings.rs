@@ -0,0 +1,440 @@
+// Bindings files map each Game Boy button to keyboard keys and gamepad
+// buttons, one button per line:
+//
+//   # <button> = <input>, <input>, ...
+//   a = X, Pad:South
+//   b = Z, Pad:West
+//   turbo_a = S
+//   rewind = Tab
+//
+// Keys use macroquad's `KeyCode` names, gamepad buttons use gilrs' `Button`
+// names prefixed with `Pad:`. `turbo_<button>` lines bind the keys that
+// toggle turbo for that button, and `rewind` the keys held to rewind.
+// Anything not listed keeps its defaults.
+
+use std::{fs, path::Path};
+
+use macroquad::input::KeyCode;
+
+use super::button::Button;
+
+type PadButton = gilrs::Button;
+
+/// Prefix for gamepad buttons in bindings files.
+const PAD_PREFIX: &str = "Pad:";
+
+/// Prefix for turbo toggle bindings in bindings files.
+const TURBO_PREFIX: &str = "turbo_";
+
+/// Name of the rewind binding in bindings files.
+const REWIND_NAME: &str = "rewind";
+
+/// What a bindings entry binds inputs to.
+#[derive(Clone, Copy)]
+enum Target {
+    Button(Button),
+    Turbo(Button),
+    Rewind,
+}
+
+/// Maps host inputs to Game Boy buttons.
+#[derive(Clone)]
+pub struct Bindings {
+    keys: Vec<(KeyCode, Button)>,
+    pad_buttons: Vec<(PadButton, Button)>,
+    turbo_keys: Vec<(KeyCode, Button)>,
+    rewind_keys: Vec<KeyCode>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            keys: vec![
+                (KeyCode::Up, Button::Up),
+                (KeyCode::Right, Button::Right),
+                (KeyCode::Down, Button::Down),
+                (KeyCode::Left, Button::Left),
+                (KeyCode::Z, Button::B),
+                (KeyCode::X, Button::A),
+                (KeyCode::Enter, Button::Start),
+                (KeyCode::RightShift, Button::Select),
+            ],
+            pad_buttons: vec![
+                (PadButton::DPadUp, Button::Up),
+                (PadButton::DPadRight, Button::Right),
+                (PadButton::DPadDown, Button::Down),
+                (PadButton::DPadLeft, Button::Left),
+                (PadButton::West, Button::B),
+                (PadButton::South, Button::A),
+                (PadButton::Start, Button::Start),
+                (PadButton::Select, Button::Select),
+            ],
+            turbo_keys: vec![(KeyCode::S, Button::A), (KeyCode::A, Button::B)],
+            rewind_keys: vec![KeyCode::R],
+        }
+    }
+}
+
+impl Bindings {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("Unable to read bindings {:?}: {}", path, err))?;
+
+        return Self::parse(&text);
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut bindings = Self::default();
+        let mut replaced = vec![];
+
+        for (line_idx, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let err = |msg: String| format!("Bindings line {}: {}", line_idx + 1, msg);
+
+            let Some((name, inputs)) = line.split_once('=') else {
+                return Err(err(format!("expected `<button> = <inputs>`: {}", line)));
+            };
+            let inputs = inputs.split(',').map(str::trim).collect::<Vec<_>>();
+
+            bindings
+                .bind(&mut replaced, name.trim(), &inputs)
+                .map_err(err)?;
+        }
+
+        return Ok(bindings);
+    }
+
+    /// Creates bindings from (button, inputs) entries, such as those
+    /// in the config file.
+    pub fn from_entries<'a>(
+        entries: impl IntoIterator<Item = (&'a str, Vec<&'a str>)>,
+    ) -> Result<Self, String> {
+        let mut bindings = Self::default();
+        let mut replaced = vec![];
+
+        for (name, inputs) in entries {
+            bindings
+                .bind(&mut replaced, name, &inputs)
+                .map_err(|msg| format!("Bindings for `{}`: {}", name, msg))?;
+        }
+
+        return Ok(bindings);
+    }
+
+    /// Binds `inputs` to the button, turbo toggle or rewind called `name`.
+    /// The first time a name is bound, its defaults are replaced.
+    fn bind<'a>(
+        &mut self,
+        replaced: &mut Vec<&'a str>,
+        name: &'a str,
+        inputs: &[&str],
+    ) -> Result<(), String> {
+        let target = if name == REWIND_NAME {
+            Target::Rewind
+        } else {
+            let (is_turbo, button_name) = match name.strip_prefix(TURBO_PREFIX) {
+                Some(button_name) => (true, button_name),
+                None => (false, name),
+            };
+            let Some(button) = Button::from_name(button_name) else {
+                return Err(format!("unknown button `{}`", button_name));
+            };
+            if is_turbo {
+                Target::Turbo(button)
+            } else {
+                Target::Button(button)
+            }
+        };
+
+        if !replaced.contains(&name) {
+            replaced.push(name);
+            match target {
+                Target::Button(button) => {
+                    self.keys.retain(|(_, b)| *b != button);
+                    self.pad_buttons.retain(|(_, b)| *b != button);
+                }
+                Target::Turbo(button) => self.turbo_keys.retain(|(_, b)| *b != button),
+                Target::Rewind => self.rewind_keys.clear(),
+            }
+        }
+
+        for input in inputs.iter().copied() {
+            if input.is_empty() {
+                continue;
+            }
+
+            if let Some(pad_name) = input.strip_prefix(PAD_PREFIX) {
+                let Target::Button(button) = target else {
+                    return Err(format!("`{}` can only be bound to keys", name));
+                };
+                let Some(pad_button) = parse_pad_button(pad_name) else {
+                    return Err(format!("unknown gamepad button `{}`", pad_name));
+                };
+                self.pad_buttons.push((pad_button, button));
+            } else {
+                let Some(key_code) = parse_key_code(input) else {
+                    return Err(format!("unknown key `{}`", input));
+                };
+                match target {
+                    Target::Button(button) => self.keys.push((key_code, button)),
+                    Target::Turbo(button) => self.turbo_keys.push((key_code, button)),
+                    Target::Rewind => self.rewind_keys.push(key_code),
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    pub fn keys(&self) -> &[(KeyCode, Button)] {
+        &self.keys
+    }
+
+    pub fn pad_buttons(&self) -> &[(PadButton, Button)] {
+        &self.pad_buttons
+    }
+
+    pub fn turbo_keys(&self) -> &[(KeyCode, Button)] {
+        &self.turbo_keys
+    }
+
+    pub fn rewind_keys(&self) -> &[KeyCode] {
+        &self.rewind_keys
+    }
+}
+
+/// Every `KeyCode`, by the name used in bindings files.
+const KEY_NAMES: [(&str, KeyCode); 121] = {
+    use KeyCode::*;
+    [
+        ("Space", Space),
+        ("Apostrophe", Apostrophe),
+        ("Comma", Comma),
+        ("Minus", Minus),
+        ("Period", Period),
+        ("Slash", Slash),
+        ("Key0", Key0),
+        ("Key1", Key1),
+        ("Key2", Key2),
+        ("Key3", Key3),
+        ("Key4", Key4),
+        ("Key5", Key5),
+        ("Key6", Key6),
+        ("Key7", Key7),
+        ("Key8", Key8),
+        ("Key9", Key9),
+        ("Semicolon", Semicolon),
+        ("Equal", Equal),
+        ("A", A),
+        ("B", B),
+        ("C", C),
+        ("D", D),
+        ("E", E),
+        ("F", F),
+        ("G", G),
+        ("H", H),
+        ("I", I),
+        ("J", J),
+        ("K", K),
+        ("L", L),
+        ("M", M),
+        ("N", N),
+        ("O", O),
+        ("P", P),
+        ("Q", Q),
+        ("R", R),
+        ("S", S),
+        ("T", T),
+        ("U", U),
+        ("V", V),
+        ("W", W),
+        ("X", X),
+        ("Y", Y),
+        ("Z", Z),
+        ("LeftBracket", LeftBracket),
+        ("Backslash", Backslash),
+        ("RightBracket", RightBracket),
+        ("GraveAccent", GraveAccent),
+        ("World1", World1),
+        ("World2", World2),
+        ("Escape", Escape),
+        ("Enter", Enter),
+        ("Tab", Tab),
+        ("Backspace", Backspace),
+        ("Insert", Insert),
+        ("Delete", Delete),
+        ("Right", Right),
+        ("Left", Left),
+        ("Down", Down),
+        ("Up", Up),
+        ("PageUp", PageUp),
+        ("PageDown", PageDown),
+        ("Home", Home),
+        ("End", End),
+        ("CapsLock", CapsLock),
+        ("ScrollLock", ScrollLock),
+        ("NumLock", NumLock),
+        ("PrintScreen", PrintScreen),
+        ("Pause", Pause),
+        ("F1", F1),
+        ("F2", F2),
+        ("F3", F3),
+        ("F4", F4),
+        ("F5", F5),
+        ("F6", F6),
+        ("F7", F7),
+        ("F8", F8),
+        ("F9", F9),
+        ("F10", F10),
+        ("F11", F11),
+        ("F12", F12),
+        ("F13", F13),
+        ("F14", F14),
+        ("F15", F15),
+        ("F16", F16),
+        ("F17", F17),
+        ("F18", F18),
+        ("F19", F19),
+        ("F20", F20),
+        ("F21", F21),
+        ("F22", F22),
+        ("F23", F23),
+        ("F24", F24),
+        ("F25", F25),
+        ("Kp0", Kp0),
+        ("Kp1", Kp1),
+        ("Kp2", Kp2),
+        ("Kp3", Kp3),
+        ("Kp4", Kp4),
+        ("Kp5", Kp5),
+        ("Kp6", Kp6),
+        ("Kp7", Kp7),
+        ("Kp8", Kp8),
+        ("Kp9", Kp9),
+        ("KpDecimal", KpDecimal),
+        ("KpDivide", KpDivide),
+        ("KpMultiply", KpMultiply),
+        ("KpSubtract", KpSubtract),
+        ("KpAdd", KpAdd),
+        ("KpEnter", KpEnter),
+        ("KpEqual", KpEqual),
+        ("LeftShift", LeftShift),
+        ("LeftControl", LeftControl),
+        ("LeftAlt", LeftAlt),
+        ("LeftSuper", LeftSuper),
+        ("RightShift", RightShift),
+        ("RightControl", RightControl),
+        ("RightAlt", RightAlt),
+        ("RightSuper", RightSuper),
+        ("Menu", Menu),
+        ("Back", Back),
+    ]
+};
+
+/// Every gamepad button, by the name used in bindings files.
+const PAD_BUTTON_NAMES: [(&str, PadButton); 19] = {
+    use gilrs::Button::*;
+    [
+        ("South", South),
+        ("East", East),
+        ("North", North),
+        ("West", West),
+        ("C", C),
+        ("Z", Z),
+        ("LeftTrigger", LeftTrigger),
+        ("LeftTrigger2", LeftTrigger2),
+        ("RightTrigger", RightTrigger),
+        ("RightTrigger2", RightTrigger2),
+        ("Select", Select),
+        ("Start", Start),
+        ("Mode", Mode),
+        ("LeftThumb", LeftThumb),
+        ("RightThumb", RightThumb),
+        ("DPadUp", DPadUp),
+        ("DPadDown", DPadDown),
+        ("DPadLeft", DPadLeft),
+        ("DPadRight", DPadRight),
+    ]
+};
+
+fn parse_key_code(name: &str) -> Option<KeyCode> {
+    let (_, key_code) = KEY_NAMES
+        .into_iter()
+        .find(|(key_name, _)| *key_name == name)?;
+    return Some(key_code);
+}
+
+fn parse_pad_button(name: &str) -> Option<PadButton> {
+    let (_, pad_button) = PAD_BUTTON_NAMES
+        .into_iter()
+        .find(|(button_name, _)| *button_name == name)?;
+    return Some(pad_button);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = "
+            # Swap A and B.
+            a = Z, Pad:West
+            b = X
+            turbo_a = Q
+            rewind = Tab, Kp0
+        ";
+        let bindings = Bindings::parse(text).unwrap();
+
+        assert!(bindings.keys().contains(&(KeyCode::Z, Button::A)));
+        assert!(bindings.keys().contains(&(KeyCode::X, Button::B)));
+        assert!(!bindings.keys().contains(&(KeyCode::X, Button::A)));
+        assert!(bindings.keys().contains(&(KeyCode::Up, Button::Up)));
+
+        assert!(bindings
+            .pad_buttons()
+            .contains(&(PadButton::West, Button::A)));
+        assert!(!bindings
+            .pad_buttons()
+            .contains(&(PadButton::South, Button::A)));
+
+        assert_eq!(
+            bindings.turbo_keys(),
+            &[(KeyCode::A, Button::B), (KeyCode::Q, Button::A)]
+        );
+        assert_eq!(bindings.rewind_keys(), &[KeyCode::Tab, KeyCode::Kp0]);
+        assert_eq!(Bindings::default().rewind_keys(), &[KeyCode::R]);
+    }
+
+    #[test]
+    fn test_input_names() {
+        for (name, key_code) in KEY_NAMES {
+            assert_eq!(format!("{:?}", key_code), name);
+            assert_eq!(parse_key_code(name), Some(key_code));
+        }
+        for (name, pad_button) in PAD_BUTTON_NAMES {
+            assert_eq!(format!("{:?}", pad_button), name);
+            assert_eq!(parse_pad_button(name), Some(pad_button));
+        }
+
+        let bindings = Bindings::parse("select = LeftSuper, F13").unwrap();
+        assert!(bindings
+            .keys()
+            .contains(&(KeyCode::LeftSuper, Button::Select)));
+        assert!(bindings.keys().contains(&(KeyCode::F13, Button::Select)));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Bindings::parse("jump = Space").is_err());
+        assert!(Bindings::parse("a = NotAKey").is_err());
+        assert!(Bindings::parse("a = Pad:NotAButton").is_err());
+        assert!(Bindings::parse("turbo_a = Pad:South").is_err());
+        assert!(Bindings::parse("rewind = Pad:North").is_err());
+        assert!(Bindings::parse("a Space").is_err());
+    }
+}
@@ -0,0 +1,83 @@
+/// Represents a Game Boy button.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Button {
+    Up,
+    Right,
+    Down,
+    Left,
+
+    B,
+    A,
+
+    Start,
+    Select,
+}
+
+impl Button {
+    pub const ALL: [Button; 8] = [
+        Button::Up,
+        Button::Right,
+        Button::Down,
+        Button::Left,
+        Button::B,
+        Button::A,
+        Button::Start,
+        Button::Select,
+    ];
+
+    /// Name used for the button in bindings files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Button::Up => "up",
+            Button::Right => "right",
+            Button::Down => "down",
+            Button::Left => "left",
+
+            Button::B => "b",
+            Button::A => "a",
+
+            Button::Start => "start",
+            Button::Select => "select",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|button| button.name() == name)
+    }
+
+    fn mask(self) -> u8 {
+        1 << (self as u8)
+    }
+}
+
+/// The set of buttons held down during a frame.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct JoypadState {
+    bits: u8,
+}
+
+impl JoypadState {
+    pub fn from_bits(bits: u8) -> Self {
+        Self { bits }
+    }
+
+    pub fn bits(self) -> u8 {
+        self.bits
+    }
+
+    pub fn is_pressed(self, button: Button) -> bool {
+        (self.bits & button.mask()) != 0
+    }
+
+    pub fn set(&mut self, button: Button, is_pressed: bool) {
+        if is_pressed {
+            self.bits |= button.mask();
+        } else {
+            self.bits &= !button.mask();
+        }
+    }
+
+    pub fn toggle(&mut self, button: Button) {
+        self.bits ^= button.mask();
+    }
+}
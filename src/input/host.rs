@@ -0,0 +1,86 @@
+use std::{cell::Cell, rc::Rc};
+
+use gilrs::{Axis, Gilrs};
+use macroquad::input::is_key_down;
+
+use super::{
+    bindings::Bindings,
+    button::{Button, JoypadState},
+    source::InputSource,
+};
+
+/// How far an analog stick must be pushed to press a direction.
+const STICK_THRESHOLD: f32 = 0.5;
+
+/// Reads the joypad from the host keyboard and any connected gamepads.
+pub struct HostInput {
+    bindings: Bindings,
+    gilrs: Option<Gilrs>,
+
+    /// Set while the user is typing into the emulator's own UI, during
+    /// which no buttons are pressed.
+    is_blocked: Rc<Cell<bool>>,
+}
+
+impl HostInput {
+    pub fn new(bindings: Bindings, is_blocked: Rc<Cell<bool>>) -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(err) => {
+                println!("Gamepads unavailable: {}", err);
+                None
+            }
+        };
+
+        Self {
+            bindings,
+            gilrs,
+            is_blocked,
+        }
+    }
+}
+
+impl InputSource for HostInput {
+    fn poll(&mut self) -> JoypadState {
+        let mut state = JoypadState::default();
+        if self.is_blocked.get() {
+            return state;
+        }
+
+        for (key_code, button) in self.bindings.keys() {
+            if is_key_down(*key_code) {
+                state.set(*button, true);
+            }
+        }
+
+        let Some(gilrs) = &mut self.gilrs else {
+            return state;
+        };
+
+        // Gamepad states are only updated as their events are processed.
+        while gilrs.next_event().is_some() {}
+
+        for (_, gamepad) in gilrs.gamepads() {
+            for (pad_button, button) in self.bindings.pad_buttons() {
+                if gamepad.is_pressed(*pad_button) {
+                    state.set(*button, true);
+                }
+            }
+
+            let x = gamepad.value(Axis::LeftStickX);
+            let y = gamepad.value(Axis::LeftStickY);
+            if x >= STICK_THRESHOLD {
+                state.set(Button::Right, true);
+            } else if x <= -STICK_THRESHOLD {
+                state.set(Button::Left, true);
+            }
+            if y >= STICK_THRESHOLD {
+                state.set(Button::Up, true);
+            } else if y <= -STICK_THRESHOLD {
+                state.set(Button::Down, true);
+            }
+        }
+
+        return state;
+    }
+}
@@ -0,0 +1,5 @@
+pub mod bindings;
+pub mod button;
+pub mod host;
+pub mod movie;
+pub mod source;
@@ -0,0 +1,201 @@
+// Movie files record the joypad input polled each frame, so that a run can
+// be replayed exactly. A movie starts either from power-on or from an
+// embedded snapshot, and also stores a hash of every emulated frame, so that
+// a replay can be checked against the recording.
+
+use std::{cell::RefCell, fs, path::Path, rc::Rc};
+
+use crate::{
+    cart::header::CartHeader,
+    util::state::{StateReader, StateWriter},
+};
+
+use super::{button::JoypadState, source::InputSource};
+
+/// Identifies movie files.
+const MOVIE_MAGIC: &[u8] = b"GBMV";
+
+/// A recording of joypad input.
+#[derive(Default)]
+pub struct Movie {
+    /// Snapshot the movie starts from, or `None` to start from power-on.
+    pub start_state: Option<Vec<u8>>,
+
+    /// Buttons held each time the joypad was polled.
+    pub inputs: Vec<JoypadState>,
+
+    /// Hash of the frame and WRAM after each emulated frame.
+    pub frame_hashes: Vec<u64>,
+}
+
+impl Movie {
+    pub fn new(start_state: Option<Vec<u8>>) -> Self {
+        Self {
+            start_state,
+            ..Self::default()
+        }
+    }
+
+    /// Loads a movie, which must have been recorded with the game with `header`.
+    pub fn load(path: &Path, header: &CartHeader) -> Result<Self, String> {
+        let data =
+            fs::read(path).map_err(|err| format!("Unable to read movie {:?}: {}", path, err))?;
+
+        return Self::parse(&data, header)
+            .map_err(|msg| format!("Unable to load movie {:?}: {}", path, msg));
+    }
+
+    pub fn save(&self, path: &Path, header: &CartHeader) -> Result<(), String> {
+        fs::write(path, self.to_bytes(header))
+            .map_err(|err| format!("Unable to save movie {:?}: {}", path, err))
+    }
+
+    fn parse(data: &[u8], header: &CartHeader) -> Result<Self, String> {
+        let mut r = StateReader::new(data);
+        if r.read_bytes()? != MOVIE_MAGIC {
+            return Err("not a movie file.".into());
+        }
+        header.check_id(&mut r)?;
+
+        let start_state = match r.read_bool()? {
+            true => Some(r.read_bytes()?.to_vec()),
+            false => None,
+        };
+
+        let inputs = r
+            .read_bytes()?
+            .iter()
+            .map(|bits| JoypadState::from_bits(*bits))
+            .collect();
+
+        let hash_count = r.read_u32()?;
+        let frame_hashes = (0..hash_count)
+            .map(|_| r.read_u64())
+            .collect::<Result<_, _>>()?;
+
+        return Ok(Self {
+            start_state,
+            inputs,
+            frame_hashes,
+        });
+    }
+
+    fn to_bytes(&self, header: &CartHeader) -> Vec<u8> {
+        let mut w = StateWriter::new();
+        w.write_bytes(MOVIE_MAGIC);
+        header.write_id(&mut w);
+
+        w.write_bool(self.start_state.is_some());
+        if let Some(start_state) = &self.start_state {
+            w.write_bytes(start_state);
+        }
+
+        let inputs = self.inputs.iter().map(|state| state.bits());
+        w.write_bytes(&inputs.collect::<Vec<_>>());
+
+        w.write_u32(self.frame_hashes.len() as u32);
+        for hash in &self.frame_hashes {
+            w.write_u64(*hash);
+        }
+
+        return w.into_bytes();
+    }
+}
+
+/// Passes through the input from another source, recording it into a movie.
+pub struct MovieRecorder {
+    source: Box<dyn InputSource>,
+    movie: Rc<RefCell<Movie>>,
+}
+
+impl MovieRecorder {
+    pub fn new(source: Box<dyn InputSource>, movie: Rc<RefCell<Movie>>) -> Self {
+        Self { source, movie }
+    }
+}
+
+impl InputSource for MovieRecorder {
+    fn poll(&mut self) -> JoypadState {
+        let state = self.source.poll();
+        self.movie.borrow_mut().inputs.push(state);
+        return state;
+    }
+}
+
+/// Plays back the input recorded in a movie. Nothing is pressed once the
+/// movie runs out.
+pub struct MoviePlayer {
+    movie: Rc<RefCell<Movie>>,
+    next_input: usize,
+}
+
+impl MoviePlayer {
+    pub fn new(movie: Rc<RefCell<Movie>>) -> Self {
+        Self {
+            movie,
+            next_input: 0,
+        }
+    }
+}
+
+impl InputSource for MoviePlayer {
+    fn poll(&mut self) -> JoypadState {
+        let state = self.movie.borrow().inputs.get(self.next_input).copied();
+        self.next_input += 1;
+        return state.unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Presses a different set of buttons on each poll.
+    struct CountingInput(u8);
+
+    impl InputSource for CountingInput {
+        fn poll(&mut self) -> JoypadState {
+            self.0 += 1;
+            JoypadState::from_bits(self.0)
+        }
+    }
+
+    #[test]
+    fn test_record_play() {
+        let movie = Rc::new(RefCell::new(Movie::new(None)));
+        let mut recorder = MovieRecorder::new(Box::new(CountingInput(0)), movie.clone());
+        let recorded = (0..10).map(|_| recorder.poll()).collect::<Vec<_>>();
+        assert_eq!(movie.borrow().inputs, recorded);
+
+        let mut player = MoviePlayer::new(movie);
+        let played = (0..10).map(|_| player.poll()).collect::<Vec<_>>();
+        assert_eq!(played, recorded);
+        assert_eq!(player.poll(), JoypadState::default());
+    }
+
+    fn header(title: &[u8], global_checksum: u16) -> CartHeader {
+        let mut rom = vec![0; 0x8000];
+        rom[0x134..0x134 + title.len()].copy_from_slice(title);
+        rom[0x14E..0x150].copy_from_slice(&global_checksum.to_be_bytes());
+        return CartHeader::parse(&rom).unwrap();
+    }
+
+    #[test]
+    fn test_file() {
+        let movie = Movie {
+            start_state: Some(vec![1, 2, 3]),
+            inputs: vec![JoypadState::from_bits(0x81), JoypadState::default()],
+            frame_hashes: vec![0x1234_5678_9ABC_DEF0],
+        };
+        let data = movie.to_bytes(&header(b"TETRIS", 0x1234));
+
+        let parsed = Movie::parse(&data, &header(b"TETRIS", 0x1234)).unwrap();
+        assert_eq!(parsed.start_state, movie.start_state);
+        assert_eq!(parsed.inputs, movie.inputs);
+        assert_eq!(parsed.frame_hashes, movie.frame_hashes);
+
+        assert!(Movie::parse(&data, &header(b"TETRIS", 0x4321)).is_err());
+        assert!(Movie::parse(&data, &header(b"ZELDA", 0x1234)).is_err());
+        assert!(Movie::parse(&data[..data.len() - 1], &header(b"TETRIS", 0x1234)).is_err());
+    }
+}
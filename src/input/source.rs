@@ -0,0 +1,16 @@
+use super::button::JoypadState;
+
+/// Provides the buttons held on the emulated joypad. Polled by the core
+/// once per frame.
+pub trait InputSource {
+    fn poll(&mut self) -> JoypadState;
+}
+
+/// No buttons are ever pressed.
+pub struct NoInput;
+
+impl InputSource for NoInput {
+    fn poll(&mut self) -> JoypadState {
+        JoypadState::default()
+    }
+}
@@ -9,16 +9,55 @@
 //                                                             //
 // /////////////////////////////////////////////////////////// //
 
+use std::{
+    cell::Cell,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
 use cart::cart::Cart;
 use consts::PIXEL_SCALE;
 use debug::{debug_state, initialize_debug, DebugConfig};
+use debugger::{
+    cdl::{save_cdl, start_cdl},
+    console::{print_break, Console},
+    gdb::GdbStub,
+    listing::run_disasm,
+    mem_viewer::{Input as MemViewerInput, ROW_LEN, VIEW_ROWS, WHEEL_ROWS},
+    profiler::{save_profile, start_profile},
+    symbols::Symbols,
+    trace::{flush_trace, start_trace, stop_trace},
+};
+use infrared::link::connect_ir_link;
+use input::{bindings::Bindings, host::HostInput};
 use macroquad::{
     color::BLACK,
-    input::{is_key_pressed, KeyCode},
+    input::{
+        get_char_pressed, get_dropped_files, is_key_down, is_key_pressed, mouse_wheel, KeyCode,
+    },
     window::next_frame,
 };
-use other::save::{load_state, save_state};
-use ppu::{consts::window_size, ui::render_ui};
+use mem::io_regs::IoReg;
+use other::{
+    cli::{Args, Command},
+    config::Config,
+    emu::Advance,
+    movie::{MovieMode, MovieSession},
+    pacing::FramePacer,
+    rewind::Rewind,
+    save::{load_state_file, BatterySave, StateSave},
+};
+use ppu::{
+    consts::window_size,
+    ppu::DOTS_PER_SCANLINE,
+    ui::{render_linked_viewport, render_speed, render_ui},
+};
+use serial::{
+    cable::{connect_link_cable, run_linked_m_cycle},
+    printer::Printer,
+    socket::SocketLink,
+};
 use sys::{options::Options, Sys};
 use xf::mq::{
     draw::draw_rect,
@@ -33,93 +72,332 @@ mod cart;
 mod consts;
 mod cpu;
 mod debug;
+mod debugger;
+mod infrared;
+mod input;
 mod mem;
 mod other;
 mod ppu;
+mod serial;
 mod sys;
 mod test;
 mod time;
 mod util;
 
-#[macroquad::main("rust_cgb_emu")]
-async fn main() {
+fn main() {
     //std::env::set_var("RUST_BACKTRACE", "1");
-    println!("*** RUST GAMEBOY COLOR EMU (Matthew Ducasse 2025) ***");
+    let command = Command::parse(std::env::args().skip(1)).unwrap_or_else(|msg| {
+        println!("{}", msg);
+        std::process::exit(1);
+    });
 
-    run_emu().await;
+    match command {
+        Command::Run(args) => {
+            println!("*** RUST GAMEBOY COLOR EMU (Matthew Ducasse 2025) ***");
+            macroquad::Window::new("rust_cgb_emu", run_emu(*args));
+        }
+        Command::Disasm(args) => {
+            if let Err(msg) = run_disasm(&args) {
+                println!("{}", msg);
+                std::process::exit(1);
+            }
+        }
+    }
 }
 
-async fn run_emu() {
+async fn run_emu(args: Args) {
     initialize_debug(DebugConfig {
         enable_debug_print: false,
-        kill_after_cpu_ticks: None,
-        kill_after_nop_count: None,
+        kill_after_cpu_ticks: args.kill_after_cpu_ticks,
+        kill_after_nop_count: args.kill_after_nop_count,
         last_instr_count: 15,
     });
 
-    //let path = ".\\assets\\real_gb_roms\\tetris.gb";
-    //let path = ".\\assets\\real_gb_roms\\Dr_Mario.gb";
-    //let path = ".\\assets\\real_gb_roms\\Pokemon.gb";
-    //let path = ".\\assets\\real_gb_roms\\Zelda.gb";
-    //let path = ".\\assets\\real_gb_roms\\Kirby.gb";
-    //let path = ".\\assets\\real_gb_roms\\Tennis.gb";
-    //let path = ".\\assets\\real_gb_roms\\Super Mario Land 2.gb";
-    //let path = ".\\assets\\real_gb_roms\\Wario Land.gb";
-    //let path = ".\\assets\\real_gb_roms\\DuckTales.gb";
-
-    //let path = ".\\assets\\real_cgb_roms\\Oracle of Seasons.gbc";
-    //let path = ".\\assets\\real_cgb_roms\\Oracle of Ages.gbc";
-    //let path = ".\\assets\\real_cgb_roms\\Crystal Version.gbc";
-    let path = ".\\assets\\real_cgb_roms\\Silver Version.gbc";
-    //let path = ".\\assets\\real_cgb_roms\\Link's Awakening DX.gbc";
-
-    //let path = ".\\assets\\homebrew_roms\\porklike.gb";
-    //let path = ".\\assets\\homebrew_roms\\20y.gb";
-    //let path = ".\\assets\\homebrew_roms\\64boy-opcode-scroll.gb";
-    //let path = ".\\assets\\homebrew_roms\\life.gb";
-
-    //let path = ".\\assets\\other\\hello_world\\rom.gb";
-
-    let cart = Cart::load_from(path, true).unwrap_or_else(|msg| {
+    let config_path = args.config_path.clone().or_else(Config::default_path);
+    let config = match &config_path {
+        Some(path) => Config::load(path).unwrap_or_else(|msg| {
+            panic!("{}", msg);
+        }),
+        None => Config::default(),
+    };
+
+    let save_dir = find_save_dir(&args, &config, &args.rom_path);
+
+    let bindings = match &args.bindings_path {
+        Some(path) => Bindings::load(path).unwrap_or_else(|msg| {
+            panic!("{}", msg);
+        }),
+        None => config.bindings().unwrap_or_else(|msg| {
+            panic!("{}", msg);
+        }),
+    };
+
+    // While the memory view takes typed input, keys don't reach the game.
+    let is_typing = Rc::new(Cell::new(false));
+
+    // The linked game is shown where the VRAM views would be.
+    let is_linked = args.link_path.is_some();
+    let show_vram_views =
+        args.show_vram_views && config.show_vram_views.unwrap_or(true) && !is_linked;
+
+    let mut sys =
+        create_sys(&args, &config, &args.rom_path, show_vram_views).unwrap_or_else(|msg| {
+            panic!("{}", msg);
+        });
+    start_recordings(&args, &mut sys).unwrap_or_else(|msg| {
         panic!("{}", msg);
     });
 
-    let show_vram_views = true;
-    let options = Options {
-        kill_on_infinite_loop: true,
-        show_vram_views,
-    };
+    let mut link_peer = args.link_path.as_ref().map(|path| {
+        let mut peer = create_sys(&args, &config, path, false).unwrap_or_else(|msg| {
+            panic!("{}", msg);
+        });
+        connect_link_cable(&mut sys, &mut peer);
+        connect_ir_link(&mut sys, &mut peer);
 
-    let mut sys = Sys::new(options, cart);
+        // Both games read the same keyboard and gamepads.
+        peer.joypad.set_source(Box::new(HostInput::new(
+            bindings.clone(),
+            is_typing.clone(),
+        )));
+        peer
+    });
+
+    if let Some(addr) = &args.link_listen_addr {
+        let link = SocketLink::listen(addr).unwrap_or_else(|err| {
+            panic!("Unable to listen for link on {}: {}", addr, err);
+        });
+        sys.serial.connect(Box::new(link));
+    } else if let Some(addr) = &args.link_connect_addr {
+        let link = SocketLink::connect(addr).unwrap_or_else(|err| {
+            panic!("Unable to connect link to {}: {}", addr, err);
+        });
+        sys.serial.connect(Box::new(link));
+    } else if let Some(dir) = &args.printer_dir {
+        fs::create_dir_all(dir).unwrap_or_else(|err| {
+            panic!("Unable to create {}: {}", dir.display(), err);
+        });
+        let printer = Printer::new(Some(dir.clone()));
+        sys.serial.connect(Box::new(printer));
+    }
 
     let window = Window::new(WindowParams {
-        resolution: window_size(show_vram_views),
-        scale: PIXEL_SCALE,
+        resolution: window_size(show_vram_views || is_linked),
+        scale: args.scale.or(config.scale).unwrap_or(PIXEL_SCALE),
     });
 
-    load_state(&mut sys);
+    // Movies don't use battery saves, so that they replay the same way
+    // regardless of what's been saved since.
+    let mut battery_save = match &args.movie {
+        Some(_) => None,
+        None => Some(BatterySave::new(&args.rom_path, &save_dir)),
+    };
+    if let Some(battery_save) = &battery_save {
+        battery_save.load(&mut sys);
+    }
+
+    let mut state_save = StateSave::new(&args.rom_path, &save_dir);
+    if let Some(path) = &args.load_state_path {
+        load_state_file(&mut sys, path).unwrap_or_else(|msg| {
+            panic!("{}", msg);
+        });
+    }
+
+    let host_input = Box::new(HostInput::new(bindings.clone(), is_typing.clone()));
+    let mut movie = match args.movie.clone() {
+        Some((MovieMode::Record, path)) => Some(MovieSession::record(
+            &mut sys,
+            path,
+            host_input,
+            args.load_state_path.is_some(),
+        )),
+        Some((mode, path)) => Some(MovieSession::play(&mut sys, path, mode).unwrap_or_else(
+            |msg| {
+                panic!("{}", msg);
+            },
+        )),
+        None => {
+            sys.joypad.set_source(host_input);
+            None
+        }
+    };
+
+    // Rewinding one side of a link would desync it from the other side,
+    // and rewinding a movie would desync it from its input.
+    let can_rewind = config.rewind.enabled && !sys.serial.is_connected() && movie.is_none();
+    let new_rewind = || {
+        Rewind::new(
+            config.rewind.budget_mb * 1024 * 1024,
+            config.rewind.snapshot_period_frames,
+        )
+    };
+    let mut rewind = can_rewind.then(new_rewind);
 
+    let mut pacer = FramePacer::new();
+    let console = args.debugger.then(Console::new);
+    let mut gdb = args.gdb_addr.as_ref().map(|addr| {
+        GdbStub::listen(addr).unwrap_or_else(|msg| {
+            panic!("{}", msg);
+        })
+    });
+
+    // Escape quits. `hard_lock` is only set when emulation can't go on.
+    let mut is_quitting = false;
     while !sys.hard_lock {
-        check_misc_inputs(&mut sys);
+        // A ROM dropped onto the window replaces the running one, and the
+        // linked game is connected to it instead. Movies, traces, profiles
+        // and code/data logs are tied to the game they started with.
+        let dropped_rom_path = get_dropped_files().into_iter().find_map(|file| file.path);
+        if let Some(rom_path) = dropped_rom_path.filter(|_| movie.is_none()) {
+            match create_sys(&args, &config, &rom_path, show_vram_views) {
+                Ok(mut new_sys) => {
+                    if let Some(battery_save) = &battery_save {
+                        if sys.mem.cart.is_ram_dirty() {
+                            battery_save.save(&mut sys);
+                        }
+                    }
+                    finish_recordings(&args, &mut sys);
+                    if let Some(device) = sys.serial.disconnect() {
+                        new_sys.serial.connect(device);
+                    }
+                    if let Some(peer) = &mut link_peer {
+                        connect_link_cable(&mut new_sys, peer);
+                        connect_ir_link(&mut new_sys, peer);
+                    }
+                    new_sys.joypad.set_source(Box::new(HostInput::new(
+                        bindings.clone(),
+                        is_typing.clone(),
+                    )));
+                    sys = new_sys;
+
+                    let save_dir = find_save_dir(&args, &config, &rom_path);
+                    let new_battery_save = BatterySave::new(&rom_path, &save_dir);
+                    new_battery_save.load(&mut sys);
+                    battery_save = Some(new_battery_save);
+                    state_save = StateSave::new(&rom_path, &save_dir);
+                    rewind = rewind.map(|_| new_rewind());
+
+                    println!("Loaded ROM: {}", rom_path.display());
+                }
+                Err(msg) => println!("{}", msg),
+            }
+        }
+
+        is_typing.set(sys.options.show_vram_views && check_mem_viewer_inputs(&mut sys));
+        if !is_typing.get() {
+            is_quitting = check_misc_inputs(
+                &mut sys,
+                &bindings,
+                battery_save.as_ref(),
+                &state_save,
+                movie.is_some(),
+            );
+            if is_quitting {
+                break;
+            }
+        }
+        if let Some(console) = &console {
+            console.update(&mut sys);
+        }
+        if let Some(gdb) = &mut gdb {
+            gdb.update(&mut sys);
+        }
 
         window.render_pass(|| {
             draw_rect(window.bounds(), BLACK);
-            let speed = sys.emu.speed();
-            for _ in 0..speed {
-                while !sys.is_render_pending && !sys.hard_lock {
-                    sys.run_one_m_cycle();
+
+            let is_rewind_held = bindings.rewind_keys().iter().any(|key| is_key_down(*key));
+            let is_rewinding = is_rewind_held
+                && !is_typing.get()
+                && rewind
+                    .as_mut()
+                    .is_some_and(|rewind| rewind.step_back(&mut sys));
+
+            let advance = if is_rewinding {
+                None
+            } else {
+                sys.emu.take_advance()
+            };
+
+            match advance {
+                Some(Advance::Run) => {
+                    pacer.start_update(sys.emu.speed());
+                    while pacer.next_frame() {
+                        // Movies hash the rendered frame, so they can't skip any.
+                        let skip_render = !pacer.is_first_frame() && movie.is_none();
+                        set_skip_render(&mut sys, &mut link_peer, skip_render);
+
+                        if !run_frame(&mut sys, &mut link_peer) {
+                            break;
+                        }
+                        end_frame(&mut sys, &mut rewind, &mut movie);
+                    }
+                    set_skip_render(&mut sys, &mut link_peer, false);
+                }
+                Some(Advance::Frames(count)) => {
+                    pacer.idle();
+                    for _ in 0..count {
+                        if !run_frame(&mut sys, &mut link_peer) {
+                            break;
+                        }
+                        end_frame(&mut sys, &mut rewind, &mut movie);
+                    }
+                }
+                Some(Advance::Scanline) => {
+                    pacer.idle();
+                    if run_scanline(&mut sys, &mut link_peer) {
+                        end_frame(&mut sys, &mut rewind, &mut movie);
+                    }
+                }
+                None => pacer.idle(),
+            }
+
+            if let Some(reason) = sys.debugger.take_break() {
+                sys.emu.pause();
+                print_break(&sys, reason);
+                if let Some(gdb) = &mut gdb {
+                    gdb.report_break(reason);
                 }
-                sys.is_render_pending = false;
             }
 
             render_ui(&mut sys);
+            render_speed(&sys, pacer.measured_fps());
             sys.is_render_pending = false;
+
+            if let Some(peer) = &mut link_peer {
+                render_linked_viewport(peer);
+                peer.is_render_pending = false;
+            }
         });
 
+        if let Some(battery_save) = &mut battery_save {
+            battery_save.update(&mut sys);
+        }
+        flush_trace(&mut sys);
+
         next_frame().await;
     }
 
+    if let Some(battery_save) = &battery_save {
+        if sys.mem.cart.is_ram_dirty() {
+            battery_save.save(&mut sys);
+        }
+    }
+
+    if let Some(movie) = &movie {
+        let is_match = movie.finish(&sys);
+        if movie.mode() == MovieMode::Verify {
+            std::process::exit(if is_match { 0 } else { 1 });
+        }
+    }
+
+    finish_recordings(&args, &mut sys);
     debug::flush_serial_char();
+    if is_quitting {
+        return;
+    }
+
+    // Leave the window open to show what went wrong.
     debug::print_system_state(&sys);
 
     loop {
@@ -131,20 +409,271 @@ async fn run_emu() {
     }
 }
 
-fn check_misc_inputs(sys: &mut Sys) {
+/// Creates a system running the ROM at `rom_path`, with the settings for it.
+fn create_sys(
+    args: &Args,
+    config: &Config,
+    rom_path: &Path,
+    show_vram_views: bool,
+) -> Result<Sys, String> {
+    let cart = Cart::load_from(rom_path, true)?;
+    let header = cart.header();
+    let model = args.model.or(config.model(header));
+    let palette = config.palette(header);
+    let boot_rom = match &args.boot_rom_path {
+        Some(path) => Some(
+            fs::read(path)
+                .map_err(|err| format!("Unable to read boot ROM {}: {}", path.display(), err))?,
+        ),
+        None => None,
+    };
+
+    let options = Options {
+        kill_on_infinite_loop: true,
+        show_vram_views,
+        model,
+        boot_rom,
+    };
+
+    let mut sys = Sys::new(options, cart);
+    sys.ppu.set_dmg_palette(palette);
+    sys.debugger.break_on_software = args.debugger;
+    if let Some(symbols) = Symbols::load_for_rom(rom_path)? {
+        println!("Loaded {} symbols.", symbols.count());
+        sys.debugger.symbols = symbols;
+    }
+
+    return Ok(sys);
+}
+
+/// Starts the trace, profile and code/data log asked for on the command line.
+/// These are only kept for the ROM the emulator was started with.
+fn start_recordings(args: &Args, sys: &mut Sys) -> Result<(), String> {
+    if let Some(path) = &args.trace_log_path {
+        start_trace(sys, path)?;
+    }
+    if args.profile_path.is_some() {
+        start_profile(sys);
+    }
+    if let Some(path) = &args.cdl_path {
+        start_cdl(sys, path)?;
+    }
+    return Ok(());
+}
+
+/// Ends the trace and saves the profile and code/data log, if they're running.
+fn finish_recordings(args: &Args, sys: &mut Sys) {
+    stop_trace(sys);
+    if let Some(path) = args
+        .profile_path
+        .as_ref()
+        .filter(|_| sys.debugger.profiler.is_some())
+    {
+        match save_profile(sys, path) {
+            Ok(()) => println!("Saved the profile to {}.", path.display()),
+            Err(msg) => println!("{}", msg),
+        }
+    }
+    if let Some(path) = args
+        .cdl_path
+        .as_ref()
+        .filter(|_| sys.mem.code_data_log.is_some())
+    {
+        match save_cdl(sys, path) {
+            Ok(()) => println!("Saved the code/data log to {}.", path.display()),
+            Err(msg) => println!("{}", msg),
+        }
+    }
+}
+
+/// Battery saves go next to the ROM unless a folder was specified.
+fn find_save_dir(args: &Args, config: &Config, rom_path: &Path) -> PathBuf {
+    match args.save_dir.as_ref().or(config.save_dir.as_ref()) {
+        Some(dir) => dir.clone(),
+        None => match rom_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_owned(),
+            _ => PathBuf::from("."),
+        },
+    }
+}
+
+fn run_m_cycle(sys: &mut Sys, link_peer: &mut Option<Sys>) {
+    match link_peer {
+        Some(peer) => run_linked_m_cycle(sys, peer),
+        None => sys.run_one_m_cycle(),
+    }
+}
+
+/// Runs until the next frame is ready. Returns false if the debugger
+/// broke first.
+fn run_frame(sys: &mut Sys, link_peer: &mut Option<Sys>) -> bool {
+    while !sys.is_render_pending && !sys.hard_lock {
+        run_m_cycle(sys, link_peer);
+        if sys.debugger.is_breaking() {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+/// Runs until the next scanline starts. Returns whether a frame became
+/// ready along the way.
+fn run_scanline(sys: &mut Sys, link_peer: &mut Option<Sys>) -> bool {
+    let ly = sys.mem.io_regs.get(IoReg::Ly);
+
+    // LY stays put while the LCD is off, so stop after a scanline's worth.
+    for _ in 0..DOTS_PER_SCANLINE / 4 {
+        run_m_cycle(sys, link_peer);
+        if sys.is_render_pending || sys.hard_lock || sys.debugger.is_breaking() {
+            return sys.is_render_pending;
+        }
+        if sys.mem.io_regs.get(IoReg::Ly) != ly {
+            break;
+        }
+    }
+
+    return false;
+}
+
+fn set_skip_render(sys: &mut Sys, link_peer: &mut Option<Sys>, skip_render: bool) {
+    sys.skip_render = skip_render;
+    if let Some(peer) = link_peer {
+        peer.skip_render = skip_render;
+    }
+}
+
+/// Call after each emulated frame.
+fn end_frame(sys: &mut Sys, rewind: &mut Option<Rewind>, movie: &mut Option<MovieSession>) {
+    sys.is_render_pending = false;
+
+    if let Some(rewind) = rewind {
+        rewind.update(sys);
+    }
+    if let Some(movie) = movie {
+        movie.update(sys);
+    }
+}
+
+/// Handles the memory view's keys. Returns whether it's taking typed input,
+/// in which case other keys shouldn't act.
+fn check_mem_viewer_inputs(sys: &mut Sys) -> bool {
+    let viewer = &mut sys.debugger.mem_viewer;
+    let Some(input) = viewer.input() else {
+        if is_key_pressed(KeyCode::G) {
+            viewer.start_goto();
+        }
+        if is_key_pressed(KeyCode::E) && sys.emu.is_paused {
+            viewer.start_edit();
+        }
+        if is_key_pressed(KeyCode::B) {
+            viewer.cycle_bank(&sys.mem);
+        }
+        if is_key_pressed(KeyCode::PageUp) {
+            viewer.scroll(-(VIEW_ROWS as i32));
+        }
+        if is_key_pressed(KeyCode::PageDown) {
+            viewer.scroll(VIEW_ROWS as i32);
+        }
+        let (_, wheel_y) = mouse_wheel();
+        if wheel_y != 0.0 {
+            viewer.scroll(-WHEEL_ROWS * wheel_y.signum() as i32);
+        }
+
+        // Drop what was typed, including the key that started typing.
+        while get_char_pressed().is_some() {}
+        return viewer.input().is_some();
+    };
+
+    // Edits only happen while paused.
+    let is_editing = matches!(input, MemViewerInput::Edit(_));
+    if is_editing && !sys.emu.is_paused {
+        viewer.cancel_input();
+        return false;
+    }
+
+    if is_key_pressed(KeyCode::Escape) {
+        viewer.cancel_input();
+    } else if is_key_pressed(KeyCode::Enter) {
+        viewer.confirm(&sys.debugger.symbols, &sys.mem);
+    } else if is_key_pressed(KeyCode::Backspace) {
+        viewer.backspace();
+    } else if is_editing {
+        let moves = [
+            (KeyCode::Left, -1),
+            (KeyCode::Right, 1),
+            (KeyCode::Up, -(ROW_LEN as i32)),
+            (KeyCode::Down, ROW_LEN as i32),
+        ];
+        for (key_code, delta) in moves {
+            if is_key_pressed(key_code) {
+                viewer.move_cursor(delta);
+            }
+        }
+    }
+
+    while let Some(c) = get_char_pressed() {
+        if c.is_ascii_graphic() {
+            viewer.type_char(c, &mut sys.mem);
+        }
+    }
+    return true;
+}
+
+/// Handles the emulator's own keys. Returns whether the user asked to quit.
+fn check_misc_inputs(
+    sys: &mut Sys,
+    bindings: &Bindings,
+    battery_save: Option<&BatterySave>,
+    state_save: &StateSave,
+    is_movie_active: bool,
+) -> bool {
     if is_key_pressed(KeyCode::Escape) {
-        sys.hard_lock = true;
+        return true;
+    }
+
+    if let Some(battery_save) = battery_save {
+        if is_key_pressed(KeyCode::Backspace) {
+            battery_save.save(sys);
+        }
+        if is_key_pressed(KeyCode::Equal) {
+            battery_save.load(sys);
+        }
+    }
+
+    if is_key_pressed(KeyCode::F5) {
+        state_save.save(sys);
+    }
+    if is_key_pressed(KeyCode::F9) && !is_movie_active {
+        state_save.load(sys);
+    }
+
+    if is_key_pressed(KeyCode::F1) {
+        sys.emu.toggle_pause();
+    }
+    if is_key_pressed(KeyCode::F2) {
+        sys.emu.request_step(Advance::Frames(1));
+    }
+    if is_key_pressed(KeyCode::F3) {
+        sys.emu.request_step(Advance::Scanline);
     }
 
-    if is_key_pressed(KeyCode::Backspace) {
-        save_state(sys);
+    // A movie's input only lines up with the run it was recorded from.
+    if is_key_pressed(KeyCode::F6) && !is_movie_active {
+        sys.reset();
     }
-    if is_key_pressed(KeyCode::Equal) {
-        load_state(sys);
+    if is_key_pressed(KeyCode::F7) && !is_movie_active {
+        sys.power_cycle();
     }
 
     if is_key_pressed(KeyCode::Space) {
-        sys.emu.is_speedup_enabled = !sys.emu.is_speedup_enabled;
+        sys.emu.toggle_fast_forward();
+    }
+    if is_key_pressed(KeyCode::LeftBracket) {
+        sys.emu.slower();
+    }
+    if is_key_pressed(KeyCode::RightBracket) {
+        sys.emu.faster();
     }
     if is_key_pressed(KeyCode::T) {
         sys.emu.show_win_map = !sys.emu.show_win_map;
@@ -156,4 +685,16 @@ fn check_misc_inputs(sys: &mut Sys) {
     if is_key_pressed(KeyCode::P) {
         debug_state().print_enabled = !debug_state().print_enabled;
     }
+
+    // Turbo isn't recorded in movies, so it can't change during one.
+    if is_movie_active {
+        return false;
+    }
+    for (key_code, button) in bindings.turbo_keys() {
+        if is_key_pressed(*key_code) {
+            sys.joypad.toggle_turbo(*button);
+        }
+    }
+
+    return false;
 }
@@ -1,4 +1,7 @@
-use crate::mem::Addr;
+use crate::{
+    mem::Addr,
+    util::state::{SaveState, StateReader, StateWriter},
+};
 
 /// Array of bytes that represents a segment of memory.
 pub struct Array {
@@ -29,6 +32,18 @@ impl Array {
         self.memory[idx] = data;
     }
 
+    /// Reads like `read`, but `None` if `abs_addr` is outside the array.
+    pub fn get(&self, abs_addr: Addr) -> Option<u8> {
+        let rel_addr = abs_addr.wrapping_sub(self.start_addr);
+        return self.memory.get(rel_addr as usize).copied();
+    }
+
+    /// The byte at `abs_addr`, or `None` if it's outside the array.
+    pub fn get_mut(&mut self, abs_addr: Addr) -> Option<&mut u8> {
+        let rel_addr = abs_addr.wrapping_sub(self.start_addr);
+        return self.memory.get_mut(rel_addr as usize);
+    }
+
     pub fn mut_(&mut self, abs_addr: impl Into<Addr>) -> &mut u8 {
         let idx = self.to_idx(abs_addr);
         return &mut self.memory[idx];
@@ -44,6 +59,20 @@ impl Array {
     pub fn as_slice(&self) -> &[u8] {
         return self.memory.as_slice();
     }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        return self.memory.as_mut_slice();
+    }
+}
+
+impl SaveState for Array {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bytes(&self.memory);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        r.read_bytes_into(&mut self.memory)
+    }
 }
 
 #[cfg(test)]
@@ -71,4 +100,14 @@ mod tests {
             assert_eq!(mut_value, read_value);
         }
     }
+
+    #[test]
+    fn test_get_outside() {
+        let mut array = Array::new(0xC000, 0x1000);
+        *array.get_mut(0xCFFF).unwrap() = 0x12;
+        assert_eq!(array.get(0xCFFF), Some(0x12));
+        assert_eq!(array.get(0xD000), None);
+        assert_eq!(array.get(0x8000), None);
+        assert!(array.get_mut(0xD000).is_none());
+    }
 }
@@ -0,0 +1,89 @@
+use crate::debugger::{cdl::ReadUse, watch::AccessKind};
+
+use super::{io_regs::IoReg, mem::Mem, Addr};
+
+/// Memory as the CPU and DMA see it. `Mem` is the system's memory map, but
+/// they can run against any bus, e.g. flat RAM or a bus that logs accesses
+/// in tests. They're generic over the bus, so the real one costs nothing
+/// extra.
+pub trait Bus {
+    /// A read by the CPU or DMA.
+    fn read(&mut self, addr: Addr) -> u8;
+
+    /// A read saying what the byte is for, e.g. an opcode, so the code/data
+    /// log can tell code from data. Plain reads are data.
+    fn read_as(&mut self, addr: Addr, use_: ReadUse) -> u8;
+
+    /// A write by the CPU or DMA.
+    fn write(&mut self, addr: Addr, data: u8);
+
+    /// Reads without it counting as an access, for debugging.
+    fn peek(&self, addr: Addr) -> u8;
+
+    /// Reads an entire IO register, bypassing its read mask.
+    fn io_reg(&self, reg: IoReg) -> u8;
+
+    /// Sets an entire IO register, bypassing its write mask and side effects.
+    fn set_io_reg(&mut self, reg: IoReg, data: u8);
+
+    /// The ROM bank mapped at $4000-$7FFF, for debugging.
+    fn rom_bank(&self) -> usize;
+
+    /// Takes the OAM DMA request made by writing to DMA, if any.
+    fn take_oam_dma_request(&mut self) -> bool;
+
+    /// Takes the VRAM DMA request made by writing to HDMA5, if any.
+    fn take_vram_dma_request(&mut self) -> bool;
+}
+
+impl Bus for Mem {
+    #[inline]
+    fn read(&mut self, addr: Addr) -> u8 {
+        self.read_as(addr, ReadUse::Data)
+    }
+
+    #[inline]
+    fn read_as(&mut self, addr: Addr, use_: ReadUse) -> u8 {
+        let data = Mem::read(self, addr);
+        self.watchpoints.check(addr, AccessKind::Read, data);
+        self.log_read_use(addr, use_);
+        return data;
+    }
+
+    #[inline]
+    fn write(&mut self, addr: Addr, data: u8) {
+        Mem::write(self, addr, data);
+        self.watchpoints.check(addr, AccessKind::Write, data);
+        if let Some(recent_writes) = &mut self.recent_writes {
+            recent_writes.record(addr);
+        }
+    }
+
+    fn peek(&self, addr: Addr) -> u8 {
+        Mem::peek(self, addr)
+    }
+
+    #[inline]
+    fn io_reg(&self, reg: IoReg) -> u8 {
+        self.io_regs.get(reg)
+    }
+
+    #[inline]
+    fn set_io_reg(&mut self, reg: IoReg, data: u8) {
+        self.io_regs.set(reg, data);
+    }
+
+    fn rom_bank(&self) -> usize {
+        self.cart.rom_bank()
+    }
+
+    #[inline]
+    fn take_oam_dma_request(&mut self) -> bool {
+        std::mem::take(&mut self.io_regs.dma_requested)
+    }
+
+    #[inline]
+    fn take_vram_dma_request(&mut self) -> bool {
+        std::mem::take(&mut self.io_regs.hdma_requested)
+    }
+}
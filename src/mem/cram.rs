@@ -1,4 +1,8 @@
-use crate::util::{bits::Bits, math::join_16};
+use crate::util::{
+    bits::Bits,
+    math::join_16,
+    state::{SaveState, StateReader, StateWriter},
+};
 
 const COLOR_SIZE: usize = 2;
 const PALETTE_LEN: usize = 4;
@@ -55,3 +59,15 @@ impl Cram {
         }
     }
 }
+
+impl SaveState for Cram {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.index);
+        w.write_bytes(&self.mem);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.index = r.read_u8()?;
+        r.read_bytes_into(&mut self.mem)
+    }
+}
@@ -5,7 +5,13 @@ use num::FromPrimitive;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-use crate::{debug, util::bits::Bits};
+use crate::{
+    debug,
+    util::{
+        bits::Bits,
+        state::{SaveState, StateReader, StateWriter},
+    },
+};
 
 use super::{array::Array, cram::Cram, sections::MemSection, Addr};
 
@@ -36,6 +42,7 @@ pub enum IoReg {
 
     Key1 = 0xFF4D,
     Vbk = 0xFF4F,
+    Boot = 0xFF50,
     Hdma1 = 0xFF51,
     Hdma2 = 0xFF52,
     Hdma3 = 0xFF53,
@@ -109,12 +116,19 @@ impl IoRegs {
 
     /// Reads from the readable bits in the IO register.
     pub fn user_read(&self, addr: Addr) -> u8 {
+        if let Some(reg) = IoReg::from_u16(addr) {
+            debug::record_io_reg_usage(reg, false, 0x00);
+        }
+        return self.peek(addr);
+    }
+
+    /// Reads like `user_read`, but without recording the use. For debuggers.
+    pub fn peek(&self, addr: Addr) -> u8 {
         let Some(reg) = IoReg::from_u16(addr) else {
             return self.mem.read(addr);
         };
 
         let mut data = self.get(reg);
-        debug::record_io_reg_usage(reg, false, 0x00);
         let Some(reg_data) = self.reg_datas.get(&reg) else {
             unreachable!();
         };
@@ -162,6 +176,9 @@ impl IoRegs {
 
         if reg == IoReg::Div {
             value = 0x00;
+        } else if reg == IoReg::Boot {
+            // The boot ROM can't be mapped back in once it's disabled.
+            value |= self.get(IoReg::Boot);
         }
 
         let mut data = self.get(reg);
@@ -196,6 +213,27 @@ impl IoRegs {
     }
 }
 
+impl SaveState for IoRegs {
+    fn save_state(&self, w: &mut StateWriter) {
+        self.mem.save_state(w);
+        self.ie.save_state(w);
+        w.write_bool(self.dma_requested);
+        w.write_bool(self.hdma_requested);
+        self.bg_cram.save_state(w);
+        self.obj_cram.save_state(w);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.mem.load_state(r)?;
+        self.ie.load_state(r)?;
+        self.dma_requested = r.read_bool()?;
+        self.hdma_requested = r.read_bool()?;
+        self.bg_cram.load_state(r)?;
+        self.obj_cram.load_state(r)?;
+        return Ok(());
+    }
+}
+
 mod io_reg_data {
     use super::IoReg;
 
@@ -228,6 +266,7 @@ mod io_reg_data {
                 Ly => 0b0000_0000,
                 Key1 => 0b0111_1111,
                 Rp => 0b1111_1101,
+                Boot => 0b0000_0001,
                 Pcm12 => 0b0000_0000,
                 Pcm34 => 0b0000_0000,
                 _ => 0xFF,
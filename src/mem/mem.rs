@@ -1,27 +1,116 @@
-use crate::{cart::cart::Cart, consts::FAIL_ON_BAD_RW, debug};
+use crate::{
+    cart::cart::Cart,
+    consts::FAIL_ON_BAD_RW,
+    debug,
+    debugger::{
+        cdl::{CodeDataLog, ReadUse},
+        mem_viewer::RecentWrites,
+        watch::Watchpoints,
+    },
+    util::{
+        bits::Bits,
+        state::{SaveState, StateReader, StateWriter},
+    },
+};
 
-use super::{array::Array, io_regs::IoRegs, sections::MemSection, vram::Vram, wram::Wram, Addr};
+use super::{
+    array::Array,
+    io_regs::{IoReg, IoRegs},
+    power_on::{fill_power_on, PowerOnRegion},
+    sections::MemSection,
+    vram::Vram,
+    wram::Wram,
+    Addr,
+};
+
+/// The CGB boot ROM is 0x900 bytes, but the cartridge header shows through this range.
+const BOOT_ROM_HEADER_GAP: std::ops::Range<usize> = 0x0100..0x0200;
 
 pub struct Mem {
     pub cart: Cart,
+    boot_rom: Option<Vec<u8>>,
     pub wram: Wram,
     pub vram: Vram,
     pub oam: Array,
     pub io_regs: IoRegs,
     pub hram: Array,
+
+    /// Checked on CPU and DMA accesses, which go through `Bus`.
+    pub watchpoints: Watchpoints,
+
+    /// Records what ROM bytes the CPU and DMA read for, while set.
+    pub code_data_log: Option<CodeDataLog>,
+
+    /// Records when the CPU and DMA last wrote each address, while set.
+    pub recent_writes: Option<RecentWrites>,
 }
 
 impl Mem {
-    pub fn new(cart: Cart) -> Self {
-        let is_cgb_mode = cart.header().compatibility_mode().is_cgb();
-
+    pub fn new(cart: Cart, is_cgb_mode: bool, boot_rom: Option<Vec<u8>>) -> Self {
         Self {
             cart,
+            boot_rom,
             wram: Wram::new(is_cgb_mode),
             vram: Vram::new(is_cgb_mode),
             oam: MemSection::into_array(MemSection::Oam),
             io_regs: IoRegs::new(),
             hram: MemSection::into_array(MemSection::Hram),
+            watchpoints: Watchpoints::new(),
+            code_data_log: None,
+            recent_writes: None,
+        }
+    }
+
+    /// Returns the registers to their power-on values, as a reset does.
+    /// RAM keeps its contents.
+    pub fn reset(&mut self) {
+        self.cart.reset();
+        self.io_regs = IoRegs::new();
+    }
+
+    /// Fills RAM with the contents it holds at power-on. VRAM is cleared,
+    /// since the boot ROM clears it anyway.
+    pub fn fill_power_on_ram(&mut self, is_cgb_mode: bool) {
+        for (idx, bank) in self.wram.banks_mut().enumerate() {
+            fill_power_on(bank, PowerOnRegion::Wram(idx), is_cgb_mode);
+        }
+        fill_power_on(self.oam.as_mut_slice(), PowerOnRegion::Oam, is_cgb_mode);
+        fill_power_on(self.hram.as_mut_slice(), PowerOnRegion::Hram, is_cgb_mode);
+        self.vram = Vram::new(is_cgb_mode);
+    }
+
+    /// Is the boot ROM still mapped over the start of the cartridge ROM?
+    pub fn is_boot_rom_mapped(&self) -> bool {
+        self.boot_rom.is_some() && self.io_regs.get(IoReg::Boot).bit(0) == 0
+    }
+
+    fn read_boot_rom(&self, addr: Addr) -> Option<u8> {
+        if !self.is_boot_rom_mapped() {
+            return None;
+        }
+
+        let addr = addr as usize;
+        if BOOT_ROM_HEADER_GAP.contains(&addr) {
+            return None;
+        }
+
+        return self.boot_rom.as_ref()?.get(addr).copied();
+    }
+
+    /// Records what the cartridge ROM byte at `addr` was read for, if the
+    /// code/data log is on.
+    #[inline]
+    pub fn log_read_use(&mut self, addr: Addr, use_: ReadUse) {
+        if self.code_data_log.is_none()
+            || MemSection::from_abs_addr(addr) != MemSection::CartRom
+            || self.read_boot_rom(addr).is_some()
+        {
+            return;
+        }
+
+        let offset = self.cart.rom_offset(addr);
+        if let Some(log) = &mut self.code_data_log {
+            log.log(offset, use_);
         }
     }
 
@@ -31,7 +120,10 @@ impl Mem {
         //println!("Rel Addr ({:?}) = {} {:#04x}", section, addr, addr);
 
         match section {
-            MemSection::CartRom => self.cart.read(addr),
+            MemSection::CartRom => match self.read_boot_rom(addr) {
+                Some(data) => data,
+                None => self.cart.read(addr),
+            },
             MemSection::Vram => self.vram.read(&self.io_regs, addr),
             MemSection::ExtRam => self.cart.read(addr), // sys.ext_ram.rd(abs_addr),
             MemSection::Wram => self.wram.read(&self.io_regs, addr),
@@ -54,6 +146,59 @@ impl Mem {
         }
     }
 
+    /// Reads like `read`, but without it counting as a use of an IO register
+    /// or a bad read. For debuggers.
+    pub fn peek(&self, addr: Addr) -> u8 {
+        return match MemSection::from_abs_addr(addr) {
+            MemSection::EchoRam | MemSection::UnusableMemory => 0x00,
+            MemSection::IoRegs | MemSection::IeReg => self.io_regs.peek(addr),
+            _ => self.read(addr),
+        };
+    }
+
+    /// Reads like `peek`, but from `bank` of ROM, VRAM, cartridge RAM or
+    /// WRAM instead of the mapped one. Other addresses have no banks, so
+    /// `bank` is ignored. Cartridge RAM reads even while it's disabled.
+    pub fn peek_banked(&self, addr: Addr, bank: Option<usize>) -> u8 {
+        let Some(bank) = bank else {
+            return self.peek(addr);
+        };
+
+        return match MemSection::from_abs_addr(addr) {
+            MemSection::CartRom if addr >= 0x4000 => self.cart.peek_rom(bank, addr),
+            MemSection::Vram => self.vram.get(bank, addr),
+            MemSection::ExtRam => self.cart.peek_ram(bank, addr),
+            MemSection::Wram if addr >= 0xD000 => self.wram.get(bank, addr),
+            _ => self.peek(addr),
+        };
+    }
+
+    /// Writes like the CPU does, except that writes to ROM change the ROM
+    /// instead of going to the MBC. For debuggers.
+    pub fn poke(&mut self, addr: Addr, data: u8) {
+        self.poke_banked(addr, None, data);
+    }
+
+    /// Writes like `poke`, but to `bank` of ROM, VRAM, cartridge RAM or WRAM
+    /// instead of the mapped one.
+    pub fn poke_banked(&mut self, addr: Addr, bank: Option<usize>, data: u8) {
+        match (MemSection::from_abs_addr(addr), bank) {
+            (MemSection::CartRom, _) => {
+                let mapped = if addr < 0x4000 {
+                    0
+                } else {
+                    self.cart.rom_bank()
+                };
+                let bank = bank.filter(|_| addr >= 0x4000).unwrap_or(mapped);
+                self.cart.poke_rom(bank, addr, data);
+            }
+            (MemSection::Vram, Some(bank)) => self.vram.set(bank, addr, data),
+            (MemSection::ExtRam, Some(bank)) => self.cart.poke_ram(bank, addr, data),
+            (MemSection::Wram, Some(bank)) if addr >= 0xD000 => self.wram.set(bank, addr, data),
+            _ => self.write(addr, data),
+        }
+    }
+
     pub fn write(&mut self, addr: Addr, data: u8) {
         let section = MemSection::from_abs_addr(addr);
 
@@ -95,3 +240,24 @@ impl Mem {
         }
     }
 }
+
+impl SaveState for Mem {
+    fn save_state(&self, w: &mut StateWriter) {
+        self.cart.save_state(w);
+        self.wram.save_state(w);
+        self.vram.save_state(w);
+        self.oam.save_state(w);
+        self.io_regs.save_state(w);
+        self.hram.save_state(w);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.cart.load_state(r)?;
+        self.wram.load_state(r)?;
+        self.vram.load_state(r)?;
+        self.oam.load_state(r)?;
+        self.io_regs.load_state(r)?;
+        self.hram.load_state(r)?;
+        return Ok(());
+    }
+}
@@ -1,8 +1,10 @@
 mod addr;
 mod array;
+pub mod bus;
 pub mod cram;
 pub mod io_regs;
 pub mod mem;
+mod power_on;
 pub mod sections;
 mod vram;
 mod wram;
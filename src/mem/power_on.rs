@@ -0,0 +1,84 @@
+// RAM isn't cleared at power-on. Each cell settles to whatever value it
+// leans towards, which gives every model its own recognisable pattern:
+// DMG RAM comes up as noise, while CGB WRAM comes up as alternating runs of
+// 0x00 and 0xFF. Some games read RAM before writing it, so the emulator
+// fills RAM with an approximation of these patterns instead of zeros. The
+// noise is seeded, so that every power-on is the same.
+
+/// Seeds the noise RAM powers up with. Each region offsets it differently.
+const NOISE_SEED: u32 = 0x2545_F491;
+
+/// CGB WRAM powers up as alternating runs of 0x00 and 0xFF this long.
+const CGB_WRAM_RUN_LEN: usize = 8;
+
+/// Regions of RAM that power up with their own contents.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PowerOnRegion {
+    Wram(usize),
+    Oam,
+    Hram,
+}
+
+/// Fills `ram` with the contents `region` holds at power-on.
+pub fn fill_power_on(ram: &mut [u8], region: PowerOnRegion, is_cgb_model: bool) {
+    let seed = match region {
+        PowerOnRegion::Wram(bank) => {
+            if is_cgb_model {
+                fill_runs(ram);
+                return;
+            }
+            NOISE_SEED.wrapping_add(bank as u32)
+        }
+        PowerOnRegion::Oam => NOISE_SEED.wrapping_add(0x100),
+        PowerOnRegion::Hram => NOISE_SEED.wrapping_add(0x200),
+    };
+
+    fill_noise(ram, seed);
+}
+
+fn fill_runs(ram: &mut [u8]) {
+    for (idx, byte) in ram.iter_mut().enumerate() {
+        let is_high_run = (idx / CGB_WRAM_RUN_LEN) % 2 == 1;
+        *byte = if is_high_run { 0xFF } else { 0x00 };
+    }
+}
+
+/// Xorshift noise. Cheap, and the same on every platform.
+fn fill_noise(ram: &mut [u8], seed: u32) {
+    let mut state = seed.max(1);
+    for byte in ram {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        *byte = (state >> 24) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cgb_wram() {
+        let mut ram = [0x55; 32];
+        fill_power_on(&mut ram, PowerOnRegion::Wram(1), true);
+
+        assert_eq!(ram[..8], [0x00; 8]);
+        assert_eq!(ram[8..16], [0xFF; 8]);
+        assert_eq!(ram[16..24], [0x00; 8]);
+    }
+
+    #[test]
+    fn test_noise() {
+        let mut a = [0; 256];
+        let mut b = [0; 256];
+        fill_power_on(&mut a, PowerOnRegion::Hram, false);
+        fill_power_on(&mut b, PowerOnRegion::Hram, false);
+        assert_eq!(a, b);
+
+        // Not blank, and not the same for every region.
+        assert!(a.iter().any(|byte| *byte != a[0]));
+        fill_power_on(&mut b, PowerOnRegion::Oam, false);
+        assert_ne!(a, b);
+    }
+}
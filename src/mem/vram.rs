@@ -1,6 +1,9 @@
 use std::ops::Range;
 
-use crate::util::bits::Bits;
+use crate::util::{
+    bits::Bits,
+    state::{SaveState, StateReader, StateWriter},
+};
 
 use super::{
     array::Array,
@@ -31,9 +34,20 @@ impl Vram {
         self.banks.len()
     }
 
+    /// Reads from bank `bank`, mapped or not. Banks the system doesn't have
+    /// read $FF.
     #[inline]
     pub fn get(&self, bank: usize, addr: Addr) -> u8 {
-        return self.banks[bank].read(addr);
+        let data = self.banks.get(bank).and_then(|bank| bank.get(addr));
+        return data.unwrap_or(0xFF);
+    }
+
+    /// Writes to bank `bank`, ignoring banks the system doesn't have.
+    #[inline]
+    pub fn set(&mut self, bank: usize, addr: Addr, data: u8) {
+        if let Some(byte) = self.banks.get_mut(bank).and_then(|bank| bank.get_mut(addr)) {
+            *byte = data;
+        }
     }
 
     pub fn get_range(&self, bank: usize, range: Range<usize>) -> &[u8] {
@@ -50,7 +64,8 @@ impl Vram {
         self.banks[bank].write(addr, data);
     }
 
-    fn get_bank(&self, io_regs: &IoRegs) -> usize {
+    /// The bank mapped at $8000-$9FFF.
+    pub fn get_bank(&self, io_regs: &IoRegs) -> usize {
         if self.banks.len() > 1 {
             let vbk = io_regs.get(IoReg::Vbk);
             return vbk.bit(0) as usize;
@@ -59,3 +74,18 @@ impl Vram {
         return 0;
     }
 }
+
+impl SaveState for Vram {
+    fn save_state(&self, w: &mut StateWriter) {
+        for bank in &self.banks {
+            bank.save_state(w);
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        for bank in &mut self.banks {
+            bank.load_state(r)?;
+        }
+        return Ok(());
+    }
+}
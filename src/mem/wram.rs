@@ -1,4 +1,10 @@
-use crate::{mem::sections::MemSection, util::bits::Bits};
+use crate::{
+    mem::sections::MemSection,
+    util::{
+        bits::Bits,
+        state::{SaveState, StateReader, StateWriter},
+    },
+};
 
 use super::{
     array::Array,
@@ -45,7 +51,36 @@ impl Wram {
         self.banks[b].write(addr, data);
     }
 
-    fn get_bank(&self, io_regs: &IoRegs, addr: Addr) -> usize {
+    /// Reads from bank `bank`, mapped or not. Bank 0 is at $C000-$CFFF and
+    /// the others at $D000-$DFFF. Banks the system doesn't have and
+    /// addresses outside the bank read $FF.
+    pub fn get(&self, bank: usize, addr: Addr) -> u8 {
+        let data = self.banks.get(bank).and_then(|bank| bank.get(addr));
+        return data.unwrap_or(0xFF);
+    }
+
+    /// Writes to bank `bank` like `get` reads, ignoring writes outside it.
+    pub fn set(&mut self, bank: usize, addr: Addr, data: u8) {
+        if let Some(byte) = self.banks.get_mut(bank).and_then(|bank| bank.get_mut(addr)) {
+            *byte = data;
+        }
+    }
+
+    pub fn num_banks(&self) -> usize {
+        self.banks.len()
+    }
+
+    /// The contents of each bank, in order.
+    pub fn banks(&self) -> impl Iterator<Item = &[u8]> {
+        self.banks.iter().map(Array::as_slice)
+    }
+
+    pub fn banks_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
+        self.banks.iter_mut().map(Array::as_mut_slice)
+    }
+
+    /// The bank mapped at `addr`.
+    pub fn get_bank(&self, io_regs: &IoRegs, addr: Addr) -> usize {
         if self.banks[0].contains_addr(addr) {
             return 0;
         }
@@ -58,3 +93,18 @@ impl Wram {
         };
     }
 }
+
+impl SaveState for Wram {
+    fn save_state(&self, w: &mut StateWriter) {
+        for bank in &self.banks {
+            bank.save_state(w);
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        for bank in &mut self.banks {
+            bank.load_state(r)?;
+        }
+        return Ok(());
+    }
+}
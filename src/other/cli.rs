@@ -0,0 +1,400 @@
+use std::path::PathBuf;
+
+use super::{mode::HardwareModel, movie::MovieMode};
+
+pub const USAGE: &str = "\
+Usage: rust_cgb_emu [OPTIONS] <ROM>
+       rust_cgb_emu disasm [OPTIONS] <ROM>
+
+Options:
+  --config <PATH>                Config file [default: <config dir>/rust_cgb_emu/config.toml]
+  --boot-rom <PATH>              Run this boot ROM before the cartridge
+  --model <auto|dmg|cgb>         Hardware model to emulate [default: auto]
+  --scale <N>                    Window pixel scale [default: 2]
+  --no-vram-views                Hide the tile map, tile data and palette views
+  --save-dir <DIR>               Folder for battery saves and save states [default: the ROM's folder]
+  --load-state <PATH>            Start from a save state
+  --bindings <PATH>              Key bindings file
+  --link <ROM>                   Run a second game linked by cable in this process
+  --link-listen <ADDR>           Wait for another process to link at ADDR
+  --link-connect <ADDR>          Link to another process listening at ADDR
+  --printer <DIR>                Connect a Game Boy Printer saving printouts to DIR
+  --record-movie <PATH>          Record input to a movie, from power-on or --load-state
+  --play-movie <PATH>            Play back a movie
+  --verify-movie <PATH>          Play back a movie, stopping where it diverges
+  --debugger                     Read debugger commands from the terminal
+  --trace-log <PATH>             Log every instruction to PATH, in Gameboy Doctor's format
+  --gdb <ADDR>                   Let GDB connect at ADDR (e.g. 127.0.0.1:2159)
+  --profile <PATH>               Count cycles per routine, saving them to PATH as folded stacks on exit
+  --cdl <PATH>                   Log which ROM bytes are code and which are data, adding to the log at PATH
+  --kill-after-cpu-ticks <N>     Stop emulation after N CPU ticks
+  --kill-after-nop-count <N>     Stop emulation after N NOPs
+  -h, --help                     Print this message
+";
+
+pub const DISASM_USAGE: &str = "\
+Usage: rust_cgb_emu disasm [OPTIONS] <ROM>
+
+Disassembles the ROM, bank by bank, naming addresses with the symbols in
+the .sym file next to it if there is one.
+
+Options:
+  --bank <N>                     Only disassemble ROM bank N
+  --cdl <PATH>                   Use a code/data log to list data as bytes rather than code
+  --out <PATH>                   Write the listing to PATH instead of printing it
+  -h, --help                     Print this message
+";
+
+/// What to do, chosen by the first argument.
+pub enum Command {
+    Run(Box<Args>),
+    Disasm(DisasmArgs),
+}
+
+impl Command {
+    /// Parses the arguments, not including the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.into_iter().peekable();
+        if args.peek().is_some_and(|arg| arg == "disasm") {
+            args.next();
+            return Ok(Command::Disasm(DisasmArgs::parse(args)?));
+        }
+        return Ok(Command::Run(Box::new(Args::parse(args)?)));
+    }
+}
+
+/// Arguments for the `disasm` subcommand.
+pub struct DisasmArgs {
+    pub rom_path: PathBuf,
+    pub bank: Option<usize>,
+    pub cdl_path: Option<PathBuf>,
+    pub out_path: Option<PathBuf>,
+}
+
+impl DisasmArgs {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut rom_path = None;
+        let mut bank = None;
+        let mut cdl_path = None;
+        let mut out_path = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| disasm_usage_error(format!("Missing value for {}.", name)))
+            };
+
+            match arg.as_str() {
+                "-h" | "--help" => return Err(DISASM_USAGE.to_owned()),
+                "--bank" => {
+                    let value = value(&arg)?;
+                    bank =
+                        Some(value.parse().map_err(|_| {
+                            disasm_usage_error(format!("Invalid bank: {}.", value))
+                        })?);
+                }
+                "--cdl" => cdl_path = Some(value(&arg)?.into()),
+                "--out" => out_path = Some(value(&arg)?.into()),
+                _ if arg.starts_with('-') => {
+                    return Err(disasm_usage_error(format!("Unknown option: {}.", arg)));
+                }
+                _ => {
+                    if rom_path.is_some() {
+                        return Err(disasm_usage_error(format!("Unexpected argument: {}.", arg)));
+                    }
+                    rom_path = Some(PathBuf::from(arg));
+                }
+            }
+        }
+
+        let Some(rom_path) = rom_path else {
+            return Err(disasm_usage_error("No ROM specified.".to_owned()));
+        };
+
+        return Ok(Self {
+            rom_path,
+            bank,
+            cdl_path,
+            out_path,
+        });
+    }
+}
+
+/// Command-line arguments.
+pub struct Args {
+    pub rom_path: PathBuf,
+    pub config_path: Option<PathBuf>,
+    pub boot_rom_path: Option<PathBuf>,
+    pub model: Option<HardwareModel>,
+    pub scale: Option<f32>,
+    pub show_vram_views: bool,
+    pub save_dir: Option<PathBuf>,
+    pub load_state_path: Option<PathBuf>,
+    pub bindings_path: Option<PathBuf>,
+    pub link_path: Option<PathBuf>,
+    pub link_listen_addr: Option<String>,
+    pub link_connect_addr: Option<String>,
+    pub printer_dir: Option<PathBuf>,
+    pub movie: Option<(MovieMode, PathBuf)>,
+    pub debugger: bool,
+    pub trace_log_path: Option<PathBuf>,
+    pub gdb_addr: Option<String>,
+    pub profile_path: Option<PathBuf>,
+    pub cdl_path: Option<PathBuf>,
+    pub kill_after_cpu_ticks: Option<u64>,
+    pub kill_after_nop_count: Option<u64>,
+}
+
+impl Args {
+    /// Parses the arguments, not including the program name. On failure
+    /// (or if help was requested), returns the message to print.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut rom_path = None;
+        let mut parsed = Self {
+            rom_path: PathBuf::new(),
+            config_path: None,
+            boot_rom_path: None,
+            model: None,
+            scale: None,
+            show_vram_views: true,
+            save_dir: None,
+            load_state_path: None,
+            bindings_path: None,
+            link_path: None,
+            link_listen_addr: None,
+            link_connect_addr: None,
+            printer_dir: None,
+            movie: None,
+            debugger: false,
+            trace_log_path: None,
+            gdb_addr: None,
+            profile_path: None,
+            cdl_path: None,
+            kill_after_cpu_ticks: None,
+            kill_after_nop_count: None,
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| usage_error(format!("Missing value for {}.", name)))
+            };
+
+            match arg.as_str() {
+                "-h" | "--help" => return Err(USAGE.to_owned()),
+                "--config" => parsed.config_path = Some(value(&arg)?.into()),
+                "--boot-rom" => parsed.boot_rom_path = Some(value(&arg)?.into()),
+                "--model" => {
+                    parsed.model = match value(&arg)?.as_str() {
+                        "auto" => None,
+                        "dmg" => Some(HardwareModel::Dmg),
+                        "cgb" => Some(HardwareModel::Cgb),
+                        model => {
+                            return Err(usage_error(format!("Unknown model: {}.", model)));
+                        }
+                    };
+                }
+                "--scale" => {
+                    let scale = value(&arg)?;
+                    parsed.scale = match scale.parse::<f32>() {
+                        Ok(scale) if scale > 0.0 => Some(scale),
+                        _ => return Err(usage_error(format!("Invalid scale: {}.", scale))),
+                    };
+                }
+                "--no-vram-views" => parsed.show_vram_views = false,
+                "--save-dir" => parsed.save_dir = Some(value(&arg)?.into()),
+                "--load-state" => parsed.load_state_path = Some(value(&arg)?.into()),
+                "--bindings" => parsed.bindings_path = Some(value(&arg)?.into()),
+                "--link" => parsed.link_path = Some(value(&arg)?.into()),
+                "--link-listen" => parsed.link_listen_addr = Some(value(&arg)?),
+                "--link-connect" => parsed.link_connect_addr = Some(value(&arg)?),
+                "--printer" => parsed.printer_dir = Some(value(&arg)?.into()),
+                "--record-movie" | "--play-movie" | "--verify-movie" => {
+                    if parsed.movie.is_some() {
+                        return Err(usage_error(
+                            "Only one of --record-movie, --play-movie and --verify-movie can be used."
+                                .to_owned(),
+                        ));
+                    }
+                    let mode = match arg.as_str() {
+                        "--record-movie" => MovieMode::Record,
+                        "--play-movie" => MovieMode::Play,
+                        _ => MovieMode::Verify,
+                    };
+                    parsed.movie = Some((mode, value(&arg)?.into()));
+                }
+                "--debugger" => parsed.debugger = true,
+                "--trace-log" => parsed.trace_log_path = Some(value(&arg)?.into()),
+                "--gdb" => parsed.gdb_addr = Some(value(&arg)?),
+                "--profile" => parsed.profile_path = Some(value(&arg)?.into()),
+                "--cdl" => parsed.cdl_path = Some(value(&arg)?.into()),
+                "--kill-after-cpu-ticks" => {
+                    parsed.kill_after_cpu_ticks = Some(parse_count(&arg, &value(&arg)?)?);
+                }
+                "--kill-after-nop-count" => {
+                    parsed.kill_after_nop_count = Some(parse_count(&arg, &value(&arg)?)?);
+                }
+                _ if arg.starts_with('-') => {
+                    return Err(usage_error(format!("Unknown option: {}.", arg)));
+                }
+                _ => {
+                    if rom_path.is_some() {
+                        return Err(usage_error(format!("Unexpected argument: {}.", arg)));
+                    }
+                    rom_path = Some(PathBuf::from(arg));
+                }
+            }
+        }
+
+        let Some(rom_path) = rom_path else {
+            return Err(usage_error("No ROM specified.".to_owned()));
+        };
+        parsed.rom_path = rom_path;
+
+        let link_count = [
+            parsed.link_path.is_some(),
+            parsed.link_listen_addr.is_some(),
+            parsed.link_connect_addr.is_some(),
+            parsed.printer_dir.is_some(),
+        ]
+        .into_iter()
+        .filter(|is_set| *is_set)
+        .count();
+        if link_count > 1 {
+            return Err(usage_error(
+                "Only one of --link, --link-listen, --link-connect and --printer can be used."
+                    .to_owned(),
+            ));
+        }
+
+        // Played back movies start from their own state.
+        let is_playing = matches!(parsed.movie, Some((mode, _)) if mode != MovieMode::Record);
+        if is_playing && parsed.load_state_path.is_some() {
+            return Err(usage_error(
+                "--load-state can't be used when playing a movie.".to_owned(),
+            ));
+        }
+
+        return Ok(parsed);
+    }
+}
+
+fn parse_count(name: &str, value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| usage_error(format!("Invalid value for {}: {}.", name, value)))
+}
+
+fn usage_error(msg: String) -> String {
+    format!("{}\n\n{}", msg, USAGE)
+}
+
+fn disasm_usage_error(msg: String) -> String {
+    format!("{}\n\n{}", msg, DISASM_USAGE)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let args = parse(&[
+            "--model",
+            "dmg",
+            "--no-vram-views",
+            "--scale",
+            "3",
+            "roms/tetris.gb",
+            "--kill-after-nop-count",
+            "100",
+            "--debugger",
+            "--trace-log",
+            "trace.log",
+            "--profile",
+            "cpu.folded",
+            "--cdl",
+            "tetris.cdl",
+        ])
+        .unwrap();
+
+        assert_eq!(args.rom_path, PathBuf::from("roms/tetris.gb"));
+        assert_eq!(args.model, Some(HardwareModel::Dmg));
+        assert!(!args.show_vram_views);
+        assert_eq!(args.scale, Some(3.0));
+        assert_eq!(args.kill_after_nop_count, Some(100));
+        assert_eq!(args.kill_after_cpu_ticks, None);
+        assert_eq!(args.boot_rom_path, None);
+        assert!(args.movie.is_none());
+        assert!(args.debugger);
+        assert_eq!(args.trace_log_path, Some(PathBuf::from("trace.log")));
+        assert_eq!(args.profile_path, Some(PathBuf::from("cpu.folded")));
+        assert_eq!(args.cdl_path, Some(PathBuf::from("tetris.cdl")));
+    }
+
+    #[test]
+    fn test_parse_movie() {
+        let args = parse(&["a.gb", "--verify-movie", "a.movie"]).unwrap();
+        assert_eq!(
+            args.movie,
+            Some((MovieMode::Verify, PathBuf::from("a.movie")))
+        );
+
+        let args = parse(&[
+            "a.gb",
+            "--record-movie",
+            "a.movie",
+            "--load-state",
+            "a.state",
+        ]);
+        assert!(args.is_ok());
+
+        assert!(parse(&["a.gb", "--play-movie", "a.movie", "--load-state", "a.state"]).is_err());
+        assert!(parse(&[
+            "a.gb",
+            "--play-movie",
+            "a.movie",
+            "--record-movie",
+            "b.movie"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["a.gb", "b.gb"]).is_err());
+        assert!(parse(&["a.gb", "--model", "gba"]).is_err());
+        assert!(parse(&["a.gb", "--scale"]).is_err());
+        assert!(parse(&["a.gb", "--frobnicate"]).is_err());
+        assert!(parse(&["a.gb", "--link", "b.gb", "--printer", "out"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_command() {
+        let command = Command::parse(["a.gb".to_owned()]).unwrap();
+        assert!(matches!(command, Command::Run(args) if args.rom_path == Path::new("a.gb")));
+
+        let command =
+            Command::parse(["disasm", "a.gb", "--bank", "3", "--out", "a.asm"].map(str::to_owned))
+                .unwrap();
+        let Command::Disasm(args) = command else {
+            panic!("Expected the disasm subcommand.");
+        };
+        assert_eq!(args.rom_path, PathBuf::from("a.gb"));
+        assert_eq!(args.bank, Some(3));
+        assert_eq!(args.out_path, Some(PathBuf::from("a.asm")));
+
+        assert!(Command::parse(["disasm".to_owned()]).is_err());
+        assert!(Command::parse(["disasm", "a.gb", "--bank", "x"].map(str::to_owned)).is_err());
+        assert!(Command::parse(["disasm", "a.gb", "--debugger"].map(str::to_owned)).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+// The config file is read from `<config dir>/rust_cgb_emu/config.toml`,
+// or from the path given with `--config`. Command-line arguments take
+// precedence over per-game overrides, which take precedence over the rest
+// of the file:
+//
+//   scale = 3.0
+//   save_dir = "/home/me/gb_saves"
+//   palette = "green"
+//
+//   [bindings]
+//   a = ["X", "Pad:South"]
+//   turbo_a = ["S"]
+//
+//   [audio]
+//   volume = 0.5
+//
+//   [rewind]
+//   budget_mb = 128
+//
+//   [[game]]
+//   title = "POKEMON SILVER"
+//   global_checksum = 0x1234
+//   model = "dmg"
+
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{cart::header::CartHeader, input::bindings::Bindings, ppu::colors::DmgPalette};
+
+use super::mode::HardwareModel;
+
+const CONFIG_DIR_NAME: &str = "rust_cgb_emu";
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// User settings loaded from the config file.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub scale: Option<f32>,
+    pub save_dir: Option<PathBuf>,
+    pub show_vram_views: Option<bool>,
+    pub model: Option<HardwareModel>,
+    pub palette: Option<DmgPalette>,
+    pub bindings: BTreeMap<String, Vec<String>>,
+    #[allow(dead_code)]
+    pub audio: AudioConfig,
+    pub rewind: RewindConfig,
+
+    #[serde(rename = "game")]
+    pub games: Vec<GameOverride>,
+}
+
+/// Audio output settings. They're accepted in the file, but nothing reads
+/// them until sound is emulated.
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    pub enabled: bool,
+    pub volume: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            volume: 1.0,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RewindConfig {
+    pub enabled: bool,
+
+    /// Memory used to store rewind history, in MiB.
+    pub budget_mb: usize,
+
+    /// Take a snapshot every N frames. Higher values trade rewind smoothness
+    /// for a longer history.
+    pub snapshot_period_frames: u32,
+}
+
+impl Default for RewindConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            budget_mb: 64,
+            snapshot_period_frames: 1,
+        }
+    }
+}
+
+/// Settings that only apply to one game, identified by its header.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GameOverride {
+    pub title: String,
+
+    /// If not specified, every ROM with a matching title is overridden.
+    pub global_checksum: Option<u16>,
+
+    pub model: Option<HardwareModel>,
+    pub palette: Option<DmgPalette>,
+}
+
+impl Config {
+    /// Path of the config file in the user's config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = if cfg!(windows) {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+        } else {
+            env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        };
+
+        return config_dir.map(|dir| dir.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME));
+    }
+
+    /// Loads the config file at `path`. A missing file gives the default config.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default());
+            }
+            Err(err) => return Err(format!("Unable to read config {:?}: {}", path, err)),
+        };
+
+        return Self::parse(&text).map_err(|msg| format!("Config {:?}: {}", path, msg));
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|err| err.to_string())
+    }
+
+    /// Returns the overrides for the game with the given header, if any.
+    pub fn game_override(&self, header: &CartHeader) -> Option<&GameOverride> {
+        self.games.iter().find(|game| {
+            game.title == header.title()
+                && game
+                    .global_checksum
+                    .is_none_or(|checksum| checksum == header.global_checksum)
+        })
+    }
+
+    /// The model to emulate for the game, if the config specifies one.
+    pub fn model(&self, header: &CartHeader) -> Option<HardwareModel> {
+        self.game_override(header)
+            .and_then(|game| game.model)
+            .or(self.model)
+    }
+
+    /// The DMG palette to use for the game.
+    pub fn palette(&self, header: &CartHeader) -> DmgPalette {
+        self.game_override(header)
+            .and_then(|game| game.palette)
+            .or(self.palette)
+            .unwrap_or_default()
+    }
+
+    pub fn bindings(&self) -> Result<Bindings, String> {
+        let entries = self.bindings.iter().map(|(name, inputs)| {
+            let inputs = inputs.iter().map(String::as_str).collect::<Vec<_>>();
+            (name.as_str(), inputs)
+        });
+
+        return Bindings::from_entries(entries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = r#"
+            scale = 3.0
+            palette = "green"
+
+            [bindings]
+            a = ["Z"]
+
+            [audio]
+            volume = 0.5
+
+            [rewind]
+            budget_mb = 128
+
+            [[game]]
+            title = "POKEMON SILVER"
+            global_checksum = 0x1234
+            model = "dmg"
+            palette = "pocket"
+        "#;
+        let config = Config::parse(text).unwrap();
+
+        assert_eq!(config.scale, Some(3.0));
+        assert_eq!(config.palette, Some(DmgPalette::Green));
+        assert_eq!(config.model, None);
+        assert_eq!(config.bindings["a"], vec!["Z".to_owned()]);
+        assert!(config.bindings().is_ok());
+        assert!(config.audio.enabled);
+        assert_eq!(config.audio.volume, 0.5);
+        assert!(config.rewind.enabled);
+        assert_eq!(config.rewind.budget_mb, 128);
+        assert_eq!(config.rewind.snapshot_period_frames, 1);
+
+        assert_eq!(config.games.len(), 1);
+        assert_eq!(config.games[0].title, "POKEMON SILVER");
+        assert_eq!(config.games[0].global_checksum, Some(0x1234));
+        assert_eq!(config.games[0].model, Some(HardwareModel::Dmg));
+        assert_eq!(config.games[0].palette, Some(DmgPalette::Pocket));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Config::parse("scale = \"big\"").is_err());
+        assert!(Config::parse("colour = \"green\"").is_err());
+        assert!(Config::parse("palette = \"purple\"").is_err());
+        assert!(Config::parse("[[game]]\nmodel = \"dmg\"").is_err());
+    }
+}
@@ -1,7 +1,60 @@
+/// How far to run the system during one host frame.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Advance {
+    /// As many frames as are due at the current speed.
+    Run,
+    Frames(u32),
+    Scanline,
+}
+
+/// How fast the system runs, relative to real hardware.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EmuSpeed {
+    Multiplier(f64),
+
+    /// As fast as the host can go.
+    Uncapped,
+}
+
+impl EmuSpeed {
+    /// Short label for the on-screen indicator, with multipliers shown as
+    /// percentages.
+    pub fn label(self) -> String {
+        match self {
+            EmuSpeed::Multiplier(multiplier) => {
+                format!("SPD {}", (multiplier * 100.0).round() as u32)
+            }
+            EmuSpeed::Uncapped => "SPD MAX".into(),
+        }
+    }
+}
+
+/// Speeds that can be picked with the speed keys, slowest first.
+const SPEEDS: [EmuSpeed; 8] = [
+    EmuSpeed::Multiplier(0.25),
+    EmuSpeed::Multiplier(0.5),
+    EmuSpeed::Multiplier(1.0),
+    EmuSpeed::Multiplier(2.0),
+    EmuSpeed::Multiplier(4.0),
+    EmuSpeed::Multiplier(8.0),
+    EmuSpeed::Multiplier(16.0),
+    EmuSpeed::Uncapped,
+];
+const NORMAL_SPEED_IDX: usize = 2;
+const DEFAULT_FAST_FORWARD_IDX: usize = 4;
+
 // Emulator user settings.
-#[derive(Default)]
 pub struct Emu {
-    pub is_speedup_enabled: bool,
+    /// Index of the current speed in `SPEEDS`.
+    speed_idx: usize,
+
+    /// The speed that fast-forward switches to.
+    fast_forward_idx: usize,
+
+    pub is_paused: bool,
+
+    /// A single step requested while paused.
+    step: Option<Advance>,
 
     /// False: the background tilemap is shown.
     /// True: the window tilemap is shown.
@@ -11,14 +64,73 @@ pub struct Emu {
     pub vram_bank_sel: usize,
 }
 
+impl Default for Emu {
+    fn default() -> Self {
+        Self {
+            speed_idx: NORMAL_SPEED_IDX,
+            fast_forward_idx: DEFAULT_FAST_FORWARD_IDX,
+            is_paused: false,
+            step: None,
+            show_win_map: false,
+            vram_bank_sel: 0,
+        }
+    }
+}
+
 impl Emu {
-    pub const SPEEDUP_RATE: u32 = 4;
+    pub fn speed(&self) -> EmuSpeed {
+        SPEEDS[self.speed_idx]
+    }
 
-    pub fn speed(&self) -> u32 {
-        if self.is_speedup_enabled {
-            Self::SPEEDUP_RATE
+    pub fn faster(&mut self) {
+        self.set_speed_idx(usize::min(self.speed_idx + 1, SPEEDS.len() - 1));
+    }
+
+    pub fn slower(&mut self) {
+        self.set_speed_idx(self.speed_idx.saturating_sub(1));
+    }
+
+    /// Switches between normal speed and the last other speed picked.
+    pub fn toggle_fast_forward(&mut self) {
+        if self.speed_idx == NORMAL_SPEED_IDX {
+            self.speed_idx = self.fast_forward_idx;
         } else {
-            1
+            self.set_speed_idx(NORMAL_SPEED_IDX);
+        }
+    }
+
+    fn set_speed_idx(&mut self, idx: usize) {
+        if self.speed_idx != NORMAL_SPEED_IDX {
+            self.fast_forward_idx = self.speed_idx;
+        }
+        self.speed_idx = idx;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.is_paused = !self.is_paused;
+        self.step = None;
+    }
+
+    pub fn pause(&mut self) {
+        self.is_paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.is_paused = false;
+        self.step = None;
+    }
+
+    /// Pauses, then runs `step` once.
+    pub fn request_step(&mut self, step: Advance) {
+        self.is_paused = true;
+        self.step = Some(step);
+    }
+
+    /// How far to run during this host frame, if at all.
+    pub fn take_advance(&mut self) -> Option<Advance> {
+        if self.is_paused {
+            return self.step.take();
         }
+        return Some(Advance::Run);
     }
 }
@@ -1,7 +1,4 @@
-use macroquad::{
-    color::{BLACK, RED, WHITE},
-    input::{is_key_down, KeyCode},
-};
+use macroquad::color::{BLACK, RED, WHITE};
 use xf::{
     mq::draw::draw_rect,
     num::{
@@ -12,59 +9,123 @@ use xf::{
 
 use crate::{
     consts::P8,
+    cpu::interrupt::{request_interrupt, InterruptType},
+    input::{
+        button::{Button, JoypadState},
+        source::{InputSource, NoInput},
+    },
     mem::io_regs::IoReg,
+    ppu::ppu::MCYCLES_PER_FRAME,
     sys::Sys,
-    util::{bits::Bits, draw::draw_empty_rect},
+    util::{
+        bits::Bits,
+        draw::draw_empty_rect,
+        state::{SaveState, StateReader, StateWriter},
+    },
 };
 
-/// Represents a Game Boy button.
-#[derive(Clone, Copy)]
-enum Button {
-    Up,
-    Right,
-    Down,
-    Left,
+/// Turbo buttons are held for this many frames, then released for as many.
+const TURBO_PERIOD_FRAMES: u64 = 2;
+
+/// Represents the joypad state, as seen by the emulated system.
+pub struct Joypad {
+    source: Box<dyn InputSource>,
+
+    /// Buttons held after turbo and the opposing-direction filter.
+    state: JoypadState,
+
+    /// Buttons that have turbo enabled.
+    turbo: JoypadState,
+
+    frames_polled: u64,
+    mcycles_since_poll: u32,
+}
+
+impl Joypad {
+    pub fn new() -> Self {
+        Self {
+            source: Box::new(NoInput),
+            state: JoypadState::default(),
+            turbo: JoypadState::default(),
+            frames_polled: 0,
+            mcycles_since_poll: MCYCLES_PER_FRAME,
+        }
+    }
+
+    /// Releases all buttons. The input source and turbo settings are kept.
+    pub fn reset(&mut self) {
+        self.state = JoypadState::default();
+        self.frames_polled = 0;
+        self.mcycles_since_poll = MCYCLES_PER_FRAME;
+    }
+
+    /// Replaces where the joypad's inputs come from.
+    pub fn set_source(&mut self, source: Box<dyn InputSource>) {
+        self.source = source;
+    }
+
+    pub fn state(&self) -> JoypadState {
+        self.state
+    }
+
+    pub fn toggle_turbo(&mut self, button: Button) {
+        self.turbo.toggle(button);
+    }
+}
 
-    B,
-    A,
+/// Turbo settings are left alone, like other user settings.
+impl SaveState for Joypad {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u8(self.state.bits());
+        w.write_u64(self.frames_polled);
+        w.write_u32(self.mcycles_since_poll);
+    }
 
-    Start,
-    Select,
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.state = JoypadState::from_bits(r.read_u8()?);
+        self.frames_polled = r.read_u64()?;
+        self.mcycles_since_poll = r.read_u32()?;
+        return Ok(());
+    }
 }
 
-impl Button {
-    pub fn key_code(self) -> KeyCode {
-        match self {
-            Button::Up => KeyCode::Up,
-            Button::Right => KeyCode::Right,
-            Button::Down => KeyCode::Down,
-            Button::Left => KeyCode::Left,
+/// Applies turbo and prevents opposing directions from being held at once.
+fn filter_input(raw: JoypadState, turbo: JoypadState, frame: u64) -> JoypadState {
+    let mut state = raw;
 
-            Button::B => KeyCode::Z,
-            Button::A => KeyCode::X,
+    let is_turbo_released = (frame / TURBO_PERIOD_FRAMES) % 2 == 1;
+    if is_turbo_released {
+        state = JoypadState::from_bits(state.bits() & !turbo.bits());
+    }
 
-            Button::Start => KeyCode::Enter,
-            Button::Select => KeyCode::RightShift,
+    for (a, b) in [(Button::Left, Button::Right), (Button::Up, Button::Down)] {
+        if state.is_pressed(a) && state.is_pressed(b) {
+            state.set(a, false);
+            state.set(b, false);
         }
     }
+
+    return state;
 }
 
-pub fn draw_joypad_state(org: IVec2) {
-    draw_button(Button::Up, i2(4, 1), org);
-    draw_button(Button::Right, i2(5, 2), org);
-    draw_button(Button::Down, i2(4, 3), org);
-    draw_button(Button::Left, i2(3, 2), org);
+pub fn draw_joypad_state(sys: &Sys, org: IVec2) {
+    let state = sys.joypad.state();
 
-    draw_button(Button::B, i2(13, 3), org);
-    draw_button(Button::A, i2(14, 2), org);
+    draw_button(state, Button::Up, i2(4, 1), org);
+    draw_button(state, Button::Right, i2(5, 2), org);
+    draw_button(state, Button::Down, i2(4, 3), org);
+    draw_button(state, Button::Left, i2(3, 2), org);
 
-    draw_button(Button::Start, i2(8, 4), org);
-    draw_button(Button::Select, i2(10, 4), org);
+    draw_button(state, Button::B, i2(13, 3), org);
+    draw_button(state, Button::A, i2(14, 2), org);
+
+    draw_button(state, Button::Start, i2(8, 4), org);
+    draw_button(state, Button::Select, i2(10, 4), org);
 }
 
-fn draw_button(button: Button, pos: IVec2, org: IVec2) {
+fn draw_button(state: JoypadState, button: Button, pos: IVec2, org: IVec2) {
     let bounds = ir(org + (pos * P8), P8);
-    if is_key_down(button.key_code()) {
+    if state.is_pressed(button) {
         draw_rect(bounds, RED);
     } else {
         draw_rect(bounds, BLACK);
@@ -72,35 +133,176 @@ fn draw_button(button: Button, pos: IVec2, org: IVec2) {
     draw_empty_rect(bounds, WHITE);
 }
 
+/// Polls the input source once per frame. Counted in M-cycles rather than
+/// PPU frames so that input is still read while the LCD is off.
+fn poll_input(sys: &mut Sys) {
+    let joypad = &mut sys.joypad;
+
+    joypad.mcycles_since_poll += 1;
+    if joypad.mcycles_since_poll < MCYCLES_PER_FRAME {
+        return;
+    }
+    joypad.mcycles_since_poll = 0;
+
+    let raw = joypad.source.poll();
+    joypad.state = filter_input(raw, joypad.turbo, joypad.frames_polled);
+    joypad.frames_polled += 1;
+}
+
 pub fn handle_joypad_inputs(sys: &mut Sys) {
+    poll_input(sys);
+
+    let state = sys.joypad.state();
     let p1 = sys.mem.io_regs.get(IoReg::P1);
+    let prev_lo_4 = p1.bits(3, 0);
     let select_btns = p1.bit(5) == 0;
     let select_dpad = p1.bit(4) == 0;
 
     let mut lo_4 = 0xF;
     if select_btns {
-        read_button(&mut lo_4, 0, Button::A);
-        read_button(&mut lo_4, 1, Button::B);
-        read_button(&mut lo_4, 2, Button::Select);
-        read_button(&mut lo_4, 3, Button::Start);
+        read_button(&mut lo_4, 0, state, Button::A);
+        read_button(&mut lo_4, 1, state, Button::B);
+        read_button(&mut lo_4, 2, state, Button::Select);
+        read_button(&mut lo_4, 3, state, Button::Start);
     }
 
     if select_dpad {
-        read_button(&mut lo_4, 0, Button::Right);
-        read_button(&mut lo_4, 1, Button::Left);
-        read_button(&mut lo_4, 2, Button::Up);
-        read_button(&mut lo_4, 3, Button::Down);
+        read_button(&mut lo_4, 0, state, Button::Right);
+        read_button(&mut lo_4, 1, state, Button::Left);
+        read_button(&mut lo_4, 2, state, Button::Up);
+        read_button(&mut lo_4, 3, state, Button::Down);
     }
 
     sys.mem.io_regs.mut_(IoReg::P1, |p1| {
         p1.set_bits(3, 0, lo_4);
     });
+
+    // A button press pulls its input line from high to low.
+    let pressed = prev_lo_4 & !lo_4;
+    if pressed != 0 {
+        request_interrupt(sys, InterruptType::Joypad);
+        sys.speed_ctrl.wake();
+    }
 }
 
-fn read_button(p1: &mut u8, idx: u8, button: Button) {
-    let value = if is_key_down(button.key_code()) { 0 } else { 1 };
+fn read_button(p1: &mut u8, idx: u8, state: JoypadState, button: Button) {
+    let value = if state.is_pressed(button) { 0 } else { 1 };
     let mut mask = 0xFF;
     mask.set_bit(0, value);
     mask = u8::rotate_left(mask, idx as u32);
     *p1 &= mask;
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        cpu::{exec::execute_next_instr, regs::CpuReg16},
+        test::headless::{create_headless_sys, lock_headless},
+    };
+
+    use super::*;
+
+    /// Holds the same buttons every frame.
+    struct Held(JoypadState);
+
+    impl InputSource for Held {
+        fn poll(&mut self) -> JoypadState {
+            self.0
+        }
+    }
+
+    fn create_sys() -> Sys {
+        let rom = Path::new("assets/files/custom_roms/ld_r8_r8/rom.gb");
+        return create_headless_sys(rom, None).unwrap();
+    }
+
+    fn is_joypad_requested(sys: &Sys) -> bool {
+        sys.mem
+            .io_regs
+            .get(IoReg::If)
+            .bit(InterruptType::Joypad.flag_idx())
+            == 1
+    }
+
+    #[test]
+    fn test_opposing_directions() {
+        let mut raw = JoypadState::default();
+        raw.set(Button::Left, true);
+        raw.set(Button::Right, true);
+        raw.set(Button::Up, true);
+
+        let state = filter_input(raw, JoypadState::default(), 0);
+        assert!(!state.is_pressed(Button::Left));
+        assert!(!state.is_pressed(Button::Right));
+        assert!(state.is_pressed(Button::Up));
+    }
+
+    #[test]
+    fn test_turbo() {
+        let mut raw = JoypadState::default();
+        raw.set(Button::A, true);
+        raw.set(Button::B, true);
+        let mut turbo = JoypadState::default();
+        turbo.set(Button::A, true);
+
+        let pressed = (0..8)
+            .map(|frame| filter_input(raw, turbo, frame).is_pressed(Button::A))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pressed,
+            [true, true, false, false, true, true, false, false]
+        );
+
+        assert!((0..8).all(|frame| filter_input(raw, turbo, frame).is_pressed(Button::B)));
+    }
+
+    #[test]
+    fn test_joypad_interrupt() {
+        let _lock = lock_headless();
+        let mut sys = create_sys();
+        let mut state = JoypadState::default();
+        state.set(Button::A, true);
+        sys.joypad.set_source(Box::new(Held(state)));
+
+        // Select the buttons, not the d-pad.
+        sys.mem.io_regs.set(IoReg::P1, 0xDF);
+        sys.mem.io_regs.set(IoReg::If, 0x00);
+        handle_joypad_inputs(&mut sys);
+        assert_eq!(sys.mem.io_regs.get(IoReg::P1).bits(3, 0), 0xE);
+        assert!(is_joypad_requested(&sys));
+
+        // Holding the button doesn't request it again.
+        sys.mem.io_regs.set(IoReg::If, 0x00);
+        handle_joypad_inputs(&mut sys);
+        assert!(!is_joypad_requested(&sys));
+    }
+
+    #[test]
+    fn test_stop_wakes_on_press() {
+        let _lock = lock_headless();
+        let mut sys = create_sys();
+        sys.mem.poke(0x0100, 0x10);
+        sys.mem.poke(0x0101, 0x00);
+        sys.regs.set_16(CpuReg16::PC, 0x0100);
+        sys.mem.io_regs.set(IoReg::P1, 0xDF);
+
+        execute_next_instr(&mut sys);
+        assert!(sys.speed_ctrl.is_low_power_mode_active());
+
+        // Nothing's pressed, so it stays stopped.
+        for _ in 0..MCYCLES_PER_FRAME {
+            sys.run_one_m_cycle();
+        }
+        assert!(sys.speed_ctrl.is_low_power_mode_active());
+
+        let mut state = JoypadState::default();
+        state.set(Button::Start, true);
+        sys.joypad.set_source(Box::new(Held(state)));
+        for _ in 0..MCYCLES_PER_FRAME {
+            sys.run_one_m_cycle();
+        }
+        assert!(!sys.speed_ctrl.is_low_power_mode_active());
+    }
+}
@@ -1,4 +1,9 @@
+pub mod cli;
+pub mod config;
 pub mod emu;
 pub mod joypad;
 pub mod mode;
+pub mod movie;
+pub mod pacing;
+pub mod rewind;
 pub mod save;
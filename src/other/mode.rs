@@ -1,3 +1,13 @@
+use serde::Deserialize;
+
+/// The Game Boy model being emulated.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HardwareModel {
+    Dmg,
+    Cgb,
+}
+
 #[derive(Clone, Copy)]
 pub enum CompatibilityMode {
     DmgOnly,
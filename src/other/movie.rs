@@ -0,0 +1,162 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use crate::{
+    input::{
+        movie::{Movie, MoviePlayer, MovieRecorder},
+        source::InputSource,
+    },
+    sys::Sys,
+    util::hash::Fnv1a,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MovieMode {
+    Record,
+    Play,
+
+    /// Plays the movie and stops at the first frame that differs from the
+    /// recording.
+    Verify,
+}
+
+/// A movie being recorded or played back by the running system.
+pub struct MovieSession {
+    mode: MovieMode,
+    path: PathBuf,
+    movie: Rc<RefCell<Movie>>,
+
+    /// Emulated frames since the movie started.
+    frame: usize,
+    diverged_frame: Option<usize>,
+}
+
+impl MovieSession {
+    /// Starts recording the input from `source` to a movie at `path`. The
+    /// movie starts from power-on, unless `from_current_state` is set.
+    pub fn record(
+        sys: &mut Sys,
+        path: PathBuf,
+        source: Box<dyn InputSource>,
+        from_current_state: bool,
+    ) -> Self {
+        let start_state = from_current_state.then(|| sys.save_snapshot());
+        let movie = Rc::new(RefCell::new(Movie::new(start_state)));
+        sys.joypad
+            .set_source(Box::new(MovieRecorder::new(source, movie.clone())));
+
+        return Self::new(MovieMode::Record, path, movie);
+    }
+
+    /// Starts playing back the movie at `path`. `sys` must have just been
+    /// powered on.
+    pub fn play(sys: &mut Sys, path: PathBuf, mode: MovieMode) -> Result<Self, String> {
+        let movie = Movie::load(&path, sys.mem.cart.header())?;
+        if let Some(start_state) = &movie.start_state {
+            sys.load_snapshot(start_state)
+                .map_err(|msg| format!("Unable to load movie {:?}: {}", path, msg))?;
+        }
+
+        let movie = Rc::new(RefCell::new(movie));
+        sys.joypad
+            .set_source(Box::new(MoviePlayer::new(movie.clone())));
+
+        return Ok(Self::new(mode, path, movie));
+    }
+
+    fn new(mode: MovieMode, path: PathBuf, movie: Rc<RefCell<Movie>>) -> Self {
+        Self {
+            mode,
+            path,
+            movie,
+            frame: 0,
+            diverged_frame: None,
+        }
+    }
+
+    pub fn mode(&self) -> MovieMode {
+        self.mode
+    }
+
+    /// Call once per emulated frame. Records the frame's hash, or checks it
+    /// against the recording.
+    pub fn update(&mut self, sys: &mut Sys) {
+        let hash = frame_hash(sys);
+        let mut movie = self.movie.borrow_mut();
+
+        if self.mode == MovieMode::Record {
+            movie.frame_hashes.push(hash);
+            self.frame += 1;
+            return;
+        }
+
+        let frame_count = movie.frame_hashes.len();
+        if self.frame > frame_count {
+            return;
+        }
+
+        if self.frame == frame_count {
+            println!("Movie finished after {} frames.", frame_count);
+            if self.mode == MovieMode::Verify {
+                sys.hard_lock = true;
+            }
+        } else if self.diverged_frame.is_none() && movie.frame_hashes[self.frame] != hash {
+            println!("Movie diverged from the recording at frame {}.", self.frame);
+            self.diverged_frame = Some(self.frame);
+            if self.mode == MovieMode::Verify {
+                sys.hard_lock = true;
+            }
+        }
+
+        self.frame += 1;
+    }
+
+    /// Call when emulation stops. Saves a recording, or returns whether
+    /// the whole movie played back exactly as recorded.
+    pub fn finish(&self, sys: &Sys) -> bool {
+        let movie = self.movie.borrow();
+
+        if self.mode == MovieMode::Record {
+            return match movie.save(&self.path, sys.mem.cart.header()) {
+                Ok(()) => {
+                    println!("Saved {} frame movie to: {:?}", self.frame, self.path);
+                    true
+                }
+                Err(err) => {
+                    println!("{}", err);
+                    false
+                }
+            };
+        }
+
+        if let Some(frame) = self.diverged_frame {
+            println!("Movie {:?} FAILED at frame {}.", self.path, frame);
+            return false;
+        }
+
+        let frame_count = movie.frame_hashes.len();
+        if self.frame < frame_count {
+            println!(
+                "Movie {:?} stopped at frame {} of {}.",
+                self.path, self.frame, frame_count
+            );
+            return false;
+        }
+
+        println!(
+            "Movie {:?} PASSED: {} frames match.",
+            self.path, frame_count
+        );
+        return true;
+    }
+}
+
+/// Hashes what a replay has to reproduce: the frame on the LCD and WRAM.
+fn frame_hash(sys: &Sys) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(sys.ppu.frame().pixels().as_flattened());
+    for bank in sys.mem.wram.banks() {
+        hasher.write(bank);
+    }
+
+    return hasher.finish();
+}
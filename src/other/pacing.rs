@@ -0,0 +1,174 @@
+// Emulated frames are paced against real time, not the display's refresh
+// rate. Each host frame, the pacer works out how many frames are due at the
+// current speed: slow motion shows some frames for several refreshes, and
+// fast-forward runs several frames per refresh. Uncapped speed runs frames
+// until the host frame's time is used up.
+
+use std::time::{Duration, Instant};
+
+use crate::ppu::ppu::MCYCLES_PER_FRAME;
+
+use super::emu::EmuSpeed;
+
+/// M-cycles per second at normal speed.
+const MCYCLES_PER_SECOND: f64 = 1_048_576.0;
+
+/// About 59.73 Hz.
+const FRAMES_PER_SECOND: f64 = MCYCLES_PER_SECOND / MCYCLES_PER_FRAME as f64;
+
+/// Longest stretch of real time the pacer catches up on. Anything longer
+/// (a stall, or the emulator falling behind) is dropped, not run in a burst.
+const MAX_CATCH_UP: Duration = Duration::from_millis(100);
+
+/// Time spent running frames each host frame when uncapped. The rest is
+/// left for rendering.
+const UNCAPPED_RUN_TIME: Duration = Duration::from_millis(12);
+
+/// How often the measured frame rate is updated.
+const FPS_PERIOD: Duration = Duration::from_millis(500);
+
+/// Decides how many frames to run each host frame, and measures how many
+/// actually ran.
+pub struct FramePacer {
+    last_update: Instant,
+    frames_due: f64,
+
+    /// Set when uncapped: frames run until then.
+    deadline: Option<Instant>,
+    frames_this_update: u32,
+
+    fps_period_start: Instant,
+    fps_period_frames: u32,
+    measured_fps: f64,
+}
+
+impl FramePacer {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            last_update: now,
+            frames_due: 0.0,
+            deadline: None,
+            frames_this_update: 0,
+            fps_period_start: now,
+            fps_period_frames: 0,
+            measured_fps: 0.0,
+        }
+    }
+
+    /// Call once per host frame in which the system runs, then run frames
+    /// while `next_frame` returns true.
+    pub fn start_update(&mut self, speed: EmuSpeed) {
+        let now = Instant::now();
+        let elapsed = Duration::min(now - self.last_update, MAX_CATCH_UP);
+        self.last_update = now;
+        self.frames_this_update = 0;
+        self.measure(now);
+
+        match speed {
+            EmuSpeed::Multiplier(multiplier) => {
+                let max_due = MAX_CATCH_UP.as_secs_f64() * FRAMES_PER_SECOND * multiplier;
+                self.frames_due += elapsed.as_secs_f64() * FRAMES_PER_SECOND * multiplier;
+                self.frames_due = f64::min(self.frames_due, max_due);
+                self.deadline = None;
+            }
+            EmuSpeed::Uncapped => {
+                self.frames_due = 0.0;
+                self.deadline = Some(now + UNCAPPED_RUN_TIME);
+            }
+        }
+    }
+
+    /// Call instead of `start_update` in host frames where the system
+    /// doesn't run freely (paused, stepping or rewinding).
+    pub fn idle(&mut self) {
+        let now = Instant::now();
+        self.last_update = now;
+        self.frames_due = 0.0;
+        self.deadline = None;
+        self.measure(now);
+    }
+
+    /// Returns whether to run another frame in this host frame.
+    pub fn next_frame(&mut self) -> bool {
+        let is_due = match self.deadline {
+            Some(deadline) => Instant::now() < deadline,
+            None => self.frames_due >= 1.0,
+        };
+        if !is_due {
+            return false;
+        }
+
+        self.frames_due = f64::max(self.frames_due - 1.0, 0.0);
+        self.frames_this_update += 1;
+        self.fps_period_frames += 1;
+        return true;
+    }
+
+    /// Is the frame about to run the first in this host frame? Only that
+    /// frame gets displayed, so the rest don't need to be rendered.
+    pub fn is_first_frame(&self) -> bool {
+        self.frames_this_update <= 1
+    }
+
+    /// Emulated frames per second, over the last measurement period.
+    pub fn measured_fps(&self) -> f64 {
+        self.measured_fps
+    }
+
+    fn measure(&mut self, now: Instant) {
+        let period = now - self.fps_period_start;
+        if period < FPS_PERIOD {
+            return;
+        }
+
+        self.measured_fps = self.fps_period_frames as f64 / period.as_secs_f64();
+        self.fps_period_start = now;
+        self.fps_period_frames = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_update(pacer: &mut FramePacer, speed: EmuSpeed, elapsed: Duration) -> u32 {
+        pacer.last_update -= elapsed;
+        pacer.start_update(speed);
+
+        let mut frames = 0;
+        while pacer.next_frame() {
+            frames += 1;
+        }
+        return frames;
+    }
+
+    #[test]
+    fn test_multiplier() {
+        let mut pacer = FramePacer::new();
+        let elapsed = Duration::from_millis(50);
+
+        // About 3 frames are due every 50 ms; the remainder carries over.
+        let frames = (0..20)
+            .map(|_| run_update(&mut pacer, EmuSpeed::Multiplier(1.0), elapsed))
+            .sum::<u32>();
+        assert!((59..=61).contains(&frames));
+
+        let frames = run_update(&mut pacer, EmuSpeed::Multiplier(4.0), elapsed);
+        assert!((11..=12).contains(&frames));
+
+        let frames = run_update(&mut pacer, EmuSpeed::Multiplier(0.25), elapsed);
+        assert!(frames <= 1);
+    }
+
+    #[test]
+    fn test_catch_up_limit() {
+        let mut pacer = FramePacer::new();
+        let frames = run_update(
+            &mut pacer,
+            EmuSpeed::Multiplier(1.0),
+            Duration::from_secs(5),
+        );
+        assert!(frames <= 6);
+    }
+}
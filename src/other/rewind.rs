@@ -0,0 +1,201 @@
+// Rewind keeps a history of machine snapshots in groups. Each group starts
+// with a keyframe, stored on its own, followed by snapshots stored as
+// deltas against that keyframe (see `util::delta`). When the history goes
+// over its memory budget, the oldest group is dropped as a whole, since its
+// deltas can't be decoded without it.
+
+use crate::{
+    sys::Sys,
+    util::{
+        delta::{decode_delta, encode_delta},
+        ring_buffer::RingBuffer,
+    },
+};
+
+/// Snapshots in a group, including the keyframe.
+const SNAPSHOTS_PER_KEYFRAME: usize = 30;
+
+/// Upper bound on the number of groups, regardless of the memory budget.
+const MAX_GROUPS: usize = 1 << 16;
+
+struct SnapshotGroup {
+    keyframe: Vec<u8>,
+    deltas: Vec<Vec<u8>>,
+}
+
+impl SnapshotGroup {
+    fn size_bytes(&self) -> usize {
+        self.keyframe.len() + self.deltas.iter().map(Vec::len).sum::<usize>()
+    }
+}
+
+/// A bounded history of compressed snapshots, newest last.
+pub struct SnapshotHistory {
+    groups: RingBuffer<SnapshotGroup>,
+    budget_bytes: usize,
+    used_bytes: usize,
+
+    /// The decoded keyframe of the newest group.
+    keyframe: Vec<u8>,
+}
+
+impl SnapshotHistory {
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            groups: RingBuffer::new(MAX_GROUPS),
+            budget_bytes,
+            used_bytes: 0,
+            keyframe: vec![],
+        }
+    }
+
+    pub fn push(&mut self, snapshot: Vec<u8>) {
+        let group = self
+            .groups
+            .newest_mut()
+            .filter(|group| group.deltas.len() + 1 < SNAPSHOTS_PER_KEYFRAME);
+
+        match group {
+            Some(group) => {
+                let delta = encode_delta(&snapshot, &self.keyframe);
+                self.used_bytes += delta.len();
+                group.deltas.push(delta);
+            }
+            None => {
+                let group = SnapshotGroup {
+                    keyframe: encode_delta(&snapshot, &[]),
+                    deltas: vec![],
+                };
+                self.used_bytes += group.size_bytes();
+                if self.groups.len() == MAX_GROUPS {
+                    self.pop_oldest_group();
+                }
+                self.groups.add(group);
+                self.keyframe = snapshot;
+            }
+        }
+
+        // Always keep the newest group, even if it doesn't fit.
+        while self.used_bytes > self.budget_bytes && self.groups.len() > 1 {
+            self.pop_oldest_group();
+        }
+    }
+
+    /// Removes and returns the newest snapshot.
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        let group = self.groups.newest_mut()?;
+        if let Some(delta) = group.deltas.pop() {
+            self.used_bytes -= delta.len();
+            return Some(decode(&delta, &self.keyframe));
+        }
+
+        let group = self.groups.pop_newest()?;
+        self.used_bytes -= group.size_bytes();
+        let snapshot = std::mem::take(&mut self.keyframe);
+
+        if let Some(group) = self.groups.newest_mut() {
+            self.keyframe = decode(&group.keyframe, &[]);
+        }
+
+        return Some(snapshot);
+    }
+
+    fn pop_oldest_group(&mut self) {
+        if let Some(group) = self.groups.pop_oldest() {
+            self.used_bytes -= group.size_bytes();
+        }
+    }
+}
+
+fn decode(delta: &[u8], base: &[u8]) -> Vec<u8> {
+    // Only the history encodes deltas, so they are always valid.
+    return decode_delta(delta, base).expect("Corrupt rewind snapshot.");
+}
+
+/// Records the system state as it runs, so that it can be stepped backwards.
+pub struct Rewind {
+    history: SnapshotHistory,
+    snapshot_period_frames: u32,
+    frames_until_snapshot: u32,
+}
+
+impl Rewind {
+    pub fn new(budget_bytes: usize, snapshot_period_frames: u32) -> Self {
+        Self {
+            history: SnapshotHistory::new(budget_bytes),
+            snapshot_period_frames: snapshot_period_frames.max(1),
+            frames_until_snapshot: 0,
+        }
+    }
+
+    /// Call once per emulated frame. Takes a snapshot every N frames.
+    pub fn update(&mut self, sys: &Sys) {
+        if self.frames_until_snapshot > 0 {
+            self.frames_until_snapshot -= 1;
+            return;
+        }
+
+        self.frames_until_snapshot = self.snapshot_period_frames - 1;
+        self.history.push(sys.save_snapshot());
+    }
+
+    /// Restores the newest snapshot and removes it from the history. Returns
+    /// false when there is nothing left to rewind.
+    pub fn step_back(&mut self, sys: &mut Sys) -> bool {
+        let Some(snapshot) = self.history.pop() else {
+            return false;
+        };
+
+        sys.load_snapshot(&snapshot)
+            .expect("Unable to restore rewind snapshot.");
+        self.frames_until_snapshot = 0;
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(idx: usize) -> Vec<u8> {
+        let mut snapshot = vec![0; 1024];
+        snapshot[idx % 1024] = idx as u8;
+        snapshot[1023] = (idx / 256) as u8;
+        return snapshot;
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut history = SnapshotHistory::new(usize::MAX);
+        let count = SNAPSHOTS_PER_KEYFRAME * 2 + 5;
+        for idx in 0..count {
+            history.push(snapshot(idx));
+        }
+
+        for idx in (0..count).rev() {
+            assert_eq!(history.pop(), Some(snapshot(idx)));
+        }
+        assert_eq!(history.pop(), None);
+        assert_eq!(history.groups.len(), 0);
+        assert_eq!(history.used_bytes, 0);
+    }
+
+    #[test]
+    fn test_budget() {
+        let mut history = SnapshotHistory::new(1024);
+        let count = SNAPSHOTS_PER_KEYFRAME * 20;
+        for idx in 0..count {
+            history.push(snapshot(idx));
+            assert!(history.used_bytes <= 1024 || history.groups.len() == 1);
+        }
+
+        // Only whole groups are dropped, so the newest snapshots are intact.
+        let mut popped = 0;
+        while let Some(data) = history.pop() {
+            assert_eq!(data, snapshot(count - 1 - popped));
+            popped += 1;
+        }
+        assert!(popped >= SNAPSHOTS_PER_KEYFRAME);
+        assert!(popped < count);
+    }
+}
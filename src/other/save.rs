@@ -1,42 +1,268 @@
-use std::{fs, io::Write, path::Path};
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
 
-use crate::{sys::Sys, util::slice::copy_from_safe};
+use crate::{
+    sys::Sys,
+    util::{
+        slice::copy_from_safe,
+        state::{StateReader, StateWriter},
+    },
+};
 
-const SAVE_FOLDER_PATH: &str = "C:\\Users\\matth\\Desktop";
+/// Cartridge RAM is checked for unsaved changes every this many frames.
+const AUTOSAVE_PERIOD_FRAMES: u32 = 300;
 
-/// Saves the contents of cartridge RAM to a file named after the currently running game.
-pub fn save_state(sys: &Sys) {
-    let cart_ram = sys.mem.cart.ram();
+/// Identifies save state files.
+const STATE_MAGIC: &[u8] = b"GBST";
 
-    let file_name = sys.mem.cart.header().title();
-    let path = format!("{}\\{}.sav", SAVE_FOLDER_PATH, file_name);
-    println!("{}", path);
-    let path = Path::new(&path);
+/// The battery-backed save file for the running game's cartridge RAM.
+pub struct BatterySave {
+    path: PathBuf,
+    frames_until_autosave: u32,
+}
+
+impl BatterySave {
+    /// Creates a save named after the ROM at `rom_path`, stored in `save_dir`.
+    pub fn new(rom_path: &Path, save_dir: &Path) -> Self {
+        let stem = rom_path.file_stem().unwrap_or_default().to_string_lossy();
+        let path = save_dir.join(format!("{}.sav", stem));
+
+        Self {
+            path,
+            frames_until_autosave: AUTOSAVE_PERIOD_FRAMES,
+        }
+    }
+
+    /// Saves the contents of cartridge RAM, if the cartridge has a battery.
+    pub fn save(&self, sys: &mut Sys) {
+        if !has_battery(sys) {
+            return;
+        }
+
+        match write_atomic(&self.path, sys.mem.cart.ram()) {
+            Ok(()) => {
+                sys.mem.cart.clear_ram_dirty();
+                println!("Saved to: {:?}", self.path);
+            }
+            Err(err) => println!("Unable to save to {:?}: {}", self.path, err),
+        }
+    }
+
+    /// Loads the contents of cartridge RAM, if the cartridge has a battery.
+    pub fn load(&self, sys: &mut Sys) -> bool {
+        if !has_battery(sys) {
+            return false;
+        }
+
+        let Ok(buffer) = fs::read(&self.path) else {
+            return false;
+        };
+
+        let ram_size = sys.mem.cart.ram().len();
+        if buffer.len() != ram_size {
+            println!(
+                "Not loading {:?}: expected {} bytes but found {}.",
+                self.path,
+                ram_size,
+                buffer.len()
+            );
+            return false;
+        }
+
+        copy_from_safe(sys.mem.cart.ram_mut(), &buffer);
+        sys.mem.cart.clear_ram_dirty();
+
+        println!("Loaded from: {:?}", self.path);
 
-    let mut file = fs::OpenOptions::new()
-        .create(true)
-        .write(true)
-        .open(&path)
-        .unwrap();
+        return true;
+    }
+
+    /// Called once per frame. Saves periodically if cartridge RAM has changed.
+    pub fn update(&mut self, sys: &mut Sys) {
+        self.frames_until_autosave -= 1;
+        if self.frames_until_autosave > 0 {
+            return;
+        }
+        self.frames_until_autosave = AUTOSAVE_PERIOD_FRAMES;
+
+        if sys.mem.cart.is_ram_dirty() {
+            self.save(sys);
+        }
+    }
+}
+
+/// The quick save state slot for the running game, holding a snapshot of
+/// the whole machine.
+pub struct StateSave {
+    path: PathBuf,
+}
+
+impl StateSave {
+    /// Creates a save state named after the ROM at `rom_path`, stored in `save_dir`.
+    pub fn new(rom_path: &Path, save_dir: &Path) -> Self {
+        let stem = rom_path.file_stem().unwrap_or_default().to_string_lossy();
+        let path = save_dir.join(format!("{}.state", stem));
+
+        Self { path }
+    }
+
+    pub fn save(&self, sys: &Sys) {
+        match save_state_file(sys, &self.path) {
+            Ok(()) => println!("Saved state to: {:?}", self.path),
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    pub fn load(&self, sys: &mut Sys) -> bool {
+        match load_state_file(sys, &self.path) {
+            Ok(()) => {
+                println!("Loaded state from: {:?}", self.path);
+                return true;
+            }
+            Err(err) => {
+                println!("{}", err);
+                return false;
+            }
+        }
+    }
+}
 
-    file.write_all(&cart_ram).unwrap();
+/// Writes a snapshot of the whole machine to `path`.
+pub fn save_state_file(sys: &Sys, path: &Path) -> Result<(), String> {
+    let mut w = StateWriter::new();
+    w.write_bytes(STATE_MAGIC);
+    sys.mem.cart.header().write_id(&mut w);
+    w.write_bytes(&sys.save_snapshot());
 
-    println!("Saved to: {:?}", path);
+    return write_atomic(path, &w.into_bytes())
+        .map_err(|err| format!("Unable to save state to {:?}: {}", path, err));
 }
 
-/// Loads the contents of cartridge RAM from a file named after the currently running game.
-pub fn load_state(sys: &mut Sys) -> bool {
-    let file_name = sys.mem.cart.header().title();
-    let path = format!("{}\\{}.sav", SAVE_FOLDER_PATH, file_name);
+/// Restores a snapshot written by `save_state_file`. The state must have
+/// been saved by the same game.
+pub fn load_state_file(sys: &mut Sys, path: &Path) -> Result<(), String> {
+    let data = fs::read(path).map_err(|err| format!("Unable to read state {:?}: {}", path, err))?;
+    let err = |msg: String| format!("Unable to load state {:?}: {}", path, msg);
 
-    let Ok(buffer) = fs::read(&path) else {
-        return false;
+    let mut r = StateReader::new(&data);
+    if r.read_bytes().map_err(err)? != STATE_MAGIC {
+        return Err(err("not a save state.".into()));
+    }
+    sys.mem.cart.header().check_id(&mut r).map_err(err)?;
+    let snapshot = r.read_bytes().map_err(err)?;
+
+    return sys.load_snapshot(snapshot).map_err(err);
+}
+
+fn has_battery(sys: &Sys) -> bool {
+    sys.mem.cart.header().cart_type.has_battery() && !sys.mem.cart.ram().is_empty()
+}
+
+/// Writes to a temporary file first, so that a crash mid-write can't
+/// leave a truncated save behind.
+fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+
+    return fs::rename(&tmp_path, path);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::{
+        cart::cart::Cart,
+        test::headless::{headless_options, initialize_headless_debug, lock_headless},
     };
 
-    let cart_ram = sys.mem.cart.ram_mut();
-    copy_from_safe(cart_ram, &buffer);
+    use super::*;
+
+    /// An empty directory for one test's files.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rust_cgb_emu_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        return dir;
+    }
+
+    /// Runs the test ROM as a cart of type `cart_type` with 8 KB of RAM.
+    fn create_cart_sys(dir: &Path, cart_type: u8) -> Sys {
+        let mut rom = fs::read("assets/files/custom_roms/ld_r8_r8/rom.gb").unwrap();
+        rom[0x0147] = cart_type;
+        rom[0x0149] = 0x02;
+        let path = dir.join("game.gb");
+        fs::write(&path, &rom).unwrap();
+
+        initialize_headless_debug();
+        let cart = Cart::load_from(&path, false).unwrap();
+        return Sys::new(headless_options(None), cart);
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = test_dir("write_atomic");
+        let path = dir.join("game.sav");
+        write_atomic(&path, &[1, 2, 3]).unwrap();
+        write_atomic(&path, &[4, 5]).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), [4, 5]);
+        assert!(!dir.join("game.sav.tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_checks_size() {
+        let _lock = lock_headless();
+        let dir = test_dir("load_checks_size");
+        // MBC1+RAM+BATTERY.
+        let mut sys = create_cart_sys(&dir, 0x03);
+        let battery_save = BatterySave::new(&dir.join("game.gb"), &dir);
+
+        fs::write(dir.join("game.sav"), [0x5A; 0x100]).unwrap();
+        assert!(!battery_save.load(&mut sys));
+        assert_eq!(sys.mem.cart.ram()[0], 0x00);
+
+        fs::write(dir.join("game.sav"), [0x5A; 0x2000]).unwrap();
+        assert!(battery_save.load(&mut sys));
+        assert_eq!(sys.mem.cart.ram()[0], 0x5A);
+        assert!(!sys.mem.cart.is_ram_dirty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_ram_dirty() {
+        let _lock = lock_headless();
+        let dir = test_dir("ram_dirty");
 
-    println!("Loaded from: {}", path);
+        // MBC1+RAM+BATTERY: only writes while RAM is enabled count.
+        let mut sys = create_cart_sys(&dir, 0x03);
+        sys.mem.cart.write(0xA000, 0x12);
+        assert!(!sys.mem.cart.is_ram_dirty());
+        sys.mem.cart.write(0x0000, 0x0A);
+        sys.mem.cart.write(0xA000, 0x12);
+        assert!(sys.mem.cart.is_ram_dirty());
 
-    return true;
+        // MBC3+TIMER+RAM+BATTERY: writes to RTC registers don't count.
+        let mut sys = create_cart_sys(&dir, 0x10);
+        sys.mem.cart.write(0x0000, 0x0A);
+        sys.mem.cart.write(0x4000, 0x08);
+        sys.mem.cart.write(0xA000, 0x12);
+        assert!(!sys.mem.cart.is_ram_dirty());
+        assert_eq!(sys.mem.cart.ram()[0], 0x00);
+        sys.mem.cart.write(0x4000, 0x00);
+        sys.mem.cart.write(0xA000, 0x12);
+        assert!(sys.mem.cart.is_ram_dirty());
+        assert_eq!(sys.mem.cart.read(0xA000), 0x12);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -1,4 +1,5 @@
-use macroquad::color::Color;
+use macroquad::color::{Color, BLACK, DARKGRAY, LIGHTGRAY, WHITE};
+use serde::Deserialize;
 
 use crate::util::bits::Bits;
 
@@ -33,6 +34,41 @@ impl Colors {
     }
 }
 
+/// Colors used to display the four DMG shades.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DmgPalette {
+    #[default]
+    Gray,
+
+    /// The original DMG's green LCD.
+    Green,
+
+    /// The Game Boy Pocket's LCD.
+    Pocket,
+}
+
+impl DmgPalette {
+    /// Colors for shades 0 (lightest) to 3 (darkest).
+    pub fn shades(self) -> [Color; 4] {
+        match self {
+            DmgPalette::Gray => [WHITE, LIGHTGRAY, DARKGRAY, BLACK],
+            DmgPalette::Green => [
+                Color::from_rgba(0x9B, 0xBC, 0x0F, 0xFF),
+                Color::from_rgba(0x8B, 0xAC, 0x0F, 0xFF),
+                Color::from_rgba(0x30, 0x62, 0x30, 0xFF),
+                Color::from_rgba(0x0F, 0x38, 0x0F, 0xFF),
+            ],
+            DmgPalette::Pocket => [
+                Color::from_rgba(0xC4, 0xCF, 0xA1, 0xFF),
+                Color::from_rgba(0x8B, 0x95, 0x6D, 0xFF),
+                Color::from_rgba(0x4D, 0x53, 0x3C, 0xFF),
+                Color::from_rgba(0x1F, 0x1F, 0x1F, 0xFF),
+            ],
+        }
+    }
+}
+
 fn convert_to_float(x: u16) -> f32 {
     const MAX: f32 = 31 as f32;
     return (x as f32) / MAX;
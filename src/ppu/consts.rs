@@ -31,17 +31,24 @@ pub const PALETTES_ORG: IVec2 = i2(
     TILE_DATA_ORG.x,
     TILE_DATA_ORG.y + (3 * (TILE_DATA_BLOCK_DRAW_SIZE.y + P8.y)),
 );
+pub const LINKED_VIEWPORT_ORG: IVec2 = TILE_MAP_ORG;
 pub const JOYPAD_ORG: IVec2 = i2(
     VIEWPORT_ORG.x,
     VIEWPORT_ORG.y + (VIEWPORT_P8_SIZE.y + 1) * P8.y,
 );
+pub const DISASM_ORG: IVec2 = i2(VIEWPORT_ORG.x, JOYPAD_ORG.y + 7 * P8.y);
+pub const DISASM_P8_SIZE: IVec2 = i2(VIEWPORT_P8_SIZE.x, 7);
+
+/// 16 rows of 16 bytes, then a line for typing into or help.
+pub const MEM_VIEW_P8_SIZE: IVec2 = i2(70, 17);
+pub const MEM_VIEW_ORG: IVec2 = i2(VIEWPORT_ORG.x, (TILE_MAP_P8_SIZE.y + 3) * P8.y);
 
 pub const WINDOW_P8_SIZE_NORMAL: IVec2 = i2(VIEWPORT_P8_SIZE.x + 2, VIEWPORT_P8_SIZE.y + 10);
 pub const WINDOW_SIZE_NORMAL: IVec2 = IVec2::mul(WINDOW_P8_SIZE_NORMAL, P8);
 
 pub const WINDOW_P8_SIZE_DEBUG: IVec2 = i2(
     VIEWPORT_P8_SIZE.x + TILE_MAP_P8_SIZE.x + TILE_DATA_P8_SIZE.x + 4,
-    TILE_MAP_P8_SIZE.y + 2,
+    TILE_MAP_P8_SIZE.y + MEM_VIEW_P8_SIZE.y + 4,
 );
 pub const WINDOW_SIZE_DEBUG: IVec2 = IVec2::mul(WINDOW_P8_SIZE_DEBUG, P8);
 
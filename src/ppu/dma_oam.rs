@@ -1,4 +1,9 @@
-use crate::{mem::io_regs::IoReg, sys::Sys};
+use crate::{
+    debugger::cdl::ReadUse,
+    mem::{bus::Bus, io_regs::IoReg},
+    sys::Sys,
+    util::state::{SaveState, StateReader, StateWriter},
+};
 
 const DMA_DURATION_M_CYCLES: u16 = 160;
 
@@ -17,12 +22,24 @@ impl DmaOam {
     }
 }
 
+impl SaveState for DmaOam {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.is_active);
+        w.write_u16(self.next_idx);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.is_active = r.read_bool()?;
+        self.next_idx = r.read_u16()?;
+        return Ok(());
+    }
+}
+
 /// Advances the OAM DMA state by one M-Cycle.
-pub fn update_oam_dma(sys: &mut Sys) {
+pub fn update_oam_dma<B: Bus>(sys: &mut Sys<B>) {
     let dma = sys.ppu.oam_dma_mut();
     if !dma.is_active {
-        if sys.mem.io_regs.dma_requested {
-            sys.mem.io_regs.dma_requested = false;
+        if sys.mem.take_oam_dma_request() {
             start_dma(sys);
         } else {
             return;
@@ -32,21 +49,21 @@ pub fn update_oam_dma(sys: &mut Sys) {
     transfer_one_byte(sys);
 }
 
-fn start_dma(sys: &mut Sys) {
+fn start_dma<B: Bus>(sys: &mut Sys<B>) {
     let dma = sys.ppu.oam_dma_mut();
     dma.is_active = true;
     dma.next_idx = 0;
 }
 
-fn transfer_one_byte(sys: &mut Sys) {
+fn transfer_one_byte<B: Bus>(sys: &mut Sys<B>) {
     let dma = sys.ppu.oam_dma_mut();
 
     let idx = dma.next_idx;
-    let dma_val = sys.mem.io_regs.get(IoReg::Dma) as u16;
+    let dma_val = sys.mem.io_reg(IoReg::Dma) as u16;
     let src_addr = (dma_val * 0x100) + idx;
     let dst_addr = 0xFE00 + idx;
 
-    let data = sys.mem.read(src_addr);
+    let data = sys.mem.read_as(src_addr, ReadUse::Dma);
     sys.mem.write(dst_addr, data);
 
     dma.next_idx += 1;
@@ -54,3 +71,41 @@ fn transfer_one_byte(sys: &mut Sys) {
         dma.is_active = false;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        other::mode::CompatibilityMode,
+        test::{
+            bus::{Access, FlatBus, LoggingBus},
+            headless::headless_options,
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_oam_dma() {
+        let mut bus = LoggingBus::new(FlatBus::new());
+        for idx in 0..0xA0 {
+            bus.inner.ram[0xC100 + idx] = idx as u8;
+        }
+        bus.inner.ram[IoReg::Dma.as_addr() as usize] = 0xC1;
+        bus.inner.oam_dma_requested = true;
+
+        let mut sys = Sys::with_bus(headless_options(None), CompatibilityMode::DmgOnly, bus);
+
+        // One byte per M-cycle, then nothing once the transfer is done.
+        for _ in 0..DMA_DURATION_M_CYCLES + 4 {
+            update_oam_dma(&mut sys);
+        }
+
+        let accesses = &sys.mem.accesses;
+        assert_eq!(accesses.len(), 2 * DMA_DURATION_M_CYCLES as usize);
+        assert_eq!(
+            accesses[2..4],
+            [Access::Read(0xC101, 1), Access::Write(0xFE01, 1)]
+        );
+        assert_eq!(sys.mem.inner.ram[0xFE9F], 0x9F);
+    }
+}
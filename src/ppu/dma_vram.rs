@@ -1,7 +1,13 @@
+use num::FromPrimitive;
+
 use crate::{
-    mem::{io_regs::IoReg, Addr},
+    debugger::cdl::ReadUse,
+    mem::{bus::Bus, io_regs::IoReg, Addr},
     sys::Sys,
-    util::bits::Bits,
+    util::{
+        bits::Bits,
+        state::{SaveState, StateReader, StateWriter},
+    },
 };
 
 use super::ppu::PpuMode;
@@ -27,7 +33,7 @@ pub struct DmaVram {
 //per microsecond (even if the itself program runs
 //it Normal Speed Mode).
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, FromPrimitive)]
 enum TransferMode {
     General,
     HBlank,
@@ -48,10 +54,37 @@ impl DmaVram {
     }
 }
 
+impl SaveState for DmaVram {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.is_active);
+        w.write_u16(self.src_addr);
+        w.write_u16(self.dst_addr);
+        w.write_u8(self.data_len);
+        w.write_u8(self.transfer_mode as u8);
+        w.write_bool(self.pending_hblank_transfer);
+        w.write_u16(self.next_idx);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.is_active = r.read_bool()?;
+        self.src_addr = r.read_u16()?;
+        self.dst_addr = r.read_u16()?;
+        self.data_len = r.read_u8()?;
+        let transfer_mode = r.read_u8()?;
+        self.transfer_mode = TransferMode::from_u8(transfer_mode).ok_or(format!(
+            "Invalid VRAM DMA mode in state: {}.",
+            transfer_mode
+        ))?;
+        self.pending_hblank_transfer = r.read_bool()?;
+        self.next_idx = r.read_u16()?;
+        return Ok(());
+    }
+}
+
 /// Advances the VRAM DMA state by one M-Cycle.
-pub fn update_vram_dma(sys: &mut Sys) {
+pub fn update_vram_dma<B: Bus>(sys: &mut Sys<B>) {
     // Is VRAM DMA supported?
-    if !sys.mem.cart.header().compatibility_mode().is_cgb() {
+    if !sys.is_cgb_mode() {
         return;
     }
 
@@ -60,8 +93,7 @@ pub fn update_vram_dma(sys: &mut Sys) {
     // Check if VRAM DMA was requested.
     //let hdma = sys.ppu.hdma_mut();
     if !sys.ppu.vram_dma_mut().is_active {
-        if sys.mem.io_regs.hdma_requested {
-            sys.mem.io_regs.hdma_requested = false;
+        if sys.mem.take_vram_dma_request() {
             start_hdma(sys);
             //return;
         } else {
@@ -84,16 +116,16 @@ pub fn update_vram_dma(sys: &mut Sys) {
     }
 }
 
-fn start_hdma(sys: &mut Sys) {
-    let hdma1 = sys.mem.io_regs.get(IoReg::Hdma1) as u16;
-    let hdma2 = sys.mem.io_regs.get(IoReg::Hdma2) as u16;
+fn start_hdma<B: Bus>(sys: &mut Sys<B>) {
+    let hdma1 = sys.mem.io_reg(IoReg::Hdma1) as u16;
+    let hdma2 = sys.mem.io_reg(IoReg::Hdma2) as u16;
     let src_addr = ((hdma1 << 8) | hdma2) & 0xFFF0;
 
-    let hdma3 = sys.mem.io_regs.get(IoReg::Hdma3) as u16;
-    let hdma4 = sys.mem.io_regs.get(IoReg::Hdma4) as u16;
+    let hdma3 = sys.mem.io_reg(IoReg::Hdma3) as u16;
+    let hdma4 = sys.mem.io_reg(IoReg::Hdma4) as u16;
     let dst_addr = ((hdma3 << 8) | hdma4) & 0x1FF0;
 
-    let hdma5 = sys.mem.io_regs.get(IoReg::Hdma5);
+    let hdma5 = sys.mem.io_reg(IoReg::Hdma5);
     let transfer_mode = if hdma5.bit(7) == 0 {
         TransferMode::General
     } else {
@@ -116,12 +148,12 @@ fn start_hdma(sys: &mut Sys) {
     // println!("  txfer mode = {:?}", transfer_mode);
 
     // Set Hdma5.7 hi to indicate transfer is active.
-    sys.mem.io_regs.mut_(IoReg::Hdma5, |hdma5| {
-        hdma5.set_bit(7, 1);
-    });
+    let mut hdma5 = sys.mem.io_reg(IoReg::Hdma5);
+    hdma5.set_bit(7, 1);
+    sys.mem.set_io_reg(IoReg::Hdma5, hdma5);
 }
 
-fn transfer_0x10_bytes(sys: &mut Sys) {
+fn transfer_0x10_bytes<B: Bus>(sys: &mut Sys<B>) {
     for _ in 0..0x10 {
         transfer_one_byte(sys);
 
@@ -130,21 +162,21 @@ fn transfer_0x10_bytes(sys: &mut Sys) {
             hdma.is_active = false;
 
             // Set Hdma5.7 lo to indicate transfer is inactive.
-            sys.mem.io_regs.mut_(IoReg::Hdma5, |hdma5| {
-                hdma5.set_bit(7, 0);
-            });
+            let mut hdma5 = sys.mem.io_reg(IoReg::Hdma5);
+            hdma5.set_bit(7, 0);
+            sys.mem.set_io_reg(IoReg::Hdma5, hdma5);
         }
     }
 }
 
-fn transfer_one_byte(sys: &mut Sys) {
+fn transfer_one_byte<B: Bus>(sys: &mut Sys<B>) {
     let hdma = sys.ppu.vram_dma_mut();
 
     let idx = hdma.next_idx;
     let src_addr = hdma.src_addr + idx;
     let dst_addr = hdma.dst_addr + idx;
 
-    let data = sys.mem.read(src_addr);
+    let data = sys.mem.read_as(src_addr, ReadUse::Dma);
     sys.mem.write(dst_addr, data);
 
     // println!(
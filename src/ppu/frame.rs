@@ -0,0 +1,66 @@
+use macroquad::color::Color;
+use xf::{
+    mq::draw::draw_rect,
+    num::{
+        irect::ir,
+        ivec2::{i2, IVec2},
+    },
+};
+
+use crate::util::state::{SaveState, StateReader, StateWriter};
+
+pub const FRAME_WIDTH: usize = 160;
+pub const FRAME_HEIGHT: usize = 144;
+
+/// The image shown on the LCD, stored as RGBA8 pixels.
+#[derive(Clone)]
+pub struct Frame {
+    pixels: Vec<[u8; 4]>,
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Self {
+            pixels: vec![[0xFF; 4]; FRAME_WIDTH * FRAME_HEIGHT],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> [u8; 4] {
+        self.pixels[y * FRAME_WIDTH + x]
+    }
+
+    /// Sets the pixel at (`x`, `y`). Pixels outside of the LCD are ignored.
+    #[inline]
+    pub fn set(&mut self, x: i32, y: i32, color: Color) {
+        if x < 0 || y < 0 || x >= FRAME_WIDTH as i32 || y >= FRAME_HEIGHT as i32 {
+            return;
+        }
+
+        let idx = (y as usize * FRAME_WIDTH) + x as usize;
+        self.pixels[idx] = color.into();
+    }
+
+    pub fn pixels(&self) -> &[[u8; 4]] {
+        &self.pixels
+    }
+
+    /// Draws the frame to the screen with its top left corner at `org`.
+    pub fn draw(&self, org: IVec2) {
+        for y in 0..FRAME_HEIGHT {
+            for x in 0..FRAME_WIDTH {
+                let color: Color = self.get(x, y).into();
+                draw_rect(ir(org + i2(x as i32, y as i32), i2(1, 1)), color);
+            }
+        }
+    }
+}
+
+impl SaveState for Frame {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bytes(self.pixels.as_flattened());
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        r.read_bytes_into(self.pixels.as_flattened_mut())
+    }
+}
@@ -1,8 +1,9 @@
 mod attrs;
-mod colors;
+pub mod colors;
 pub mod consts;
 mod dma_oam;
 mod dma_vram;
+pub mod frame;
 mod lcdc;
 mod palette;
 pub mod ppu;
@@ -1,22 +1,29 @@
+use macroquad::color::Color;
+use num::FromPrimitive;
+
 use crate::{
     cpu::interrupt::{request_interrupt, InterruptType},
     mem::io_regs::IoReg,
     sys::{speed::is_full_mcycle, Sys},
-    util::bits::Bits,
+    util::{
+        bits::Bits,
+        state::{SaveState, StateReader, StateWriter},
+    },
 };
 
 use super::{
-    colors::Colors,
-    consts::VIEWPORT_ORG,
+    colors::{Colors, DmgPalette},
     dma_oam::{update_oam_dma, DmaOam},
     dma_vram::{update_vram_dma, DmaVram},
+    frame::Frame,
     render::render_scanline,
 };
 
 pub const DOTS_PER_SCANLINE: u32 = 456;
 pub const SCANLINES_PER_FRAME: u8 = 154;
+pub const MCYCLES_PER_FRAME: u32 = DOTS_PER_SCANLINE * (SCANLINES_PER_FRAME as u32) / 4;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, FromPrimitive)]
 pub enum PpuMode {
     HBlank,
     VBlank,
@@ -32,6 +39,8 @@ pub struct Ppu {
     dma: DmaOam,
     hdma: DmaVram,
     colors: Colors,
+    dmg_shades: [Color; 4],
+    frame: Frame,
 }
 
 impl Ppu {
@@ -43,9 +52,21 @@ impl Ppu {
             dma: DmaOam::new(),
             hdma: DmaVram::new(),
             colors: Colors::new(),
+            dmg_shades: DmgPalette::default().shades(),
+            frame: Frame::new(),
         }
     }
 
+    /// Returns the PPU to its power-on state. Display settings are kept.
+    pub fn reset(&mut self) {
+        self.curr_scanline_dot = 0;
+        self.total_frames_drawn = 0;
+        self.mode = PpuMode::HBlank;
+        self.dma = DmaOam::new();
+        self.hdma = DmaVram::new();
+        self.frame = Frame::new();
+    }
+
     pub fn total_frames_drawn(&self) -> u64 {
         self.total_frames_drawn
     }
@@ -65,6 +86,46 @@ impl Ppu {
     pub fn colors(&self) -> &Colors {
         &self.colors
     }
+
+    /// Colors used to display the four DMG shades.
+    pub fn dmg_shades(&self) -> [Color; 4] {
+        self.dmg_shades
+    }
+
+    pub fn set_dmg_palette(&mut self, palette: DmgPalette) {
+        self.dmg_shades = palette.shades();
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    pub fn frame_mut(&mut self) -> &mut Frame {
+        &mut self.frame
+    }
+}
+
+impl SaveState for Ppu {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u32(self.curr_scanline_dot);
+        w.write_u64(self.total_frames_drawn);
+        w.write_u8(self.mode as u8);
+        self.dma.save_state(w);
+        self.hdma.save_state(w);
+        self.frame.save_state(w);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.curr_scanline_dot = r.read_u32()?;
+        self.total_frames_drawn = r.read_u64()?;
+        let mode = r.read_u8()?;
+        self.mode =
+            PpuMode::from_u8(mode).ok_or(format!("Invalid PPU mode in state: {}.", mode))?;
+        self.dma.load_state(r)?;
+        self.hdma.load_state(r)?;
+        self.frame.load_state(r)?;
+        return Ok(());
+    }
 }
 
 /// Advances the PPU state by 1 M-Cycle.
@@ -146,7 +207,9 @@ fn enter_mode(sys: &mut Sys, mode: PpuMode) {
         }
         PpuMode::Draw => {
             let ly = sys.mem.io_regs.get(IoReg::Ly);
-            render_scanline(sys, ly, VIEWPORT_ORG);
+            if !sys.skip_render {
+                render_scanline(sys, ly);
+            }
         }
         _ => {}
     }
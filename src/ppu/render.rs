@@ -1,5 +1,3 @@
-use xf::num::ivec2::{i2, IVec2};
-
 use crate::{
     mem::{io_regs::IoReg, Addr},
     sys::Sys,
@@ -13,10 +11,11 @@ use super::{
     },
     lcdc::LcdcState,
     palette::Palette,
-    render_util::{draw_pixel, get_tile_map_addr, tile_data_idx_to_addr},
+    render_util::{get_tile_map_addr, set_frame_pixel, tile_data_idx_to_addr},
 };
 
-pub fn render_scanline(sys: &mut Sys, ly: u8, org: IVec2) {
+/// Renders scanline `ly` into the PPU's frame.
+pub fn render_scanline(sys: &mut Sys, ly: u8) {
     let lcdc = LcdcState::from(sys);
 
     let scx = sys.mem.io_regs.get(IoReg::Scx);
@@ -25,20 +24,22 @@ pub fn render_scanline(sys: &mut Sys, ly: u8, org: IVec2) {
     let src_y = u8::wrapping_add(ly, scy);
 
     let bgp = Palette::from_reg(sys, IoReg::Bgp);
+    let shades = sys.ppu.dmg_shades();
 
     // Draw background
     if lcdc.bg_window_enable {
         for x in 0..160 {
             let src_x = u8::wrapping_add(scx, x);
             let color_id = sample_pixel_from_bg_tilemap(sys, src_x, src_y);
-            draw_pixel::<false>(i2(x as i32, ly as i32) + org, &bgp, color_id);
+            let frame = sys.ppu.frame_mut();
+            set_frame_pixel::<false>(frame, x as i32, ly as i32, &shades, &bgp, color_id);
         }
     }
 
     // Draw objects
     if lcdc.obj_enable {
         for obj_idx in 0..40u8 {
-            try_draw_obj_row(sys, obj_idx, ly, org);
+            try_draw_obj_row(sys, obj_idx, ly);
         }
     }
 
@@ -46,7 +47,8 @@ pub fn render_scanline(sys: &mut Sys, ly: u8, org: IVec2) {
     if lcdc.bg_window_enable && lcdc.window_enable {
         for x in 0..168 {
             if let Some(color_id) = sample_pixel_from_window_tilemap(sys, x, ly) {
-                draw_pixel::<false>(i2(x as i32 - 7, ly as i32) + org, &bgp, color_id);
+                let frame = sys.ppu.frame_mut();
+                set_frame_pixel::<false>(frame, x as i32 - 7, ly as i32, &shades, &bgp, color_id);
             }
         }
     }
@@ -142,7 +144,7 @@ fn sample_pixel_from_tilemap(
 }
 
 #[inline]
-fn try_draw_obj_row(sys: &Sys, obj_idx: u8, ly: u8, org: IVec2) {
+fn try_draw_obj_row(sys: &mut Sys, obj_idx: u8, ly: u8) {
     let lcdc = LcdcState::from(sys);
 
     let obj_addr = OAM_ADDR_FE00 + (OAM_OBJ_SIZE * obj_idx as Addr);
@@ -175,6 +177,7 @@ fn try_draw_obj_row(sys: &Sys, obj_idx: u8, ly: u8, org: IVec2) {
     };
 
     let palette = Palette::from_reg(sys, palette_reg);
+    let shades = sys.ppu.dmg_shades();
 
     let mut pixel_y = (ly + 16) - y_pos;
     if y_flip {
@@ -204,8 +207,11 @@ fn try_draw_obj_row(sys: &Sys, obj_idx: u8, ly: u8, org: IVec2) {
             .bit(pixel_x_bit);
 
         let color_id = (hi << 1) | lo;
-        draw_pixel::<true>(
-            i2(u8::wrapping_add(x_pos, x) as i32 - 8, ly as i32) + org,
+        set_frame_pixel::<true>(
+            sys.ppu.frame_mut(),
+            u8::wrapping_add(x_pos, x) as i32 - 8,
+            ly as i32,
+            &shades,
             &palette,
             color_id,
         );
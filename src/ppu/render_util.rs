@@ -14,6 +14,7 @@ use super::{
         TILE_DATA_ADDR_8000, TILE_DATA_ADDR_8800, TILE_DATA_ADDR_9000, TILE_DATA_TILE_SIZE,
         TILE_MAP_ADDR_9800, TILE_MAP_ADDR_9C00,
     },
+    frame::Frame,
     palette::Palette,
 };
 
@@ -55,6 +56,22 @@ pub fn draw_pixel<const TRANSPARENT: bool>(pos: IVec2, palette: &Palette, color_
     draw_rect(ir(pos, i2(1, 1)), color);
 }
 
+#[inline]
+pub fn set_frame_pixel<const TRANSPARENT: bool>(
+    frame: &mut Frame,
+    x: i32,
+    y: i32,
+    shades: &[Color; 4],
+    palette: &Palette,
+    color_id: u8,
+) {
+    if TRANSPARENT && (color_id == 0) {
+        return;
+    }
+    let color = shades[palette.map(color_id) as usize];
+    frame.set(x, y, color);
+}
+
 #[inline]
 pub fn draw_pixel_c_bg(sys: &Sys, pos: IVec2, palette_id: u8, color_id: u8) {
     let color_idx = sys.mem.io_regs.bg_cram().get(palette_id, color_id);
@@ -1,4 +1,4 @@
-use macroquad::color::BLACK;
+use macroquad::color::{BLACK, DARKBLUE, DARKGRAY, MAROON};
 use xf::{
     mq::draw::draw_rect,
     num::{
@@ -7,12 +7,23 @@ use xf::{
     },
 };
 
-use crate::{consts::P8, other::joypad::draw_joypad_state, sys::Sys};
+use crate::{
+    consts::P8,
+    cpu::disasm::disassemble_around,
+    debugger::{
+        mem_viewer::{BankedSection, Input, RecentWrites, ROW_LEN, VIEW_ROWS},
+        symbols::{label_at, mapped_bank},
+    },
+    mem::Addr,
+    other::joypad::draw_joypad_state,
+    sys::Sys,
+};
 
 use super::{
     consts::{
-        JOYPAD_ORG, PALETTES_ORG, TILE_DATA_BLOCK_DRAW_P8_SIZE, TILE_DATA_BLOCK_DRAW_SIZE,
-        TILE_DATA_ORG, TILE_MAP_ORG, VIEWPORT_P8_SIZE,
+        DISASM_ORG, DISASM_P8_SIZE, JOYPAD_ORG, LINKED_VIEWPORT_ORG, MEM_VIEW_ORG, PALETTES_ORG,
+        TILE_DATA_BLOCK_DRAW_P8_SIZE, TILE_DATA_BLOCK_DRAW_SIZE, TILE_DATA_ORG, TILE_MAP_ORG,
+        VIEWPORT_ORG, VIEWPORT_P8_SIZE,
     },
     lcdc::LcdcState,
     render_mem::{draw_palettes, render_scroll_view_area, render_tile_data_block, render_tile_map},
@@ -44,9 +55,10 @@ pub fn render_ui(sys: &mut Sys) {
     );
     let game_title = sys.mem.cart.header().title();
     draw_text(game_title, i2(1, 0) * P8);
+    sys.ppu.frame().draw(VIEWPORT_ORG);
 
     // Joypad.
-    draw_joypad_state(JOYPAD_ORG);
+    draw_joypad_state(sys, JOYPAD_ORG);
 
     if !sys.options.show_vram_views {
         return;
@@ -102,4 +114,131 @@ pub fn render_ui(sys: &mut Sys) {
     // Palettes view.
     draw_text("PALETTES", PALETTES_ORG - i2(0, 8));
     draw_palettes(sys, PALETTES_ORG);
+
+    // Disassembly view.
+    draw_text("CODE", DISASM_ORG - i2(0, 8));
+    render_disassembly(sys, DISASM_ORG);
+
+    // Memory view. Writes are only tracked while it's shown, and highlights
+    // don't fade while paused.
+    let recent_writes = sys.mem.recent_writes.get_or_insert_with(RecentWrites::new);
+    if !sys.emu.is_paused {
+        recent_writes.next_frame();
+    }
+    draw_text(mem_view_title(sys), MEM_VIEW_ORG - i2(0, 8));
+    render_mem_view(sys, MEM_VIEW_ORG);
+}
+
+/// Shows the instructions around PC, with PC's highlighted.
+fn render_disassembly(sys: &Sys, org: IVec2) {
+    let pc = sys.regs.pc();
+    let before = (DISASM_P8_SIZE.y / 2) as usize;
+    let after = DISASM_P8_SIZE.y as usize - before;
+    let read = |addr: Addr| sys.mem.peek(addr);
+    let lines = disassemble_around(pc, before, after, read, |addr| label_at(sys, addr));
+
+    for (row, (addr, disasm)) in lines.iter().enumerate() {
+        let line_org = org + i2(0, row as i32) * P8;
+        if *addr == pc {
+            draw_rect(ir(line_org, i2(DISASM_P8_SIZE.x, 1) * P8), DARKGRAY);
+        }
+
+        let line = format!("{:04X} {}", addr, disasm.text);
+        let line = line
+            .chars()
+            .take(DISASM_P8_SIZE.x as usize)
+            .collect::<String>();
+        draw_text(line, line_org);
+    }
+}
+
+/// The cursor's address, with the bank shown and its label if it has one.
+fn mem_view_title(sys: &Sys) -> String {
+    let viewer = &sys.debugger.mem_viewer;
+    let cursor = viewer.cursor();
+    let mut title = format!("MEMORY ${:04X}", cursor);
+    if let Some(section) = BankedSection::at(cursor) {
+        let bank = match (viewer.bank(section), mapped_bank(sys, cursor)) {
+            (Some(bank), _) => format!("BK{:X}", bank),
+            (None, Some(mapped)) => format!("BK{:X} MAPPED", mapped),
+            (None, None) => "MAPPED".to_owned(),
+        };
+        title += &format!(" {} {}", section.name(), bank);
+    }
+    let bank = viewer.bank_at(cursor).or(mapped_bank(sys, cursor));
+    if let Some(label) = sys.debugger.symbols.label(cursor, bank) {
+        title += &format!(" {}", label);
+    }
+    return title;
+}
+
+/// Shows a page of memory as hex and ASCII, with the cursor and recent
+/// writes highlighted, then what's being typed.
+fn render_mem_view(sys: &Sys, org: IVec2) {
+    let viewer = &sys.debugger.mem_viewer;
+    let is_recent_write = |addr: Addr| {
+        let is_mapped = viewer
+            .bank_at(addr)
+            .is_none_or(|bank| mapped_bank(sys, addr) == Some(bank));
+        let recent_writes = sys.mem.recent_writes.as_ref();
+        return is_mapped && recent_writes.is_some_and(|writes| writes.is_recent(addr));
+    };
+
+    for row in 0..VIEW_ROWS {
+        let row_addr = viewer.top() + (row * ROW_LEN) as Addr;
+        let row_org = org + i2(0, row as i32) * P8;
+        let mut hex = String::new();
+        let mut ascii = String::new();
+        for col in 0..ROW_LEN {
+            let addr = row_addr + col as Addr;
+            let data = sys.mem.peek_banked(addr, viewer.bank_at(addr));
+            hex += &format!(" {:02X}", data);
+            ascii.push(if data.is_ascii_graphic() {
+                data as char
+            } else {
+                '.'
+            });
+
+            let byte_rect = ir(row_org + i2(5 + 3 * col as i32, 0) * P8, i2(2, 1) * P8);
+            if addr == viewer.cursor() {
+                let color = if viewer.is_editing() {
+                    DARKBLUE
+                } else {
+                    DARKGRAY
+                };
+                draw_rect(byte_rect, color);
+            } else if is_recent_write(addr) {
+                draw_rect(byte_rect, MAROON);
+            }
+        }
+        draw_text(format!("{:04X}{}  {}", row_addr, hex, ascii), row_org);
+    }
+
+    let status = match (viewer.input(), &viewer.error) {
+        (Some(Input::Goto(text)), _) => format!("GOTO: {}", text),
+        (Some(Input::Edit(Some(high))), _) => format!("EDIT: {:X}", high),
+        (Some(Input::Edit(None)), _) => "EDIT: TYPE HEX, ARROWS MOVE".to_owned(),
+        (None, Some(err)) => err.clone(),
+        (None, None) => "G GOTO  B BANK  E EDIT WHILE PAUSED  PGUP/PGDN SCROLL".to_owned(),
+    };
+    draw_text(status, org + i2(0, VIEW_ROWS as i32) * P8);
+}
+
+/// Shows the speed and the measured emulated frame rate below the viewport.
+pub fn render_speed(sys: &Sys, measured_fps: f64) {
+    let org = i2(1, VIEWPORT_P8_SIZE.y + 1) * P8;
+    if sys.emu.is_paused {
+        draw_text("PAUSED", org);
+        return;
+    }
+
+    let speed = sys.emu.speed().label();
+    draw_text(format!("{} FPS {:.0}", speed, measured_fps), org);
+}
+
+/// Renders the screen of a second, linked system next to the main viewport.
+pub fn render_linked_viewport(peer: &Sys) {
+    let game_title = peer.mem.cart.header().title();
+    draw_text(game_title, LINKED_VIEWPORT_ORG - i2(0, 8));
+    peer.ppu.frame().draw(LINKED_VIEWPORT_ORG);
 }
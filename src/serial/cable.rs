@@ -0,0 +1,173 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::sys::Sys;
+
+use super::{device::SerialDevice, serial::DISCONNECTED_DATA};
+
+/// State shared by both ends of a virtual link cable.
+struct CableState {
+    /// The byte each end has ready for a transfer clocked by the other end.
+    ready: [Option<u8>; 2],
+
+    /// The byte clocked into each end by the other end, not yet picked up.
+    inbox: [Option<u8>; 2],
+}
+
+/// One end of a virtual link cable between two emulated systems
+/// running in the same process.
+pub struct LinkPort {
+    side: usize,
+    state: Rc<RefCell<CableState>>,
+}
+
+impl LinkPort {
+    /// Creates both ends of a new link cable.
+    pub fn pair() -> (Self, Self) {
+        let state = Rc::new(RefCell::new(CableState {
+            ready: [None; 2],
+            inbox: [None; 2],
+        }));
+
+        let a = Self {
+            side: 0,
+            state: state.clone(),
+        };
+        let b = Self { side: 1, state };
+
+        return (a, b);
+    }
+
+    fn peer(&self) -> usize {
+        1 - self.side
+    }
+}
+
+impl SerialDevice for LinkPort {
+    fn transfer_internal(&mut self, data: u8) -> Option<u8> {
+        let peer = self.peer();
+        let mut state = self.state.borrow_mut();
+
+        // Nothing is shifted in unless the other end is waiting for a transfer.
+        let Some(peer_data) = state.ready[peer].take() else {
+            return Some(DISCONNECTED_DATA);
+        };
+        state.inbox[peer] = Some(data);
+
+        return Some(peer_data);
+    }
+
+    fn poll_external(&mut self, ready: Option<u8>) -> Option<u8> {
+        let side = self.side;
+        let mut state = self.state.borrow_mut();
+
+        if let Some(data) = state.inbox[side].take() {
+            state.ready[side] = None;
+            return Some(data);
+        }
+
+        state.ready[side] = ready;
+        return None;
+    }
+}
+
+/// Connects two systems with a virtual link cable.
+pub fn connect_link_cable(a: &mut Sys, b: &mut Sys) {
+    let (port_a, port_b) = LinkPort::pair();
+    a.serial.connect(Box::new(port_a));
+    b.serial.connect(Box::new(port_b));
+}
+
+/// Advances two linked systems by one M-Cycle each. The systems are always
+/// stepped in the same order so that byte exchanges are deterministic.
+pub fn run_linked_m_cycle(a: &mut Sys, b: &mut Sys) {
+    a.run_one_m_cycle();
+    b.run_one_m_cycle();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        cpu::regs::CpuReg16,
+        mem::io_regs::IoReg,
+        test::headless::{create_headless_sys, lock_headless},
+        util::bits::Bits,
+    };
+
+    use super::*;
+
+    fn create_looping_sys() -> Sys {
+        let rom = Path::new("assets/files/custom_roms/ld_r8_r8/rom.gb");
+        let mut sys = create_headless_sys(rom, None).unwrap();
+
+        // jr -2, so the CPU leaves the serial registers alone.
+        sys.mem.poke(0x0100, 0x18);
+        sys.mem.poke(0x0101, 0xFE);
+        sys.regs.set_16(CpuReg16::PC, 0x0100);
+        return sys;
+    }
+
+    /// Has A clock 0x17 to B, which is waiting with 0x42. Returns the
+    /// M-cycle each side's transfer completed on.
+    fn run_exchange() -> [Option<u32>; 2] {
+        let mut a = create_looping_sys();
+        let mut b = create_looping_sys();
+        connect_link_cable(&mut a, &mut b);
+
+        b.mem.io_regs.set(IoReg::Sb, 0x42);
+        b.mem.io_regs.set(IoReg::Sc, 0x80);
+        a.mem.io_regs.set(IoReg::Sb, 0x17);
+        a.mem.io_regs.set(IoReg::Sc, 0x81);
+
+        let mut completed = [None; 2];
+        for mcycle in 0..2048 {
+            run_linked_m_cycle(&mut a, &mut b);
+            for (completed, sys) in completed.iter_mut().zip([&a, &b]) {
+                if completed.is_none() && sys.mem.io_regs.get(IoReg::Sc).bit(7) == 0 {
+                    *completed = Some(mcycle);
+                }
+            }
+        }
+
+        assert_eq!(a.mem.io_regs.get(IoReg::Sb), 0x42);
+        assert_eq!(b.mem.io_regs.get(IoReg::Sb), 0x17);
+        return completed;
+    }
+
+    #[test]
+    fn test_linked_systems() {
+        let _lock = lock_headless();
+
+        // B picks up the byte in the same M-cycle A finishes shifting it.
+        let completed = run_exchange();
+        assert!(completed[0].is_some());
+        assert_eq!(completed[0], completed[1]);
+
+        assert_eq!(run_exchange(), completed);
+    }
+
+    #[test]
+    fn test_exchange() {
+        let (mut a, mut b) = LinkPort::pair();
+
+        // B waits for a transfer with 0x42 in SB.
+        assert_eq!(b.poll_external(Some(0x42)), None);
+
+        // A clocks a transfer and receives B's byte.
+        assert_eq!(a.transfer_internal(0x17), Some(0x42));
+
+        // B picks up A's byte on its next poll.
+        assert_eq!(b.poll_external(Some(0x42)), Some(0x17));
+        assert_eq!(b.poll_external(None), None);
+    }
+
+    #[test]
+    fn test_peer_not_ready() {
+        let (mut a, mut b) = LinkPort::pair();
+
+        assert_eq!(b.poll_external(None), None);
+        assert_eq!(a.transfer_internal(0x17), Some(DISCONNECTED_DATA));
+        assert_eq!(b.poll_external(Some(0x42)), None);
+    }
+}
@@ -0,0 +1,21 @@
+use super::serial::DISCONNECTED_DATA;
+
+/// Something that can be plugged into the Game Boy's serial port
+/// (another Game Boy, a printer, etc.).
+pub trait SerialDevice {
+    /// Called when the Game Boy finishes shifting out `data` using its
+    /// internal clock. Returns the byte that was shifted in from the device,
+    /// or `None` if the device hasn't replied yet.
+    fn transfer_internal(&mut self, data: u8) -> Option<u8>;
+
+    /// Called every M-cycle after `transfer_internal` returned `None`, until
+    /// it returns the byte that was shifted in.
+    fn poll_internal(&mut self) -> Option<u8> {
+        Some(DISCONNECTED_DATA)
+    }
+
+    /// Called every M-cycle. `ready` contains SB while the Game Boy is waiting
+    /// for a transfer clocked by the device (external clock), or `None` otherwise.
+    /// Returns the byte that was shifted in if the device clocked a transfer.
+    fn poll_external(&mut self, ready: Option<u8>) -> Option<u8>;
+}
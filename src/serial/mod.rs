@@ -0,0 +1,5 @@
+pub mod cable;
+pub mod device;
+pub mod printer;
+pub mod serial;
+pub mod socket;
@@ -0,0 +1,424 @@
+use std::path::PathBuf;
+
+use crate::util::{bits::Bits, image::save_png};
+
+use super::device::SerialDevice;
+
+const MAGIC_1: u8 = 0x88;
+const MAGIC_2: u8 = 0x33;
+
+/// Sent in reply to the first byte after a packet's checksum.
+const DEVICE_ID: u8 = 0x81;
+
+const CMD_INIT: u8 = 0x01;
+const CMD_PRINT: u8 = 0x02;
+const CMD_DATA: u8 = 0x04;
+const CMD_STATUS: u8 = 0x0F;
+
+const STATUS_CHECKSUM_ERROR: u8 = 0;
+const STATUS_PRINTING: u8 = 1;
+const STATUS_IMAGE_DATA_FULL: u8 = 2;
+const STATUS_UNPROCESSED_DATA: u8 = 3;
+
+/// Number of status requests that report the printer as busy after printing.
+const PRINT_BUSY_STATUS_COUNT: u32 = 4;
+
+/// Each data packet holds 2 rows of 20 tiles.
+const STRIP_WIDTH: usize = 160;
+const TILE_SIZE: usize = 16;
+const TILES_PER_ROW: usize = STRIP_WIDTH / 8;
+
+/// The printer holds at most 9 full data packets (144 pixel rows).
+const IMAGE_DATA_CAPACITY: usize = 9 * 2 * TILES_PER_ROW * TILE_SIZE;
+
+const SHADES: [[u8; 4]; 4] = [
+    [0xFF, 0xFF, 0xFF, 0xFF],
+    [0xAA, 0xAA, 0xAA, 0xFF],
+    [0x55, 0x55, 0x55, 0xFF],
+    [0x00, 0x00, 0x00, 0xFF],
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PacketState {
+    Magic1,
+    Magic2,
+    Command,
+    Compression,
+    LenLo,
+    LenHi,
+    Data,
+    ChecksumLo,
+    ChecksumHi,
+    DeviceId,
+    Status,
+}
+
+/// Game Boy Printer connected to the serial port. Printouts are
+/// assembled from the printed strips and saved as PNG files.
+pub struct Printer {
+    save_dir: Option<PathBuf>,
+    save_count: usize,
+
+    state: PacketState,
+    command: u8,
+    is_compressed: bool,
+    data_len: u16,
+    data: Vec<u8>,
+    checksum: u16,
+    received_checksum: u16,
+
+    status: u8,
+    busy_status_count: u32,
+
+    /// Decompressed tile data received since the last print.
+    image_data: Vec<u8>,
+
+    /// Pixel rows (as shades 0-3) of the printout in progress.
+    printout: Vec<[u8; STRIP_WIDTH]>,
+}
+
+impl Printer {
+    /// Creates a printer that saves its printouts in `save_dir`, if specified.
+    pub fn new(save_dir: Option<PathBuf>) -> Self {
+        Self {
+            save_dir,
+            save_count: 0,
+
+            state: PacketState::Magic1,
+            command: 0,
+            is_compressed: false,
+            data_len: 0,
+            data: vec![],
+            checksum: 0,
+            received_checksum: 0,
+
+            status: 0,
+            busy_status_count: 0,
+
+            image_data: vec![],
+            printout: vec![],
+        }
+    }
+
+    fn receive(&mut self, byte: u8) -> u8 {
+        use PacketState::*;
+
+        let mut reply = 0x00;
+
+        match self.state {
+            Magic1 => {
+                if byte == MAGIC_1 {
+                    self.state = Magic2;
+                }
+            }
+            Magic2 => {
+                self.state = if byte == MAGIC_2 { Command } else { Magic1 };
+            }
+            Command => {
+                self.command = byte;
+                self.checksum = byte as u16;
+                self.state = Compression;
+            }
+            Compression => {
+                self.is_compressed = byte.bit(0) == 1;
+                self.add_to_checksum(byte);
+                self.state = LenLo;
+            }
+            LenLo => {
+                self.data_len = byte as u16;
+                self.add_to_checksum(byte);
+                self.state = LenHi;
+            }
+            LenHi => {
+                self.data_len |= (byte as u16) << 8;
+                self.add_to_checksum(byte);
+                self.data.clear();
+                self.state = if self.data_len > 0 { Data } else { ChecksumLo };
+            }
+            Data => {
+                self.data.push(byte);
+                self.add_to_checksum(byte);
+                if self.data.len() >= self.data_len as usize {
+                    self.state = ChecksumLo;
+                }
+            }
+            ChecksumLo => {
+                self.received_checksum = byte as u16;
+                self.state = ChecksumHi;
+            }
+            ChecksumHi => {
+                self.received_checksum |= (byte as u16) << 8;
+                self.state = DeviceId;
+            }
+            DeviceId => {
+                reply = DEVICE_ID;
+                self.run_command();
+                self.state = Status;
+            }
+            Status => {
+                reply = self.take_status();
+                self.state = Magic1;
+            }
+        }
+
+        return reply;
+    }
+
+    fn add_to_checksum(&mut self, byte: u8) {
+        self.checksum = u16::wrapping_add(self.checksum, byte as u16);
+    }
+
+    fn run_command(&mut self) {
+        if self.checksum != self.received_checksum {
+            self.status.set_bit(STATUS_CHECKSUM_ERROR, 1);
+            return;
+        }
+        self.status.set_bit(STATUS_CHECKSUM_ERROR, 0);
+
+        match self.command {
+            CMD_INIT => {
+                self.image_data.clear();
+                self.status = 0;
+                self.busy_status_count = 0;
+            }
+            CMD_PRINT => {
+                self.print();
+            }
+            CMD_DATA => {
+                if self.is_compressed {
+                    let data = decompress(&self.data);
+                    self.image_data.extend(data);
+                } else {
+                    self.image_data.extend_from_slice(&self.data);
+                }
+                self.image_data.truncate(IMAGE_DATA_CAPACITY);
+
+                let is_full = self.image_data.len() >= IMAGE_DATA_CAPACITY;
+                self.status.set_bit(STATUS_IMAGE_DATA_FULL, is_full.into());
+                let has_data = !self.image_data.is_empty();
+                self.status
+                    .set_bit(STATUS_UNPROCESSED_DATA, has_data.into());
+            }
+            CMD_STATUS => {}
+            _ => {
+                println!("Printer: unknown command {:0>2X}", self.command);
+            }
+        }
+    }
+
+    fn take_status(&mut self) -> u8 {
+        let status = self.status;
+
+        if self.busy_status_count > 0 {
+            self.busy_status_count -= 1;
+            if self.busy_status_count == 0 {
+                self.status.set_bit(STATUS_PRINTING, 0);
+            }
+        }
+
+        return status;
+    }
+
+    /// Prints the received image data. The print command's data is
+    /// [sheet count, margins, palette, exposure].
+    fn print(&mut self) {
+        let (margins, palette) = match self.data.as_slice() {
+            [_, margins, palette, ..] => (*margins, *palette),
+            _ => (0x00, 0x00),
+        };
+        let margin_before = margins.bits(7, 4);
+        let margin_after = margins.bits(3, 0);
+
+        // A palette of 0 is treated as the identity palette.
+        let palette = if palette == 0 { 0xE4 } else { palette };
+
+        if margin_before > 0 && !self.printout.is_empty() {
+            self.finish_printout();
+        }
+
+        let rows = decode_strips(&self.image_data, palette);
+        self.printout.extend(rows);
+        self.image_data.clear();
+
+        if margin_after > 0 {
+            self.finish_printout();
+        }
+
+        self.status.set_bit(STATUS_UNPROCESSED_DATA, 0);
+        self.status.set_bit(STATUS_IMAGE_DATA_FULL, 0);
+        self.status.set_bit(STATUS_PRINTING, 1);
+        self.busy_status_count = PRINT_BUSY_STATUS_COUNT;
+    }
+
+    /// Feeds the current printout out of the printer and saves it.
+    fn finish_printout(&mut self) {
+        let printout = std::mem::replace(&mut self.printout, vec![]);
+        if printout.is_empty() {
+            return;
+        }
+
+        if let Some(save_dir) = &self.save_dir {
+            self.save_count += 1;
+            let path = save_dir.join(format!("printout_{:0>3}.png", self.save_count));
+            let pixels = printout
+                .iter()
+                .flat_map(|row| row.iter().map(|shade| SHADES[*shade as usize]))
+                .collect::<Vec<_>>();
+
+            match save_png(&path, STRIP_WIDTH, printout.len(), &pixels) {
+                Ok(()) => println!("Printed to: {:?}", path),
+                Err(err) => println!("{}", err),
+            }
+        }
+    }
+}
+
+impl SerialDevice for Printer {
+    fn transfer_internal(&mut self, data: u8) -> Option<u8> {
+        Some(self.receive(data))
+    }
+
+    fn poll_external(&mut self, _: Option<u8>) -> Option<u8> {
+        // The printer never clocks transfers itself.
+        None
+    }
+}
+
+impl Drop for Printer {
+    fn drop(&mut self) {
+        self.finish_printout();
+    }
+}
+
+/// Decompresses run-length encoded packet data.
+fn decompress(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut i = 0;
+
+    while i < data.len() {
+        let ctrl = data[i];
+        i += 1;
+
+        if ctrl.bit(7) == 1 {
+            // Run of one repeated byte.
+            let len = (ctrl.bits(6, 0) as usize) + 2;
+            let Some(byte) = data.get(i) else {
+                break;
+            };
+            out.extend(std::iter::repeat(*byte).take(len));
+            i += 1;
+        } else {
+            // Run of literal bytes.
+            let len = (ctrl as usize) + 1;
+            let end = usize::min(i + len, data.len());
+            out.extend_from_slice(&data[i..end]);
+            i = end;
+        }
+    }
+
+    return out;
+}
+
+/// Converts tile data into rows of shades. Tiles are stored
+/// 20 per row, in the same format as VRAM tile data.
+fn decode_strips(data: &[u8], palette: u8) -> Vec<[u8; STRIP_WIDTH]> {
+    let tile_rows = data.len() / (TILES_PER_ROW * TILE_SIZE);
+    let mut rows = vec![[0; STRIP_WIDTH]; tile_rows * 8];
+
+    for tile_row in 0..tile_rows {
+        for tile_col in 0..TILES_PER_ROW {
+            let tile_idx = tile_row * TILES_PER_ROW + tile_col;
+            let tile = &data[(tile_idx * TILE_SIZE)..((tile_idx + 1) * TILE_SIZE)];
+
+            for y in 0..8 {
+                let lo = tile[y * 2];
+                let hi = tile[y * 2 + 1];
+                for x in 0..8 {
+                    let bit = 7 - x as u8;
+                    let color_id = (hi.bit(bit) << 1) | lo.bit(bit);
+                    let shade = (palette >> (color_id * 2)) & 0b11;
+                    rows[tile_row * 8 + y][tile_col * 8 + x] = shade;
+                }
+            }
+        }
+    }
+
+    return rows;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use macroquad::{prelude::ImageFormat, texture::Image};
+
+    use super::*;
+
+    fn send_packet(printer: &mut Printer, command: u8, data: &[u8]) -> (u8, u8) {
+        let len = data.len() as u16;
+        let mut bytes = vec![MAGIC_1, MAGIC_2, command, 0x00, len as u8, (len >> 8) as u8];
+        bytes.extend_from_slice(data);
+
+        let checksum = bytes[2..]
+            .iter()
+            .fold(0u16, |sum, b| u16::wrapping_add(sum, *b as u16));
+        bytes.push(checksum as u8);
+        bytes.push((checksum >> 8) as u8);
+
+        for byte in bytes {
+            assert_eq!(printer.receive(byte), 0x00);
+        }
+        let device_id = printer.receive(0x00);
+        let status = printer.receive(0x00);
+
+        return (device_id, status);
+    }
+
+    #[test]
+    fn test_decompress() {
+        let data = [0x82, 0xAB, 0x01, 0x12, 0x34, 0x80, 0xFF];
+        let out = decompress(&data);
+        assert_eq!(out, vec![0xAB, 0xAB, 0xAB, 0xAB, 0x12, 0x34, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn test_print() {
+        let dir = env::temp_dir().join(format!("rust_cgb_emu_printer_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut printer = Printer::new(Some(dir.clone()));
+
+        let (device_id, status) = send_packet(&mut printer, CMD_INIT, &[]);
+        assert_eq!(device_id, DEVICE_ID);
+        assert_eq!(status, 0x00);
+
+        // One strip of black tiles.
+        let data = vec![0xFF; 2 * TILES_PER_ROW * TILE_SIZE];
+        let (_, status) = send_packet(&mut printer, CMD_DATA, &data);
+        assert_eq!(status.bit(STATUS_UNPROCESSED_DATA), 1);
+
+        let (_, status) = send_packet(&mut printer, CMD_PRINT, &[0x01, 0x03, 0xE4, 0x40]);
+        assert_eq!(status.bit(STATUS_PRINTING), 1);
+
+        let bytes = fs::read(dir.join("printout_001.png")).unwrap();
+        let image = Image::from_file_with_format(&bytes, Some(ImageFormat::Png)).unwrap();
+        assert_eq!(
+            (image.width as usize, image.height as usize),
+            (STRIP_WIDTH, 16)
+        );
+        assert!(image.bytes.chunks_exact(4).all(|pixel| pixel == SHADES[3]));
+        assert!(!dir.join("printout_002.png").exists());
+    }
+
+    #[test]
+    fn test_checksum_error() {
+        let mut printer = Printer::new(None);
+
+        for byte in [MAGIC_1, MAGIC_2, CMD_STATUS, 0x00, 0x00, 0x00, 0x00, 0x00] {
+            printer.receive(byte);
+        }
+        let _ = printer.receive(0x00);
+        let status = printer.receive(0x00);
+        assert_eq!(status.bit(STATUS_CHECKSUM_ERROR), 1);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    cpu::interrupt::{request_interrupt, InterruptType},
+    mem::io_regs::IoReg,
+    sys::Sys,
+    util::{
+        bits::Bits,
+        state::{SaveState, StateReader, StateWriter},
+    },
+};
+
+use super::device::SerialDevice;
+
+/// M-cycles needed to shift one bit using the internal clock (8192 Hz).
+const BIT_PERIOD_MCYCLES: u32 = 128;
+
+/// M-cycles needed to shift one bit using the CGB fast internal clock (262144 Hz).
+const FAST_BIT_PERIOD_MCYCLES: u32 = 4;
+
+/// The byte shifted in when nothing is connected to the serial port.
+pub const DISCONNECTED_DATA: u8 = 0xFF;
+
+/// Represents the serial port state.
+pub struct Serial {
+    device: Option<Box<dyn SerialDevice>>,
+    is_active: bool,
+    mcycles_left: u32,
+}
+
+impl Serial {
+    pub fn new() -> Self {
+        Self {
+            device: None,
+            is_active: false,
+            mcycles_left: 0,
+        }
+    }
+
+    /// Stops any transfer in progress. The connected device stays plugged in.
+    pub fn reset(&mut self) {
+        self.is_active = false;
+        self.mcycles_left = 0;
+    }
+
+    /// Plugs `device` into the serial port, replacing any previous device.
+    pub fn connect(&mut self, device: Box<dyn SerialDevice>) {
+        self.device = Some(device);
+    }
+
+    /// Unplugs the current device from the serial port.
+    pub fn disconnect(&mut self) -> Option<Box<dyn SerialDevice>> {
+        self.device.take()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.device.is_some()
+    }
+}
+
+impl SaveState for Serial {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_bool(self.is_active);
+        w.write_u32(self.mcycles_left);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.is_active = r.read_bool()?;
+        self.mcycles_left = r.read_u32()?;
+        return Ok(());
+    }
+}
+
+/// Advances the serial port state by one M-Cycle.
+pub fn update_serial(sys: &mut Sys) {
+    let sc = sys.mem.io_regs.get(IoReg::Sc);
+    let transfer_enable = sc.bit(7) == 1;
+    let is_internal_clock = sc.bit(0) == 1;
+
+    if !transfer_enable || !is_internal_clock {
+        sys.serial.is_active = false;
+
+        // Let the device clock a transfer in, or reply to one that
+        // we aren't ready for.
+        let ready = if transfer_enable {
+            Some(sys.mem.io_regs.get(IoReg::Sb))
+        } else {
+            None
+        };
+        let Some(device) = &mut sys.serial.device else {
+            return;
+        };
+        if let Some(data) = device.poll_external(ready) {
+            if transfer_enable {
+                complete_transfer(sys, data);
+            }
+        }
+        return;
+    }
+
+    if !sys.serial.is_active {
+        let is_fast_clock = sys.is_cgb_mode() && sc.bit(1) == 1;
+        let bit_period = if is_fast_clock {
+            FAST_BIT_PERIOD_MCYCLES
+        } else {
+            BIT_PERIOD_MCYCLES
+        };
+
+        sys.serial.is_active = true;
+        sys.serial.mcycles_left = 8 * bit_period;
+    }
+
+    // Once all bits are shifted, the transfer waits for the device's reply.
+    let received = if sys.serial.mcycles_left > 0 {
+        sys.serial.mcycles_left -= 1;
+        if sys.serial.mcycles_left > 0 {
+            return;
+        }
+
+        let data = sys.mem.io_regs.get(IoReg::Sb);
+        match &mut sys.serial.device {
+            Some(device) => device.transfer_internal(data),
+            None => Some(DISCONNECTED_DATA),
+        }
+    } else {
+        match &mut sys.serial.device {
+            Some(device) => device.poll_internal(),
+            None => Some(DISCONNECTED_DATA),
+        }
+    };
+
+    if let Some(received) = received {
+        complete_transfer(sys, received);
+    }
+}
+
+fn complete_transfer(sys: &mut Sys, received: u8) {
+    sys.serial.is_active = false;
+    sys.mem.io_regs.set(IoReg::Sb, received);
+    sys.mem.io_regs.mut_(IoReg::Sc, |sc| {
+        sc.set_bit(7, 0);
+    });
+
+    request_interrupt(sys, InterruptType::Serial);
+}
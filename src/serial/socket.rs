@@ -0,0 +1,258 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use crate::ppu::ppu::MCYCLES_PER_FRAME;
+
+use super::{device::SerialDevice, serial::DISCONNECTED_DATA};
+
+/// Prefix used to select a Unix domain socket instead of loopback TCP.
+pub const UNIX_SOCKET_PREFIX: &str = "unix:";
+
+/// How many M-cycles the clocking side waits for the other process to reply
+/// before shifting in `DISCONNECTED_DATA`. The other process only runs once
+/// per frame, so this covers a few of them.
+const REPLY_TIMEOUT_MCYCLES: u32 = 8 * MCYCLES_PER_FRAME;
+
+/// The socket is only checked for incoming transfers every this many M-cycles.
+const POLL_PERIOD_MCYCLES: u32 = 64;
+
+/// Sent by the side that clocks a transfer, followed by its SB.
+const MSG_CLOCK: u8 = 0x01;
+
+/// Sent in reply to `MSG_CLOCK`, followed by the replying side's SB.
+const MSG_REPLY: u8 = 0x02;
+
+/// Operations needed from either kind of local socket.
+trait LinkStream: Read + Write {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+}
+
+impl LinkStream for TcpStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+#[cfg(unix)]
+impl LinkStream for UnixStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        UnixStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+/// A link cable to another emulator process on the same machine, over
+/// loopback TCP (`127.0.0.1:port`) or a Unix domain socket (`unix:path`).
+///
+/// Unlike `LinkPort`, the two processes aren't stepped in lockstep, so
+/// the timing of transfers between them isn't deterministic.
+pub struct SocketLink {
+    stream: Box<dyn LinkStream>,
+    rx: Vec<u8>,
+    mcycles_since_poll: u32,
+
+    /// M-cycles spent waiting for the reply to a transfer we clocked.
+    reply_wait_mcycles: Option<u32>,
+}
+
+impl SocketLink {
+    /// Waits for another process to connect at `addr`.
+    pub fn listen(addr: &str) -> io::Result<Self> {
+        println!("Waiting for link connection on {}...", addr);
+
+        let stream: Box<dyn LinkStream> = match addr.strip_prefix(UNIX_SOCKET_PREFIX) {
+            #[cfg(unix)]
+            Some(path) => {
+                let _ = std::fs::remove_file(path);
+                let listener = UnixListener::bind(path)?;
+                Box::new(listener.accept()?.0)
+            }
+            #[cfg(not(unix))]
+            Some(_) => return Err(unix_unsupported()),
+            None => {
+                let listener = TcpListener::bind(addr)?;
+                let (stream, _) = listener.accept()?;
+                stream.set_nodelay(true)?;
+                Box::new(stream)
+            }
+        };
+
+        return Self::new(stream);
+    }
+
+    /// Connects to another process listening at `addr`.
+    pub fn connect(addr: &str) -> io::Result<Self> {
+        let stream: Box<dyn LinkStream> = match addr.strip_prefix(UNIX_SOCKET_PREFIX) {
+            #[cfg(unix)]
+            Some(path) => Box::new(UnixStream::connect(path)?),
+            #[cfg(not(unix))]
+            Some(_) => return Err(unix_unsupported()),
+            None => {
+                let stream = TcpStream::connect(addr)?;
+                stream.set_nodelay(true)?;
+                Box::new(stream)
+            }
+        };
+
+        return Self::new(stream);
+    }
+
+    fn new(stream: Box<dyn LinkStream>) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        println!("Link connected.");
+
+        Ok(Self {
+            stream,
+            rx: vec![],
+            mcycles_since_poll: 0,
+            reply_wait_mcycles: None,
+        })
+    }
+
+    fn send(&mut self, kind: u8, data: u8) {
+        if let Err(err) = self.stream.write_all(&[kind, data]) {
+            println!("Link send failed: {}", err);
+        }
+    }
+
+    /// Returns the next message from the other process, if one has arrived.
+    fn receive(&mut self) -> Option<(u8, u8)> {
+        let mut buf = [0; 2];
+        while self.rx.len() < 2 {
+            match self.stream.read(&mut buf[..(2 - self.rx.len())]) {
+                Ok(0) => break, // Disconnected.
+                Ok(n) => self.rx.extend_from_slice(&buf[..n]),
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(_) => break, // Nothing more has arrived.
+            }
+        }
+
+        if self.rx.len() < 2 {
+            return None;
+        }
+
+        let msg = (self.rx[0], self.rx[1]);
+        self.rx.clear();
+        return Some(msg);
+    }
+}
+
+impl SerialDevice for SocketLink {
+    fn transfer_internal(&mut self, data: u8) -> Option<u8> {
+        self.send(MSG_CLOCK, data);
+        self.reply_wait_mcycles = Some(0);
+        return self.poll_internal();
+    }
+
+    fn poll_internal(&mut self) -> Option<u8> {
+        // A transfer loaded from a save state was never sent.
+        let Some(wait_mcycles) = self.reply_wait_mcycles else {
+            return Some(DISCONNECTED_DATA);
+        };
+
+        // The socket is checked at the same rate as for incoming transfers.
+        if wait_mcycles.is_multiple_of(POLL_PERIOD_MCYCLES) {
+            while let Some(msg) = self.receive() {
+                match msg {
+                    (MSG_REPLY, reply) => {
+                        self.reply_wait_mcycles = None;
+                        return Some(reply);
+                    }
+                    (MSG_CLOCK, _) => {
+                        // Both sides clocked a transfer at once; neither was listening.
+                        self.send(MSG_REPLY, DISCONNECTED_DATA);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if wait_mcycles >= REPLY_TIMEOUT_MCYCLES {
+            self.reply_wait_mcycles = None;
+            return Some(DISCONNECTED_DATA);
+        }
+        self.reply_wait_mcycles = Some(wait_mcycles + 1);
+        return None;
+    }
+
+    fn poll_external(&mut self, ready: Option<u8>) -> Option<u8> {
+        self.mcycles_since_poll += 1;
+        if self.mcycles_since_poll < POLL_PERIOD_MCYCLES {
+            return None;
+        }
+        self.mcycles_since_poll = 0;
+
+        let Some((MSG_CLOCK, data)) = self.receive() else {
+            return None;
+        };
+
+        self.send(MSG_REPLY, ready.unwrap_or(DISCONNECTED_DATA));
+        return ready.map(|_| data);
+    }
+}
+
+#[cfg(not(unix))]
+fn unix_unsupported() -> io::Error {
+    io::Error::new(
+        ErrorKind::Unsupported,
+        "Unix domain sockets aren't supported on this platform.",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+
+    /// Links two sockets over loopback TCP.
+    fn create_pair() -> (SocketLink, SocketLink) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let connecting = thread::spawn(move || SocketLink::connect(&addr).unwrap());
+
+        let (stream, _) = listener.accept().unwrap();
+        let listening = SocketLink::new(Box::new(stream)).unwrap();
+        return (listening, connecting.join().unwrap());
+    }
+
+    /// Calls `f` until it returns something, for up to a second.
+    fn poll_until<T>(mut f: impl FnMut() -> Option<T>) -> Option<T> {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(1) {
+            if let Some(result) = f() {
+                return Some(result);
+            }
+            thread::sleep(Duration::from_micros(100));
+        }
+        return None;
+    }
+
+    #[test]
+    fn test_exchange() {
+        let (mut a, mut b) = create_pair();
+
+        // The reply isn't in yet, so A doesn't wait for it.
+        assert_eq!(a.transfer_internal(0x17), None);
+        assert_eq!(poll_until(|| b.poll_external(Some(0x42))), Some(0x17));
+        assert_eq!(poll_until(|| a.poll_internal()), Some(0x42));
+    }
+
+    #[test]
+    fn test_reply_timeout() {
+        let (mut a, _b) = create_pair();
+
+        assert_eq!(a.transfer_internal(0x17), None);
+        for _ in 0..REPLY_TIMEOUT_MCYCLES - 1 {
+            assert_eq!(a.poll_internal(), None);
+        }
+        assert_eq!(a.poll_internal(), Some(DISCONNECTED_DATA));
+    }
+}
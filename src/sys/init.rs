@@ -9,7 +9,14 @@ use super::Sys;
 pub fn init(sys: &mut Sys) {
     use CompatibilityMode::*;
 
-    match sys.mem.cart.header().compatibility_mode() {
+    if sys.mem.is_boot_rom_mapped() {
+        // The boot ROM initializes everything itself.
+        sys.regs.set_16(CpuReg16::PC, 0x0000);
+        return;
+    }
+    sys.mem.io_regs.set(IoReg::Boot, 0x01);
+
+    match sys.compatibility_mode() {
         DmgOnly => {
             init_cpu_dmg_only(sys);
             init_io_regs_dmg(sys);
@@ -1,6 +1,7 @@
 mod init;
 pub mod options;
 pub mod speed;
+mod state;
 mod sys;
 
 pub use sys::Sys;
@@ -1,4 +1,13 @@
+use crate::other::mode::HardwareModel;
+
 pub struct Options {
     pub kill_on_infinite_loop: bool,
     pub show_vram_views: bool,
+
+    /// The model to emulate. If not specified, it's chosen from the cart header.
+    pub model: Option<HardwareModel>,
+
+    /// Boot ROM to run before the cartridge. If not specified, the system
+    /// starts in the state the boot ROM would have left it in.
+    pub boot_rom: Option<Vec<u8>>,
 }
@@ -16,7 +16,17 @@
 // Additionally, VRAM/OAM/… locking is “frozen”, yielding
 // different results depending on the PPU mode it’s started in.
 
-use crate::{mem::io_regs::IoReg, util::bits::Bits};
+// Outside of a speed switch, STOP enters low-power mode: the CPU, LCD
+// and timers are halted until a button is pressed.
+
+use crate::{
+    mem::io_regs::IoReg,
+    ppu::ppu::MCYCLES_PER_FRAME,
+    util::{
+        bits::Bits,
+        state::{SaveState, StateReader, StateWriter},
+    },
+};
 
 use super::Sys;
 
@@ -24,6 +34,9 @@ use super::Sys;
 pub struct SpeedControl {
     stop_mcycles_left: u32,
     mcycle: u32,
+
+    is_low_power: bool,
+    low_power_mcycles: u32,
 }
 
 impl SpeedControl {
@@ -31,6 +44,9 @@ impl SpeedControl {
         Self {
             stop_mcycles_left: 0,
             mcycle: 0,
+
+            is_low_power: false,
+            low_power_mcycles: 0,
         }
     }
 
@@ -41,6 +57,38 @@ impl SpeedControl {
     pub fn stop(&mut self) {
         self.stop_mcycles_left = 2050;
     }
+
+    pub fn is_low_power_mode_active(&self) -> bool {
+        return self.is_low_power;
+    }
+
+    /// Enters low-power STOP mode.
+    pub fn enter_low_power_mode(&mut self) {
+        self.is_low_power = true;
+        self.low_power_mcycles = 0;
+    }
+
+    /// Leaves low-power STOP mode (a button was pressed).
+    pub fn wake(&mut self) {
+        self.is_low_power = false;
+    }
+}
+
+impl SaveState for SpeedControl {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u32(self.stop_mcycles_left);
+        w.write_u32(self.mcycle);
+        w.write_bool(self.is_low_power);
+        w.write_u32(self.low_power_mcycles);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.stop_mcycles_left = r.read_u32()?;
+        self.mcycle = r.read_u32()?;
+        self.is_low_power = r.read_bool()?;
+        self.low_power_mcycles = r.read_u32()?;
+        return Ok(());
+    }
 }
 
 pub fn is_double_speed_mode_active(sys: &mut Sys) -> bool {
@@ -67,3 +115,13 @@ pub fn update_speed_ctrl(sys: &mut Sys) {
 
     sys.speed_ctrl.mcycle = (sys.speed_ctrl.mcycle + 1) % cycle_len;
 }
+
+/// Advances low-power STOP mode by one M-Cycle. The LCD is off, but frames
+/// are still presented at the usual rate so that inputs keep being read.
+pub fn update_low_power_mode(sys: &mut Sys) {
+    sys.speed_ctrl.low_power_mcycles += 1;
+    if sys.speed_ctrl.low_power_mcycles >= MCYCLES_PER_FRAME {
+        sys.speed_ctrl.low_power_mcycles = 0;
+        sys.is_render_pending = true;
+    }
+}
@@ -0,0 +1,69 @@
+use crate::util::state::{SaveState, StateReader, StateWriter};
+
+use super::Sys;
+
+impl Sys {
+    /// Captures the full machine state.
+    pub fn save_snapshot(&self) -> Vec<u8> {
+        let mut w = StateWriter::new();
+        self.save_state(&mut w);
+        return w.into_bytes();
+    }
+
+    /// Restores a machine state captured by `save_snapshot`. On error, the
+    /// system may be left partially restored.
+    pub fn load_snapshot(&mut self, snapshot: &[u8]) -> Result<(), String> {
+        let mut r = StateReader::new(snapshot);
+        self.load_state(&mut r)?;
+        if !r.is_done() {
+            return Err("Snapshot has unexpected trailing data.".into());
+        }
+
+        return Ok(());
+    }
+}
+
+impl SaveState for Sys {
+    fn save_state(&self, w: &mut StateWriter) {
+        self.speed_ctrl.save_state(w);
+
+        self.mem.save_state(w);
+        self.ppu.save_state(w);
+        self.regs.save_state(w);
+        self.serial.save_state(w);
+        self.infrared.save_state(w);
+        self.joypad.save_state(w);
+
+        self.cpu_clock.save_state(w);
+        self.div_timer_clock.save_state(w);
+        self.tima_timer_clock.save_state(w);
+
+        w.write_u32(self.cpu_delay_ticks);
+
+        w.write_bool(self.cpu_enable);
+        w.write_bool(self.lcd_enable);
+        w.write_bool(self.interrupt_master_enable);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.speed_ctrl.load_state(r)?;
+
+        self.mem.load_state(r)?;
+        self.ppu.load_state(r)?;
+        self.regs.load_state(r)?;
+        self.serial.load_state(r)?;
+        self.infrared.load_state(r)?;
+        self.joypad.load_state(r)?;
+
+        self.cpu_clock.load_state(r)?;
+        self.div_timer_clock.load_state(r)?;
+        self.tima_timer_clock.load_state(r)?;
+
+        self.cpu_delay_ticks = r.read_u32()?;
+
+        self.cpu_enable = r.read_bool()?;
+        self.lcd_enable = r.read_bool()?;
+        self.interrupt_master_enable = r.read_bool()?;
+        return Ok(());
+    }
+}
@@ -2,9 +2,21 @@ use crate::{
     cart::cart::Cart,
     cpu::{exec::execute_next_instr, interrupt::try_handle_interrupts, regs::CpuRegs},
     debug::{self, debug_state},
-    mem::{io_regs::IoReg, mem::Mem},
-    other::{emu::Emu, joypad::handle_joypad_inputs},
+    debugger::{
+        call_stack::clear_call_stack,
+        debugger::{check_before_instr, update_debugger, Debugger},
+        profiler::{begin_profiled_instr, end_profiled_instr},
+        trace::update_trace,
+    },
+    infrared::infrared::{update_infrared, Infrared},
+    mem::{bus::Bus, io_regs::IoReg, mem::Mem},
+    other::{
+        emu::Emu,
+        joypad::{handle_joypad_inputs, Joypad},
+        mode::{CompatibilityMode, HardwareModel},
+    },
     ppu::ppu::{print_ppu, update_ppu, Ppu},
+    serial::serial::{update_serial, Serial},
     time::{
         clock::Clock,
         timers::{
@@ -16,18 +28,25 @@ use crate::{
 use super::{
     init::init,
     options::Options,
-    speed::{update_speed_ctrl, SpeedControl},
+    speed::{update_low_power_mode, update_speed_ctrl, SpeedControl},
 };
 
-/// Represents the state of the emulated Game Boy system.
-pub struct Sys {
+/// Represents the state of the emulated Game Boy system. The CPU runs
+/// against `mem`, which is the real memory map unless a test swaps in
+/// another bus.
+pub struct Sys<B: Bus = Mem> {
     pub options: Options,
+    mode: CompatibilityMode,
     pub emu: Emu,
+    pub debugger: Debugger,
     pub speed_ctrl: SpeedControl,
 
-    pub mem: Mem,
+    pub mem: B,
     pub ppu: Ppu,
     pub regs: CpuRegs,
+    pub serial: Serial,
+    pub infrared: Infrared,
+    pub joypad: Joypad,
 
     pub cpu_clock: Clock,
     pub div_timer_clock: Clock,
@@ -41,18 +60,29 @@ pub struct Sys {
 
     pub hard_lock: bool,
     pub is_render_pending: bool,
+
+    /// Set while running frames that won't be displayed, so that drawing
+    /// them into the frame can be skipped.
+    pub skip_render: bool,
 }
 
-impl Sys {
-    pub fn new(options: Options, cart: Cart) -> Self {
-        let mut sys = Self {
+impl<B: Bus> Sys<B> {
+    /// Creates a system around `mem`, with everything else at its power-on
+    /// state. `Sys::new` also sets up the cart and boot state.
+    pub fn with_bus(options: Options, mode: CompatibilityMode, mem: B) -> Self {
+        return Self {
             options,
+            mode,
             emu: Emu::default(),
+            debugger: Debugger::new(),
             speed_ctrl: SpeedControl::new(),
 
-            mem: Mem::new(cart),
+            mem,
             ppu: Ppu::new(),
             regs: CpuRegs::new(),
+            serial: Serial::new(),
+            infrared: Infrared::new(),
+            joypad: Joypad::new(),
 
             cpu_clock: Clock::new("CPU", CPU_PERIOD_MCYCLES),
             div_timer_clock: Clock::new("DIV", DIV_PERIOD_MCYCLES),
@@ -66,37 +96,100 @@ impl Sys {
 
             hard_lock: false,
             is_render_pending: false,
+            skip_render: false,
         };
+    }
+
+    pub fn compatibility_mode(&self) -> CompatibilityMode {
+        self.mode
+    }
+
+    pub fn is_cgb_only_mode(&self) -> bool {
+        self.mode.is_cgb_only()
+    }
+
+    pub fn is_cgb_mode(&self) -> bool {
+        self.mode.is_cgb()
+    }
+}
+
+impl Sys {
+    pub fn new(options: Options, cart: Cart) -> Self {
+        let mode = compatibility_mode(&options, &cart);
+        let is_cgb_mode = mode.is_cgb();
+        let boot_rom = options.boot_rom.clone();
+        let mem = Mem::new(cart, is_cgb_mode, boot_rom);
 
+        let mut sys = Self::with_bus(options, mode, mem);
+        sys.mem.fill_power_on_ram(is_cgb_mode);
         init(&mut sys);
 
         return sys;
     }
 
-    pub fn is_cgb_only_mode(&self) -> bool {
-        self.mem.cart.header().compatibility_mode().is_cgb_only()
+    /// Restarts the cart. The hardware returns to its power-on state, but RAM
+    /// keeps its contents, and connected devices stay connected.
+    pub fn reset(&mut self) {
+        self.speed_ctrl = SpeedControl::new();
+
+        self.mem.reset();
+        self.ppu.reset();
+        self.regs = CpuRegs::new();
+        self.serial.reset();
+        self.infrared.reset();
+        self.joypad.reset();
+        clear_call_stack(self);
+
+        self.cpu_clock = Clock::new("CPU", CPU_PERIOD_MCYCLES);
+        self.div_timer_clock = Clock::new("DIV", DIV_PERIOD_MCYCLES);
+        self.tima_timer_clock = Clock::new("TIMA", TAC_CLK_0_PERIOD_MCYCLES);
+
+        self.cpu_delay_ticks = 0;
+
+        self.cpu_enable = true;
+        self.lcd_enable = true;
+        self.interrupt_master_enable = false;
+
+        self.is_render_pending = false;
+
+        init(self);
     }
 
-    pub fn is_cgb_mode(&self) -> bool {
-        self.mem.cart.header().compatibility_mode().is_cgb()
+    /// Turns the system off and on again. Unlike `reset`, RAM is lost.
+    pub fn power_cycle(&mut self) {
+        self.mem.fill_power_on_ram(self.is_cgb_mode());
+        self.reset();
     }
 
     pub fn run_one_m_cycle(&mut self) {
+        if self.speed_ctrl.is_low_power_mode_active() {
+            update_low_power_mode(self);
+            handle_joypad_inputs(self);
+            return;
+        }
+
         update_speed_ctrl(self);
 
         if !self.speed_ctrl.is_stop_active() && self.cpu_clock.update_and_check() {
             self.cpu_delay_ticks = u32::saturating_sub(self.cpu_delay_ticks, 1);
             if self.cpu_delay_ticks == 0 {
                 try_handle_interrupts(self);
-                if self.cpu_enable {
-                    self.cpu_delay_ticks = execute_next_instr(self);
+                if self.cpu_enable && !check_before_instr(self) {
+                    update_trace(self);
+                    begin_profiled_instr(self);
+                    let cycles = execute_next_instr(self);
+                    end_profiled_instr(self, cycles);
+                    self.cpu_delay_ticks = cycles;
                 }
             }
         }
 
         update_ppu(self);
         update_timer_regs(self);
+        update_serial(self);
+        update_infrared(self);
         handle_joypad_inputs(self);
+        update_debugger(self);
 
         ///////// DEBUG //////////////////////////////////////////////
         if let Some(kill_after_nop_count) = debug_state().config.kill_after_nop_count {
@@ -136,3 +229,12 @@ impl Sys {
         self.tima_timer_clock.print();
     }
 }
+
+/// A DMG can only run carts in DMG mode. A CGB runs DMG carts in a compatibility
+/// mode, which is emulated the same way as a DMG.
+fn compatibility_mode(options: &Options, cart: &Cart) -> CompatibilityMode {
+    match options.model {
+        Some(HardwareModel::Dmg) => CompatibilityMode::DmgOnly,
+        Some(HardwareModel::Cgb) | None => cart.header().compatibility_mode(),
+    }
+}
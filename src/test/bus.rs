@@ -0,0 +1,124 @@
+use crate::{
+    debugger::cdl::ReadUse,
+    mem::{bus::Bus, io_regs::IoReg, Addr},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Access {
+    Read(Addr, u8),
+    Write(Addr, u8),
+}
+
+/// 64 KiB of RAM with nothing mapped. IO registers are plain RAM, and DMA
+/// only starts when a test sets a request.
+pub struct FlatBus {
+    pub ram: Vec<u8>,
+    pub oam_dma_requested: bool,
+    pub vram_dma_requested: bool,
+}
+
+impl FlatBus {
+    pub fn new() -> Self {
+        Self {
+            ram: vec![0; 0x10000],
+            oam_dma_requested: false,
+            vram_dma_requested: false,
+        }
+    }
+}
+
+impl Bus for FlatBus {
+    fn read(&mut self, addr: Addr) -> u8 {
+        self.ram[addr as usize]
+    }
+
+    fn read_as(&mut self, addr: Addr, _use: ReadUse) -> u8 {
+        self.read(addr)
+    }
+
+    fn write(&mut self, addr: Addr, data: u8) {
+        self.ram[addr as usize] = data;
+    }
+
+    fn peek(&self, addr: Addr) -> u8 {
+        self.ram[addr as usize]
+    }
+
+    fn io_reg(&self, reg: IoReg) -> u8 {
+        self.ram[reg.as_addr() as usize]
+    }
+
+    fn set_io_reg(&mut self, reg: IoReg, data: u8) {
+        self.ram[reg.as_addr() as usize] = data;
+    }
+
+    fn rom_bank(&self) -> usize {
+        1
+    }
+
+    fn take_oam_dma_request(&mut self) -> bool {
+        std::mem::take(&mut self.oam_dma_requested)
+    }
+
+    fn take_vram_dma_request(&mut self) -> bool {
+        std::mem::take(&mut self.vram_dma_requested)
+    }
+}
+
+/// Wraps another bus and logs every read and write made through it.
+pub struct LoggingBus<B: Bus> {
+    pub inner: B,
+    pub accesses: Vec<Access>,
+}
+
+impl<B: Bus> LoggingBus<B> {
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            accesses: vec![],
+        }
+    }
+}
+
+impl<B: Bus> Bus for LoggingBus<B> {
+    fn read(&mut self, addr: Addr) -> u8 {
+        let data = self.inner.read(addr);
+        self.accesses.push(Access::Read(addr, data));
+        return data;
+    }
+
+    fn read_as(&mut self, addr: Addr, use_: ReadUse) -> u8 {
+        let data = self.inner.read_as(addr, use_);
+        self.accesses.push(Access::Read(addr, data));
+        return data;
+    }
+
+    fn write(&mut self, addr: Addr, data: u8) {
+        self.inner.write(addr, data);
+        self.accesses.push(Access::Write(addr, data));
+    }
+
+    fn peek(&self, addr: Addr) -> u8 {
+        self.inner.peek(addr)
+    }
+
+    fn io_reg(&self, reg: IoReg) -> u8 {
+        self.inner.io_reg(reg)
+    }
+
+    fn set_io_reg(&mut self, reg: IoReg, data: u8) {
+        self.inner.set_io_reg(reg, data);
+    }
+
+    fn rom_bank(&self) -> usize {
+        self.inner.rom_bank()
+    }
+
+    fn take_oam_dma_request(&mut self) -> bool {
+        self.inner.take_oam_dma_request()
+    }
+
+    fn take_vram_dma_request(&mut self) -> bool {
+        self.inner.take_vram_dma_request()
+    }
+}
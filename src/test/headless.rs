@@ -0,0 +1,51 @@
+use std::{
+    path::Path,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{
+    cart::cart::Cart,
+    debug::{initialize_debug, DebugConfig},
+    other::mode::HardwareModel,
+    sys::{options::Options, Sys},
+};
+
+/// The debug state is global, so only one test can run a system at a time.
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Hold the returned guard while running headless systems.
+pub fn lock_headless() -> MutexGuard<'static, ()> {
+    LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Sets up the debug state the way headless tests need it.
+pub fn initialize_headless_debug() {
+    initialize_debug(DebugConfig {
+        enable_debug_print: false,
+        kill_after_cpu_ticks: None,
+        kill_after_nop_count: None,
+        last_instr_count: 5,
+    });
+}
+
+/// Options for running without a window.
+pub fn headless_options(model: Option<HardwareModel>) -> Options {
+    Options {
+        kill_on_infinite_loop: false,
+        show_vram_views: false,
+        model,
+        boot_rom: None,
+    }
+}
+
+/// Creates a system to run the ROM at `path` without a window. `model`
+/// defaults to the one the cart asks for.
+pub fn create_headless_sys(path: &Path, model: Option<HardwareModel>) -> Result<Sys, String> {
+    initialize_headless_debug();
+
+    let cart = Cart::load_from(path, false)?;
+    let mut sys = Sys::new(headless_options(model), cart);
+    sys.debugger.break_on_software = true;
+
+    return Ok(sys);
+}
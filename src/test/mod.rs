@@ -1,2 +1,12 @@
-pub mod blargg;
 pub mod instr;
+
+#[cfg(test)]
+pub mod bus;
+#[cfg(test)]
+pub mod headless;
+#[cfg(test)]
+mod rom_runner;
+#[cfg(test)]
+mod screenshot;
+#[cfg(test)]
+mod single_step;
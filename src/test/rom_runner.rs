@@ -0,0 +1,311 @@
+// Runs a directory of test ROMs headlessly and checks each one's result.
+// The directory defaults to `assets/test_roms`, or can be set with the
+// `TEST_ROMS_DIR` environment variable. ROMs are found recursively.
+//
+// Results are detected the way each suite reports them:
+//   blargg:  "Passed" or "Failed" printed over the serial port, or the
+//            result code at $A000 once $A001-$A003 hold DE B0 61.
+//   mooneye: `LD B,B` with B, C, D, E, H, L holding 3, 5, 8, 13, 21, 34
+//            on success, or all 0x42 on failure.
+//
+// `expected_passes.txt` in the directory lists the ROMs (one relative path
+// per line) that are known to pass. Any of them failing is a regression,
+// which fails the test. Run with `TEST_ROMS_BLESS=1` to rewrite the list
+// from the current results.
+
+use std::{
+    cell::RefCell,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    cpu::regs::CpuReg8, debug, debugger::debugger::BreakReason, mem::bus::Bus,
+    ppu::ppu::MCYCLES_PER_FRAME, serial::device::SerialDevice, sys::Sys,
+};
+
+use super::headless::create_headless_sys;
+
+const DEFAULT_DIR: &str = "assets/test_roms";
+const EXPECTED_PASSES_FILE: &str = "expected_passes.txt";
+
+/// Emulated time a ROM gets to report a result: 30 seconds.
+const MAX_MCYCLES: u64 = 30 * 1_048_576;
+
+const BLARGG_SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const BLARGG_RUNNING: u8 = 0x80;
+
+const MOONEYE_PASS_REGS: [u8; 6] = [3, 5, 8, 13, 21, 34];
+const MOONEYE_FAIL_REGS: [u8; 6] = [0x42; 6];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TestResult {
+    Passed,
+    Failed(String),
+    TimedOut,
+}
+
+/// Records the bytes a test ROM prints over the serial port.
+struct SerialCapture {
+    output: Rc<RefCell<Vec<u8>>>,
+}
+
+impl SerialDevice for SerialCapture {
+    fn transfer_internal(&mut self, data: u8) -> Option<u8> {
+        self.output.borrow_mut().push(data);
+        return Some(0xFF);
+    }
+
+    fn poll_external(&mut self, _ready: Option<u8>) -> Option<u8> {
+        None
+    }
+}
+
+/// Runs the ROM at `path` until it reports a result, or for at most
+/// `max_mcycles`.
+pub fn run_test_rom(path: &Path, max_mcycles: u64) -> Result<TestResult, String> {
+    let mut sys = create_headless_sys(path, None)?;
+
+    let serial_output = Rc::new(RefCell::new(vec![]));
+    sys.serial.connect(Box::new(SerialCapture {
+        output: serial_output.clone(),
+    }));
+
+    for mcycle in 0..max_mcycles {
+        sys.run_one_m_cycle();
+        if sys.hard_lock {
+            let failure = debug::get_failure().unwrap_or("Locked up.".into());
+            return Ok(TestResult::Failed(failure));
+        }
+
+        if matches!(sys.debugger.take_break(), Some(BreakReason::Software)) {
+            if let Some(result) = check_mooneye(&sys) {
+                return Ok(result);
+            }
+        }
+
+        // The slower checks only run once per frame.
+        if mcycle % MCYCLES_PER_FRAME as u64 == 0 {
+            let serial_output = serial_output.borrow();
+            if let Some(result) = check_blargg_serial(&serial_output) {
+                return Ok(result);
+            }
+            if let Some(result) = check_blargg_memory(&sys) {
+                return Ok(result);
+            }
+        }
+    }
+
+    return Ok(TestResult::TimedOut);
+}
+
+fn check_mooneye<B: Bus>(sys: &Sys<B>) -> Option<TestResult> {
+    use CpuReg8::*;
+    let regs = [B, C, D, E, H, L].map(|reg| sys.regs.get_8(reg));
+
+    return match regs {
+        MOONEYE_PASS_REGS => Some(TestResult::Passed),
+        MOONEYE_FAIL_REGS => Some(TestResult::Failed("Failed.".into())),
+        _ => None,
+    };
+}
+
+fn check_blargg_serial(output: &[u8]) -> Option<TestResult> {
+    let text = String::from_utf8_lossy(output);
+    if text.contains("Passed") {
+        return Some(TestResult::Passed);
+    }
+
+    // Wait for the rest of the line, which says what failed.
+    let failed_at = text.find("Failed")?;
+    let line = &text[failed_at..];
+    let line_end = line.find('\n')?;
+    return Some(TestResult::Failed(line[..line_end].trim().to_owned()));
+}
+
+fn check_blargg_memory<B: Bus>(sys: &Sys<B>) -> Option<TestResult> {
+    let signature = [0xA001, 0xA002, 0xA003].map(|addr| sys.mem.peek(addr));
+    let status = sys.mem.peek(0xA000);
+    if signature != BLARGG_SIGNATURE || status == BLARGG_RUNNING {
+        return None;
+    }
+
+    if status == 0x00 {
+        return Some(TestResult::Passed);
+    }
+
+    // The text the test printed is stored from $A004, null-terminated.
+    let text = (0xA004..0xC000)
+        .map(|addr| sys.mem.peek(addr))
+        .take_while(|c| *c != 0)
+        .map(char::from)
+        .collect::<String>();
+    let msg = format!("Result code {}: {}", status, text.trim());
+    return Some(TestResult::Failed(msg));
+}
+
+/// All `.gb` and `.gbc` files under `dir`, sorted.
+fn find_roms(dir: &Path) -> Vec<PathBuf> {
+    let mut roms = vec![];
+    let Ok(entries) = fs::read_dir(dir) else {
+        return roms;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            roms.extend(find_roms(&path));
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext == "gb" || ext == "gbc")
+        {
+            roms.push(path);
+        }
+    }
+
+    roms.sort();
+    return roms;
+}
+
+/// The path of `rom` within `dir`, with forward slashes on every platform.
+fn rom_name(dir: &Path, rom: &Path) -> String {
+    let rel = rom.strip_prefix(dir).unwrap_or(rom);
+    let parts = rel.iter().map(|part| part.to_string_lossy());
+    return parts.collect::<Vec<_>>().join("/");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::{
+        other::mode::CompatibilityMode,
+        test::{
+            bus::FlatBus,
+            headless::{headless_options, lock_headless},
+        },
+    };
+
+    use super::*;
+
+    fn create_flat_sys() -> Sys<FlatBus> {
+        let options = headless_options(None);
+        return Sys::with_bus(options, CompatibilityMode::DmgOnly, FlatBus::new());
+    }
+
+    #[test]
+    fn test_check_blargg_serial() {
+        assert_eq!(check_blargg_serial(b"cpu_instrs\n\n01:ok  "), None);
+        assert_eq!(
+            check_blargg_serial(b"cpu_instrs\n\nPassed all tests\n"),
+            Some(TestResult::Passed)
+        );
+
+        // The reason only counts once its line is done.
+        assert_eq!(check_blargg_serial(b"02:01  \nFailed 1 t"), None);
+        assert_eq!(
+            check_blargg_serial(b"02:01  \nFailed 1 tests.\n"),
+            Some(TestResult::Failed("Failed 1 tests.".into()))
+        );
+    }
+
+    #[test]
+    fn test_check_blargg_memory() {
+        let mut sys = create_flat_sys();
+        sys.mem.ram[0xA000] = BLARGG_RUNNING;
+        assert_eq!(check_blargg_memory(&sys), None);
+
+        sys.mem.ram[0xA001..0xA004].copy_from_slice(&BLARGG_SIGNATURE);
+        assert_eq!(check_blargg_memory(&sys), None);
+
+        sys.mem.ram[0xA000] = 0x00;
+        assert_eq!(check_blargg_memory(&sys), Some(TestResult::Passed));
+
+        sys.mem.ram[0xA000] = 0x02;
+        sys.mem.ram[0xA004..0xA00B].copy_from_slice(b"EI\nbad\0");
+        assert_eq!(
+            check_blargg_memory(&sys),
+            Some(TestResult::Failed("Result code 2: EI\nbad".into()))
+        );
+    }
+
+    #[test]
+    fn test_check_mooneye() {
+        use CpuReg8::*;
+        let mut sys = create_flat_sys();
+        let set_regs = |sys: &mut Sys<FlatBus>, values: [u8; 6]| {
+            for (reg, value) in [B, C, D, E, H, L].into_iter().zip(values) {
+                sys.regs.set_8(reg, value);
+            }
+        };
+
+        set_regs(&mut sys, [0; 6]);
+        assert_eq!(check_mooneye(&sys), None);
+        set_regs(&mut sys, MOONEYE_PASS_REGS);
+        assert_eq!(check_mooneye(&sys), Some(TestResult::Passed));
+        set_regs(&mut sys, MOONEYE_FAIL_REGS);
+        assert_eq!(
+            check_mooneye(&sys),
+            Some(TestResult::Failed("Failed.".into()))
+        );
+    }
+
+    #[test]
+    fn test_roms() {
+        let _lock = lock_headless();
+        let dir = env::var("TEST_ROMS_DIR").unwrap_or(DEFAULT_DIR.into());
+        let dir = Path::new(&dir);
+        let roms = find_roms(dir);
+        if roms.is_empty() {
+            println!("No test ROMs found in {:?}, skipping.", dir);
+            return;
+        }
+
+        let expected_path = dir.join(EXPECTED_PASSES_FILE);
+        let expected_passes = fs::read_to_string(&expected_path).unwrap_or_default();
+        let expected_passes = expected_passes
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+
+        let mut passes = vec![];
+        let mut regressions = vec![];
+
+        println!("{:<6} {:<9} ROM", "RESULT", "CHANGE");
+        for rom in &roms {
+            let name = rom_name(dir, rom);
+            let result = run_test_rom(rom, MAX_MCYCLES).unwrap_or_else(TestResult::Failed);
+
+            let is_pass = result == TestResult::Passed;
+            let is_expected = expected_passes.contains(&name.as_str());
+            let (label, note) = match &result {
+                TestResult::Passed => ("PASS", String::new()),
+                TestResult::Failed(msg) => ("FAIL", format!(" ({})", msg)),
+                TestResult::TimedOut => ("TIME", " (no result)".into()),
+            };
+            let change = match (is_pass, is_expected) {
+                (true, false) => "NEW PASS",
+                (false, true) => "REGRESSED",
+                _ => "",
+            };
+            println!("{:<6} {:<9} {}{}", label, change, name, note);
+
+            if is_pass {
+                passes.push(name);
+            } else if is_expected {
+                regressions.push(name);
+            }
+        }
+        println!("{} of {} passed.", passes.len(), roms.len());
+
+        if env::var_os("TEST_ROMS_BLESS").is_some() {
+            fs::write(&expected_path, passes.join("\n") + "\n").unwrap();
+            println!("Updated {:?}.", expected_path);
+            return;
+        }
+
+        assert!(regressions.is_empty(), "Regressed: {:?}", regressions);
+    }
+}
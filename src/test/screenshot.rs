@@ -0,0 +1,245 @@
+// Screenshot tests run a ROM, then compare the frame it leaves on the LCD
+// with a reference image. They are listed in `screenshots.toml` in the test
+// directory, which defaults to `assets/screenshot_tests` and can be set with
+// the `SCREENSHOT_TESTS_DIR` environment variable:
+//
+//   [[test]]
+//   rom = "dmg-acid2.gb"
+//   reference = "dmg-acid2-dmg.png"
+//   model = "dmg"
+//
+//   [[test]]
+//   rom = "mealybug/m3_scx_low_3_bits.gb"
+//   reference = "mealybug/m3_scx_low_3_bits_cgb_c.png"
+//   frames = 20
+//
+// The screenshot is taken at the end of the frame in which the ROM executes
+// `LD B,B` (how acid2 and Mealybug Tearoom signal they're done), or after
+// `frames` frames if given. On a mismatch, the frame and a diff image with
+// the differing pixels in red are written to `target/screenshot_diffs`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use macroquad::{prelude::ImageFormat, texture::Image};
+use serde::Deserialize;
+
+use crate::{
+    debug,
+    debugger::debugger::BreakReason,
+    other::mode::HardwareModel,
+    ppu::{
+        frame::{FRAME_HEIGHT, FRAME_WIDTH},
+        ppu::MCYCLES_PER_FRAME,
+    },
+    sys::Sys,
+    util::image::save_png,
+};
+
+use super::headless::create_headless_sys;
+
+const DEFAULT_DIR: &str = "assets/screenshot_tests";
+const MANIFEST_FILE: &str = "screenshots.toml";
+const DIFF_DIR: &str = "target/screenshot_diffs";
+
+/// Frames a ROM gets to reach its `LD B,B`.
+const MAX_FRAMES: u32 = 600;
+
+const DIFF_COLOR: [u8; 4] = [0xFF, 0x00, 0x00, 0xFF];
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    #[serde(rename = "test")]
+    tests: Vec<ScreenshotTest>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScreenshotTest {
+    rom: PathBuf,
+    reference: PathBuf,
+    model: Option<HardwareModel>,
+
+    /// Take the screenshot after this many frames, instead of at `LD B,B`.
+    frames: Option<u32>,
+}
+
+/// Runs `test` and compares its screenshot with the reference. On a
+/// mismatch, writes the diff images and returns an error saying how many
+/// pixels differ.
+fn run_screenshot_test(dir: &Path, test: &ScreenshotTest) -> Result<(), String> {
+    let reference = load_png(&dir.join(&test.reference))?;
+
+    let mut sys = create_headless_sys(&dir.join(&test.rom), test.model)?;
+    run_to_screenshot(&mut sys, test.frames)?;
+
+    let frame = sys.ppu.frame().pixels();
+    let is_cgb_mode = sys.is_cgb_mode();
+    let is_match =
+        |(a, b): (&[u8; 4], &[u8; 4])| quantize(*a, is_cgb_mode) == quantize(*b, is_cgb_mode);
+
+    let diff_count = frame
+        .iter()
+        .zip(&reference)
+        .filter(|pair| !is_match(*pair))
+        .count();
+    if diff_count == 0 {
+        return Ok(());
+    }
+
+    // Differing pixels are red, over a faded copy of the frame.
+    let diff = frame
+        .iter()
+        .zip(&reference)
+        .map(|pair| match is_match(pair) {
+            true => pair.0.map(|channel| channel / 4 + 0xC0),
+            false => DIFF_COLOR,
+        })
+        .collect::<Vec<_>>();
+
+    let name = test.reference.with_extension("");
+    let name = name.to_string_lossy().replace(['/', '\\'], "_");
+    let diff_dir = Path::new(DIFF_DIR);
+    fs::create_dir_all(diff_dir).map_err(|err| err.to_string())?;
+    save_png(
+        &diff_dir.join(format!("{}.png", name)),
+        FRAME_WIDTH,
+        FRAME_HEIGHT,
+        frame,
+    )?;
+    save_png(
+        &diff_dir.join(format!("{}.diff.png", name)),
+        FRAME_WIDTH,
+        FRAME_HEIGHT,
+        &diff,
+    )?;
+
+    return Err(format!("{} pixels differ", diff_count));
+}
+
+/// Runs until the end of the frame that executes `LD B,B`, or for `frames`
+/// frames if given.
+fn run_to_screenshot(sys: &mut Sys, frames: Option<u32>) -> Result<(), String> {
+    for _ in 0..frames.unwrap_or(MAX_FRAMES) {
+        let mut is_breakpoint_hit = false;
+
+        // Stops even if the LCD is off, and the frame never finishes.
+        for _ in 0..2 * MCYCLES_PER_FRAME {
+            sys.run_one_m_cycle();
+            if sys.hard_lock {
+                return Err(debug::get_failure().unwrap_or("Locked up.".into()));
+            }
+            is_breakpoint_hit |= matches!(sys.debugger.take_break(), Some(BreakReason::Software));
+
+            if sys.is_render_pending {
+                sys.is_render_pending = false;
+                break;
+            }
+        }
+
+        if is_breakpoint_hit && frames.is_none() {
+            return Ok(());
+        }
+    }
+
+    return match frames {
+        Some(_) => Ok(()),
+        None => Err(format!("No LD B,B within {} frames", MAX_FRAMES)),
+    };
+}
+
+/// Reduces a pixel to a color the LCD can show, so that references made with
+/// other color conversions still match: one of 4 grays on a DMG, or 5 bits
+/// per channel on a CGB.
+fn quantize(pixel: [u8; 4], is_cgb_mode: bool) -> [u8; 3] {
+    let [r, g, b, _] = pixel;
+    if is_cgb_mode {
+        return [r >> 3, g >> 3, b >> 3];
+    }
+
+    let gray = (r as u32 + g as u32 + b as u32) / 3;
+    let shade = ((gray + 42) / 85) as u8;
+    return [shade; 3];
+}
+
+fn load_png(path: &Path) -> Result<Vec<[u8; 4]>, String> {
+    let bytes = fs::read(path).map_err(|err| format!("Unable to read {:?}: {}", path, err))?;
+    let image = Image::from_file_with_format(&bytes, Some(ImageFormat::Png))
+        .map_err(|err| format!("Unable to load {:?}: {}", path, err))?;
+
+    if (image.width as usize, image.height as usize) != (FRAME_WIDTH, FRAME_HEIGHT) {
+        return Err(format!(
+            "{:?} is {}x{}, expected {}x{}",
+            path, image.width, image.height, FRAME_WIDTH, FRAME_HEIGHT
+        ));
+    }
+
+    let pixels = image.bytes.chunks_exact(4);
+    return Ok(pixels.map(|p| [p[0], p[1], p[2], p[3]]).collect());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::test::headless::lock_headless;
+
+    use super::*;
+
+    #[test]
+    fn test_quantize() {
+        // The same CGB color, converted by two different formulas.
+        let c = 21;
+        let scaled = (c * 255 / 31) as u8;
+        let shifted = ((c << 3) | (c >> 2)) as u8;
+        assert_eq!(
+            quantize([scaled, 0, 0xFF, 0xFF], true),
+            quantize([shifted, 0, 0xFF, 0xFF], true)
+        );
+
+        // Light gray, with the reference's and the emulator's values.
+        assert_eq!(
+            quantize([0xAA, 0xAA, 0xAA, 0xFF], false),
+            quantize([0xC7, 0xC7, 0xC7, 0xFF], false)
+        );
+        assert_ne!(
+            quantize([0xAA, 0xAA, 0xAA, 0xFF], false),
+            quantize([0x55, 0x55, 0x55, 0xFF], false)
+        );
+    }
+
+    #[test]
+    fn test_screenshots() {
+        let _lock = lock_headless();
+
+        let dir = env::var("SCREENSHOT_TESTS_DIR").unwrap_or(DEFAULT_DIR.into());
+        let dir = Path::new(&dir);
+        let Ok(manifest) = fs::read_to_string(dir.join(MANIFEST_FILE)) else {
+            println!("No screenshot tests found in {:?}, skipping.", dir);
+            return;
+        };
+        let manifest: Manifest = toml::from_str(&manifest).unwrap();
+
+        let mut failures = vec![];
+        for test in &manifest.tests {
+            let result = run_screenshot_test(dir, test);
+            let label = if result.is_ok() { "PASS" } else { "FAIL" };
+            let note = result.as_ref().err().map(|msg| format!(" ({})", msg));
+            println!(
+                "{:<6} {}{}",
+                label,
+                test.reference.display(),
+                note.unwrap_or_default()
+            );
+
+            if result.is_err() {
+                failures.push(test.reference.display().to_string());
+            }
+        }
+
+        assert!(failures.is_empty(), "Failed: {:?}", failures);
+    }
+}
@@ -0,0 +1,311 @@
+// Runs the CPU against single-step test vectors: one JSON file per opcode
+// (e.g. `3e.json`, `cb 11.json`), each an array of tests like
+//
+//   {
+//     "name": "3e 0000",
+//     "initial": { "a": 1, "b": 2, "c": 3, "d": 4, "e": 5, "f": 0, "h": 6,
+//                  "l": 7, "pc": 4001, "sp": 8, "ime": 0, "ie": 0,
+//                  "ram": [[4000, 62], [4001, 9], [4002, 0]] },
+//     "final":   { ...the same fields, "ram" listing the cells to check },
+//     "cycles":  [[4001, 9, "r-m"], [4002, 0, "r-m"]]
+//   }
+//
+// The directory defaults to `assets/single_step_tests`, or can be set with
+// the `SINGLE_STEP_TESTS_DIR` environment variable.
+//
+// Each test runs one instruction on a flat 64 KiB bus, logging every access.
+// The vectors model the SM83's overlapped fetch: the opcode at `pc - 1` has
+// already been fetched, and the last cycle fetches the next one. Each entry
+// in `cycles` is one M-cycle, with its access flagged `r` or `w`, or neither
+// for internal cycles. The CPU doesn't time accesses within an instruction,
+// so only their order and the total cycle count are compared.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    cpu::{
+        exec::execute_next_instr,
+        regs::{CpuReg16, CpuReg8},
+    },
+    mem::{bus::Bus, io_regs::IoReg, Addr},
+    other::mode::CompatibilityMode,
+    sys::Sys,
+};
+
+use super::{
+    bus::{Access, FlatBus, LoggingBus},
+    headless::{headless_options, initialize_headless_debug},
+};
+
+const DEFAULT_DIR: &str = "assets/single_step_tests";
+
+const REGS_8: [(&str, CpuReg8); 8] = [
+    ("A", CpuReg8::A),
+    ("F", CpuReg8::F),
+    ("B", CpuReg8::B),
+    ("C", CpuReg8::C),
+    ("D", CpuReg8::D),
+    ("E", CpuReg8::E),
+    ("H", CpuReg8::H),
+    ("L", CpuReg8::L),
+];
+
+#[derive(Deserialize)]
+struct TestVector {
+    name: String,
+    initial: CpuState,
+    #[serde(rename = "final")]
+    final_: CpuState,
+    cycles: Vec<Option<(Addr, Option<u8>, String)>>,
+}
+
+#[derive(Deserialize)]
+struct CpuState {
+    a: u8,
+    f: u8,
+    b: u8,
+    c: u8,
+    d: u8,
+    e: u8,
+    h: u8,
+    l: u8,
+    pc: u16,
+    sp: u16,
+    ime: u8,
+    ie: Option<u8>,
+    ram: Vec<(Addr, u8)>,
+}
+
+impl CpuState {
+    fn reg_8(&self, reg: CpuReg8) -> u8 {
+        match reg {
+            CpuReg8::A => self.a,
+            CpuReg8::F => self.f,
+            CpuReg8::B => self.b,
+            CpuReg8::C => self.c,
+            CpuReg8::D => self.d,
+            CpuReg8::E => self.e,
+            CpuReg8::H => self.h,
+            CpuReg8::L => self.l,
+        }
+    }
+}
+
+/// Runs one test vector. On a mismatch, returns what differs.
+fn run_vector(vector: &TestVector) -> Result<(), String> {
+    let initial = &vector.initial;
+    let mut bus = LoggingBus::new(FlatBus::new());
+    if let Some(ie) = initial.ie {
+        bus.inner.ram[IoReg::Ie.as_addr() as usize] = ie;
+    }
+    for &(addr, data) in &initial.ram {
+        bus.inner.ram[addr as usize] = data;
+    }
+
+    let options = headless_options(None);
+    let mut sys = Sys::with_bus(options, CompatibilityMode::DmgOnly, bus);
+    for (_, reg) in REGS_8 {
+        sys.regs.set_8(reg, initial.reg_8(reg));
+    }
+    sys.regs.set_16(CpuReg16::SP, initial.sp);
+    sys.interrupt_master_enable = initial.ime != 0;
+
+    // Fetch the opcode again, then leave it out of the accesses.
+    sys.regs.set_16(CpuReg16::PC, initial.pc.wrapping_sub(1));
+    let cycles = execute_next_instr(&mut sys);
+    let pc = sys.regs.pc();
+    sys.mem.read(pc);
+    sys.regs.set_16(CpuReg16::PC, pc.wrapping_add(1));
+    sys.mem.accesses.remove(0);
+
+    let expected = &vector.final_;
+    let mut diffs = vec![];
+    for (name, reg) in REGS_8 {
+        let (expected, actual) = (expected.reg_8(reg), sys.regs.get_8(reg));
+        if expected != actual {
+            diffs.push(format!(
+                "{}={:02X}, expected {:02X}",
+                name, actual, expected
+            ));
+        }
+    }
+    for (name, expected, actual) in [
+        ("PC", expected.pc, sys.regs.pc()),
+        ("SP", expected.sp, sys.regs.sp()),
+    ] {
+        if expected != actual {
+            diffs.push(format!(
+                "{}={:04X}, expected {:04X}",
+                name, actual, expected
+            ));
+        }
+    }
+    if (expected.ime != 0) != sys.interrupt_master_enable {
+        diffs.push(format!(
+            "IME={}, expected {}",
+            sys.interrupt_master_enable as u8, expected.ime
+        ));
+    }
+
+    let ie = expected.ie.map(|ie| (IoReg::Ie.as_addr(), ie));
+    for (addr, expected) in expected.ram.iter().copied().chain(ie) {
+        let actual = sys.mem.inner.ram[addr as usize];
+        if expected != actual {
+            diffs.push(format!(
+                "[{:04X}]={:02X}, expected {:02X}",
+                addr, actual, expected
+            ));
+        }
+    }
+
+    if cycles as usize != vector.cycles.len() {
+        diffs.push(format!(
+            "{} cycles, expected {}",
+            cycles,
+            vector.cycles.len()
+        ));
+    }
+    let expected_accesses = vector
+        .cycles
+        .iter()
+        .flatten()
+        .filter_map(|(addr, data, flags)| match (flags.as_bytes(), data) {
+            ([b'r', ..], Some(data)) => Some(Access::Read(*addr, *data)),
+            ([_, b'w', ..], Some(data)) => Some(Access::Write(*addr, *data)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if sys.mem.accesses != expected_accesses {
+        diffs.push(format!(
+            "accesses {:?}, expected {:?}",
+            sys.mem.accesses, expected_accesses
+        ));
+    }
+
+    if diffs.is_empty() {
+        return Ok(());
+    }
+    return Err(diffs.join(", "));
+}
+
+/// All `.json` files in `dir`, sorted.
+fn find_vector_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut paths = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    return paths;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::test::headless::lock_headless;
+
+    use super::*;
+
+    #[test]
+    fn test_run_vector() {
+        let _lock = lock_headless();
+        initialize_headless_debug();
+
+        // LD (HL),$5A at $C000, with HL=$D000.
+        let mut vector = TestVector {
+            name: "36 test".into(),
+            initial: CpuState {
+                a: 0,
+                f: 0,
+                b: 0,
+                c: 0,
+                d: 0,
+                e: 0,
+                h: 0xD0,
+                l: 0x00,
+                pc: 0xC001,
+                sp: 0xFFFE,
+                ime: 0,
+                ie: None,
+                ram: vec![(0xC000, 0x36), (0xC001, 0x5A), (0xC002, 0x00)],
+            },
+            final_: CpuState {
+                a: 0,
+                f: 0,
+                b: 0,
+                c: 0,
+                d: 0,
+                e: 0,
+                h: 0xD0,
+                l: 0x00,
+                pc: 0xC003,
+                sp: 0xFFFE,
+                ime: 0,
+                ie: None,
+                ram: vec![(0xD000, 0x5A)],
+            },
+            cycles: vec![
+                Some((0xC001, Some(0x5A), "r-m".into())),
+                Some((0xD000, Some(0x5A), "-wm".into())),
+                Some((0xC002, Some(0x00), "r-m".into())),
+            ],
+        };
+        assert_eq!(run_vector(&vector), Ok(()), "{}", vector.name);
+
+        vector.final_.ram = vec![(0xD000, 0x00)];
+        assert!(run_vector(&vector).is_err());
+    }
+
+    #[test]
+    fn test_single_step() {
+        let _lock = lock_headless();
+        initialize_headless_debug();
+
+        let dir = env::var("SINGLE_STEP_TESTS_DIR").unwrap_or(DEFAULT_DIR.into());
+        let dir = Path::new(&dir);
+        let paths = find_vector_files(dir);
+        if paths.is_empty() {
+            println!("No single-step tests found in {:?}, skipping.", dir);
+            return;
+        }
+
+        let mut failures = vec![];
+        for path in &paths {
+            let json = fs::read_to_string(path).unwrap();
+            let vectors: Vec<TestVector> = serde_json::from_str(&json)
+                .unwrap_or_else(|err| panic!("Unable to parse {:?}: {}", path, err));
+
+            let mut failed = vectors
+                .iter()
+                .filter_map(|vector| run_vector(vector).err().map(|msg| (vector, msg)));
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            let Some((first, msg)) = failed.next() else {
+                println!("PASS   {}", name);
+                continue;
+            };
+
+            let count = 1 + failed.count();
+            println!(
+                "FAIL   {} ({} of {} failed, first {}: {})",
+                name,
+                count,
+                vectors.len(),
+                first.name,
+                msg
+            );
+            failures.push(name.into_owned());
+        }
+
+        assert!(failures.is_empty(), "Failed: {:?}", failures);
+    }
+}
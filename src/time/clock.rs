@@ -1,3 +1,5 @@
+use crate::util::state::{SaveState, StateReader, StateWriter};
+
 // per Pan Docs: A “dot” = one 2^22 Hz (≅ 4.194 MHz) time unit.
 
 /// Represents a clock in the Game Boy hardware that ticks at a specific frequency.
@@ -23,6 +25,11 @@ impl Clock {
         self.mcycles_per_period = mcycles_per_period;
     }
 
+    /// Restarts the current period.
+    pub fn reset(&mut self) {
+        self.mcycles_since_tick = 0;
+    }
+
     pub fn update_and_check(&mut self) -> bool {
         self.mcycles_since_tick += 1;
         if self.mcycles_since_tick >= self.mcycles_per_period {
@@ -40,3 +47,18 @@ impl Clock {
         println!("  total ticks: {}", self.debug_total_ticks);
     }
 }
+
+impl SaveState for Clock {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.write_u32(self.mcycles_per_period);
+        w.write_u32(self.mcycles_since_tick);
+        w.write_u64(self.debug_total_ticks);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String> {
+        self.mcycles_per_period = r.read_u32()?;
+        self.mcycles_since_tick = r.read_u32()?;
+        self.debug_total_ticks = r.read_u64()?;
+        return Ok(());
+    }
+}
@@ -0,0 +1,136 @@
+// Delta encoding for snapshots. The data is XORed against a base (a
+// keyframe), which leaves long runs of zeros wherever the two match, and
+// then run-length encoded as a series of chunks:
+//
+//   <zero run length> <literal length> <literal bytes...>
+//
+// Lengths are LEB128 varints. Encoding against an empty base compresses the
+// data on its own. Bytes past the end of the base are XORed against zero.
+
+/// Encodes `data` as a delta against `base`.
+pub fn encode_delta(data: &[u8], base: &[u8]) -> Vec<u8> {
+    let xor = |idx: usize| data[idx] ^ base.get(idx).copied().unwrap_or(0);
+
+    let mut encoded = vec![];
+    let mut idx = 0;
+    while idx < data.len() {
+        let zeros_start = idx;
+        while idx < data.len() && xor(idx) == 0 {
+            idx += 1;
+        }
+
+        // Short zero runs inside literals cost more as a new chunk than they save.
+        let literals_start = idx;
+        while idx < data.len() {
+            let zeros = (idx..data.len().min(idx + 4))
+                .take_while(|i| xor(*i) == 0)
+                .count();
+            if zeros == 4 || idx + zeros == data.len() {
+                break;
+            }
+            idx += zeros.max(1);
+        }
+
+        write_varint(&mut encoded, literals_start - zeros_start);
+        write_varint(&mut encoded, idx - literals_start);
+        encoded.extend((literals_start..idx).map(xor));
+    }
+
+    return encoded;
+}
+
+/// Decodes a delta made by `encode_delta` with the same `base`.
+pub fn decode_delta(encoded: &[u8], base: &[u8]) -> Result<Vec<u8>, String> {
+    let base_at = |idx: usize| base.get(idx).copied().unwrap_or(0);
+
+    let mut data = vec![];
+    let mut pos = 0;
+    while pos < encoded.len() {
+        let zeros = read_varint(encoded, &mut pos)?;
+        for _ in 0..zeros {
+            data.push(base_at(data.len()));
+        }
+
+        let literals = read_varint(encoded, &mut pos)?;
+        let Some(bytes) = encoded.get(pos..pos + literals) else {
+            return Err("Delta literals ended early.".into());
+        };
+        for byte in bytes {
+            data.push(byte ^ base_at(data.len()));
+        }
+        pos += literals;
+    }
+
+    return Ok(data);
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<usize, String> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let Some(byte) = data.get(*pos) else {
+            return Err("Delta varint ended early.".into());
+        };
+        *pos += 1;
+
+        if shift >= usize::BITS {
+            return Err("Delta varint is too long.".into());
+        }
+        value |= ((byte & 0x7F) as usize) << shift;
+        shift += 7;
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let base: Vec<u8> = (0..1000).map(|i| (i * 7) as u8).collect();
+        let mut data = base.clone();
+        data[3] = 0xAA;
+        data[500..520].fill(0x55);
+        data.extend([1, 2, 3]);
+
+        let encoded = encode_delta(&data, &base);
+        assert!(encoded.len() < 40);
+        assert_eq!(decode_delta(&encoded, &base), Ok(data.clone()));
+
+        let encoded = encode_delta(&data, &[]);
+        assert_eq!(decode_delta(&encoded, &[]), Ok(data));
+    }
+
+    #[test]
+    fn test_zeros() {
+        let data = vec![0; 0x10000];
+        let encoded = encode_delta(&data, &[]);
+        assert!(encoded.len() < 8);
+        assert_eq!(decode_delta(&encoded, &[]), Ok(data));
+
+        assert_eq!(encode_delta(&[], &[]), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_truncated() {
+        let data: Vec<u8> = (1..=100).collect();
+        let encoded = encode_delta(&data, &[]);
+        assert!(decode_delta(&encoded[..encoded.len() - 1], &[]).is_err());
+    }
+}
@@ -0,0 +1,29 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+/// Saves RGBA8 `pixels` (row-major, top row first) as a PNG file.
+pub fn save_png(
+    path: &Path,
+    width: usize,
+    height: usize,
+    pixels: &[[u8; 4]],
+) -> Result<(), String> {
+    let write_err =
+        |err: &dyn std::fmt::Display| format!("Unable to write {}: {}", path.display(), err);
+
+    let file = File::create(path).map_err(|err| write_err(&err))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(|err| write_err(&err))?;
+    writer
+        .write_image_data(pixels.as_flattened())
+        .map_err(|err| write_err(&err))?;
+    let mut out = writer.finish().map_err(|err| write_err(&err))?;
+    out.flush().map_err(|err| write_err(&err))?;
+    return Ok(());
+}
@@ -1,5 +1,9 @@
 pub mod bits;
+pub mod delta;
 pub mod draw;
+pub mod hash;
+pub mod image;
 pub mod math;
 pub mod ring_buffer;
 pub mod slice;
+pub mod state;
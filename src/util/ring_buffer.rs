@@ -1,15 +1,16 @@
+use std::collections::{vec_deque, VecDeque};
+
+/// Keeps the last `max_len` values added, dropping the oldest when full.
 pub struct RingBuffer<T> {
     max_len: usize,
-    index: usize,
-    data: Vec<T>,
+    data: VecDeque<T>,
 }
 
 impl<T> RingBuffer<T> {
     pub fn new(size: usize) -> Self {
         Self {
             max_len: size,
-            index: 0,
-            data: vec![],
+            data: VecDeque::new(),
         }
     }
 
@@ -18,42 +19,61 @@ impl<T> RingBuffer<T> {
     }
 
     pub fn add(&mut self, value: T) {
+        if self.max_len == 0 {
+            return;
+        }
         if self.data.len() == self.max_len {
-            self.data[self.index] = value;
-            self.index = (self.index + 1) % self.max_len;
-        } else {
-            self.data.push(value);
+            self.data.pop_front();
         }
+        self.data.push_back(value);
     }
 
-    pub fn iter<'a>(&'a self) -> RingBufferIterator<'a, T> {
-        return RingBufferIterator {
-            ring_buffer: self,
-            index: self.index,
-            is_done: false,
-        };
+    /// The most recently added value.
+    pub fn newest_mut(&mut self) -> Option<&mut T> {
+        return self.data.back_mut();
     }
-}
 
-pub struct RingBufferIterator<'a, T> {
-    ring_buffer: &'a RingBuffer<T>,
-    index: usize,
-    is_done: bool,
+    /// Removes the most recently added value.
+    pub fn pop_newest(&mut self) -> Option<T> {
+        return self.data.pop_back();
+    }
+
+    /// Removes the least recently added value.
+    pub fn pop_oldest(&mut self) -> Option<T> {
+        return self.data.pop_front();
+    }
+
+    /// Iterates from the oldest value to the newest.
+    pub fn iter(&self) -> vec_deque::Iter<'_, T> {
+        return self.data.iter();
+    }
 }
 
-impl<'a, T> Iterator for RingBufferIterator<'a, T> {
-    type Item = &'a T;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.is_done || self.ring_buffer.len() == 0 {
-            return None;
+    #[test]
+    fn test_add() {
+        let mut ring_buffer = RingBuffer::new(3);
+        for value in 0..5 {
+            ring_buffer.add(value);
         }
 
-        let value = &self.ring_buffer.data[self.index];
-        self.index = (self.index + 1) % self.ring_buffer.len();
-        if self.index == self.ring_buffer.index {
-            self.is_done = true;
+        assert_eq!(ring_buffer.len(), 3);
+        assert_eq!(ring_buffer.iter().copied().collect::<Vec<_>>(), [2, 3, 4]);
+    }
+
+    #[test]
+    fn test_pop() {
+        let mut ring_buffer = RingBuffer::new(4);
+        for value in 0..6 {
+            ring_buffer.add(value);
         }
-        return Some(value);
+
+        assert_eq!(ring_buffer.pop_newest(), Some(5));
+        assert_eq!(ring_buffer.pop_oldest(), Some(2));
+        assert_eq!(ring_buffer.newest_mut(), Some(&mut 4));
+        assert_eq!(ring_buffer.iter().copied().collect::<Vec<_>>(), [3, 4]);
     }
 }
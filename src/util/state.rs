@@ -0,0 +1,166 @@
+/// State that can be captured into a snapshot and restored from one later.
+/// Snapshots are only loaded into a system running the same cartridge with
+/// the same options; host-side objects (connected devices, input sources,
+/// user settings) aren't part of the state.
+pub trait SaveState {
+    fn save_state(&self, w: &mut StateWriter);
+    fn load_state(&mut self, r: &mut StateReader) -> Result<(), String>;
+}
+
+/// Serializes state into a flat little-endian byte buffer.
+pub struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self { data: vec![] }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value.into());
+    }
+
+    /// Writes a length-prefixed block of bytes.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u32(bytes.len() as u32);
+        self.data.extend_from_slice(bytes);
+    }
+}
+
+/// Reads state written by a `StateWriter`, in the same order.
+pub struct StateReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Have all bytes been read?
+    pub fn is_done(&self) -> bool {
+        self.pos == self.data.len()
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let Some(bytes) = self.data.get(self.pos..self.pos + N) else {
+            return Err(format!("State ended early at byte {}.", self.pos));
+        };
+        self.pos += N;
+
+        let mut array = [0; N];
+        array.copy_from_slice(bytes);
+        return Ok(array);
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, String> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, String> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    /// Reads a block of bytes written with `write_bytes`.
+    pub fn read_bytes(&mut self) -> Result<&'a [u8], String> {
+        let len = self.read_u32()? as usize;
+        let Some(bytes) = self.data.get(self.pos..self.pos + len) else {
+            return Err(format!("State ended early at byte {}.", self.pos));
+        };
+        self.pos += len;
+
+        return Ok(bytes);
+    }
+
+    /// Reads a block of bytes into `dst`, which must be the same length as
+    /// the block that was written.
+    pub fn read_bytes_into(&mut self, dst: &mut [u8]) -> Result<(), String> {
+        let bytes = self.read_bytes()?;
+        if bytes.len() != dst.len() {
+            return Err(format!(
+                "State block is {} bytes, expected {} bytes.",
+                bytes.len(),
+                dst.len()
+            ));
+        }
+        dst.copy_from_slice(bytes);
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut w = StateWriter::new();
+        w.write_u8(0x12);
+        w.write_u16(0x3456);
+        w.write_u32(0x789A_BCDE);
+        w.write_u64(u64::MAX - 1);
+        w.write_bool(true);
+        w.write_bytes(&[1, 2, 3]);
+        w.write_bytes(&[]);
+        let data = w.into_bytes();
+
+        let mut r = StateReader::new(&data);
+        assert_eq!(r.read_u8(), Ok(0x12));
+        assert_eq!(r.read_u16(), Ok(0x3456));
+        assert_eq!(r.read_u32(), Ok(0x789A_BCDE));
+        assert_eq!(r.read_u64(), Ok(u64::MAX - 1));
+        assert_eq!(r.read_bool(), Ok(true));
+        let mut bytes = [0; 3];
+        assert!(r.read_bytes_into(&mut bytes).is_ok());
+        assert_eq!(bytes, [1, 2, 3]);
+        assert_eq!(r.read_bytes(), Ok(&[][..]));
+        assert!(r.is_done());
+
+        assert!(r.read_u8().is_err());
+    }
+
+    #[test]
+    fn test_block_size_mismatch() {
+        let mut w = StateWriter::new();
+        w.write_bytes(&[1, 2, 3]);
+        let data = w.into_bytes();
+
+        let mut bytes = [0; 4];
+        assert!(StateReader::new(&data).read_bytes_into(&mut bytes).is_err());
+    }
+}